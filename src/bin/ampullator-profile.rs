@@ -1,6 +1,6 @@
 use std::time::Instant;
 
-use ampullator::{GenGraph, UGLowPass, UGSine, UGWhite};
+use ampullator::{GenGraph, UGLowPass, UGSine, UGSum, UGWhite};
 use clap::{Parser, ValueEnum};
 
 const DEFAULT_BUFFER_SIZE: usize = 128;
@@ -19,7 +19,10 @@ enum GraphType {
     SineChain,
     /// White-noise source passed through a series of low-pass filters
     FilteredNoise,
-    /// Run all three graph types in sequence
+    /// Independent sine and low-pass voices mixed by one sum; wide levels suit parallel
+    /// execution
+    ParallelVoices,
+    /// Run every graph type above in sequence
     All,
 }
 
@@ -49,6 +52,16 @@ struct Cli {
     /// Disable parallel execution (run graph types sequentially)
     #[arg(long, default_value_t = false)]
     no_parallel: bool,
+
+    /// Worker threads each graph uses to process independent nodes; compare with 1
+    /// (serial). Implies --no-parallel when above 1
+    #[arg(long, default_value_t = 1)]
+    graph_threads: usize,
+
+    /// Instead of benchmarking, print the JSON definition of each selected graph type
+    /// built at this level
+    #[arg(long)]
+    export: Option<usize>,
 }
 
 // ---------------------------------------------------------------------------
@@ -88,6 +101,22 @@ fn build_filtered_noise(n: usize, sample_rate: f32, buffer_size: usize) -> GenGr
     graph
 }
 
+/// Build `n` independent voices, each a sine oscillator into a low-pass
+/// filter, mixed by a single sum.  Minimum `n` is 2.
+/// Total node count = 2n + 1.
+fn build_parallel_voices(n: usize, sample_rate: f32, buffer_size: usize) -> GenGraph {
+    let n = n.max(2);
+    let mut graph = GenGraph::new(sample_rate, buffer_size);
+    graph.add_node("mix", Box::new(UGSum::new(n)));
+    for i in 0..n {
+        graph.add_node(format!("osc{i}"), Box::new(UGSine::new()));
+        graph.add_node(format!("lpf{i}"), Box::new(UGLowPass::new(12.0)));
+        graph.connect(&format!("osc{i}.wave"), &format!("lpf{i}.in"));
+        graph.connect(&format!("lpf{i}.out"), &format!("mix.in{}", i + 1));
+    }
+    graph
+}
+
 // ---------------------------------------------------------------------------
 // Benchmarking
 // ---------------------------------------------------------------------------
@@ -99,6 +128,7 @@ struct BenchRow {
     buffer_size: usize,
     target_duration: f64,
     performed_duration: f64,
+    graph_threads: usize,
 }
 
 const BENCH_RUNS: usize = 2;
@@ -143,8 +173,10 @@ fn bench_level(
     sample_rate: f32,
     buffer_size: usize,
     duration: f64,
+    graph_threads: usize,
 ) -> BenchRow {
-    let graph = builder(level, sample_rate, buffer_size);
+    let mut graph = builder(level, sample_rate, buffer_size);
+    graph.set_threads(graph_threads);
     let node_count = graph.len();
     let performed = bench_graph(graph, sample_rate, buffer_size, duration);
     BenchRow {
@@ -154,6 +186,7 @@ fn bench_level(
         buffer_size,
         target_duration: duration,
         performed_duration: performed,
+        graph_threads,
     }
 }
 
@@ -164,10 +197,19 @@ fn run_benchmark_sequential(
     sample_rate: f32,
     buffer_size: usize,
     duration: f64,
+    graph_threads: usize,
 ) -> Vec<BenchRow> {
     let mut rows = Vec::new();
     for level in level_sequence() {
-        let row = bench_level(label, builder, level, sample_rate, buffer_size, duration);
+        let row = bench_level(
+            label,
+            builder,
+            level,
+            sample_rate,
+            buffer_size,
+            duration,
+            graph_threads,
+        );
         let exceeded = row.performed_duration > duration;
         rows.push(row);
         if exceeded {
@@ -202,6 +244,7 @@ fn run_benchmark_parallel(
                             sample_rate,
                             buffer_size,
                             duration,
+                            1,
                         )
                     })
                 })
@@ -223,8 +266,15 @@ fn run_benchmark_parallel(
 
 fn print_header() {
     println!(
-        "   {:<18} {:<6} {:<11} {:<8} {:<12} {:<14} {:<7}",
-        "Graph", "Nodes", "SampleRate", "Buffer", "Target", "Performed", "Ratio"
+        "   {:<18} {:<6} {:<11} {:<8} {:<8} {:<12} {:<14} {:<7}",
+        "Graph",
+        "Nodes",
+        "SampleRate",
+        "Buffer",
+        "Threads",
+        "Target",
+        "Performed",
+        "Ratio"
     );
 }
 
@@ -232,11 +282,12 @@ fn print_row(row: &BenchRow) {
     let ratio = row.performed_duration / row.target_duration;
     let status = if ratio >= 1.0 { "⚠️  " } else { "✅ " };
     println!(
-        "{status}{:<18} {:<6} {:<11} {:<7} {:<7.1} {:<14.4} {:<7.2}",
+        "{status}{:<18} {:<6} {:<11} {:<8} {:<8} {:<12.1} {:<14.4} {:<7.2}",
         row.graph_type,
         row.node_count,
         row.sample_rate,
         row.buffer_size,
+        row.graph_threads,
         row.target_duration,
         row.performed_duration,
         ratio,
@@ -257,6 +308,9 @@ fn run(cli: Cli) -> Result<(), String> {
     if !cli.buffer_size.is_multiple_of(8) {
         return Err("--buffer-size must be a non-zero multiple of 8".to_string());
     }
+    if cli.graph_threads == 0 {
+        return Err("--graph-threads must be > 0".to_string());
+    }
 
     let sr = cli.sample_rate;
     let buf = cli.buffer_size;
@@ -270,6 +324,20 @@ fn run(cli: Cli) -> Result<(), String> {
     if matches!(cli.graph_type, GraphType::FilteredNoise | GraphType::All) {
         types_to_run.push(("filtered-noise", build_filtered_noise));
     }
+    if matches!(cli.graph_type, GraphType::ParallelVoices | GraphType::All) {
+        types_to_run.push(("parallel-voices", build_parallel_voices));
+    }
+    if let Some(level) = cli.export {
+        if level == 0 {
+            return Err("--export must be > 0".to_string());
+        }
+        for (_, builder) in &types_to_run {
+            let mut graph = builder(level, sr, buf);
+            println!("{}", graph.to_facade_json()?);
+        }
+        return Ok(());
+    }
+
     let max_threads = std::thread::available_parallelism()
         .map(|n| n.get() / 2)
         .unwrap_or(1)
@@ -277,8 +345,9 @@ fn run(cli: Cli) -> Result<(), String> {
 
     print_header();
     for (label, builder) in &types_to_run {
-        let rows = if cli.no_parallel {
-            run_benchmark_sequential(label, *builder, sr, buf, dur)
+        // levels benchmarked concurrently would compete with the graph's own threads
+        let rows = if cli.no_parallel || cli.graph_threads > 1 {
+            run_benchmark_sequential(label, *builder, sr, buf, dur, cli.graph_threads)
         } else {
             run_benchmark_parallel(label, *builder, sr, buf, dur, max_threads)
         };
@@ -322,6 +391,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_exported_graph_reloads() {
+        let mut g = build_filtered_noise(4, 44_100.0, 128);
+        let json = g.to_facade_json().unwrap();
+        let g2 = ampullator::graph_from_json_definition(&json, 44_100.0, 128).unwrap();
+        assert_eq!(g2.len(), 5);
+    }
+
+    #[test]
+    fn test_parallel_voices_node_count() {
+        for n in [2, 4, 8] {
+            let g = build_parallel_voices(n, 44_100.0, 128);
+            // n oscillators + n LPFs + 1 mix
+            assert_eq!(g.len(), 2 * n + 1, "parallel-voices level {n}");
+        }
+    }
+
     #[test]
     fn test_bench_graph_produces_output() {
         // Just ensure bench_graph runs without panicking and returns a non-negative time.
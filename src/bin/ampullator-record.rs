@@ -3,8 +3,8 @@ use std::io::BufWriter;
 use std::path::PathBuf;
 
 use ampullator::{
-    GenGraph, Recorder, WavFormat, graph_from_chain_expression,
-    graph_from_json_definition,
+    AudioSink, GenGraph, RawSink, Severity, WavFormat, WavPipeSink, WavSink,
+    graph_from_chain_expression, graph_from_json_definition, stream_to_sink,
 };
 use clap::Parser;
 
@@ -13,7 +13,7 @@ const DEFAULT_BUFFER_SIZE: usize = 128;
 #[derive(Parser, Debug)]
 #[command(
     name = "ampullator-record",
-    about = "Record outputs from an Ampullator graph to a multichannel WAV file"
+    about = "Record or stream outputs from an Ampullator graph as multichannel WAV or raw PCM"
 )]
 struct Cli {
     /// Chain DSL expression or path to a text/json graph definition file
@@ -27,7 +27,7 @@ struct Cli {
     #[arg(long, value_delimiter = ',')]
     outputs: Vec<String>,
 
-    /// WAV bit depth (16, 24, or 32)
+    /// Sample bit depth (16, 24, or 32; 32 is float)
     #[arg(long, default_value_t = 16)]
     bit_depth: u16,
 
@@ -35,13 +35,27 @@ struct Cli {
     #[arg(long, default_value_t = 44_100.0)]
     sample_rate: f32,
 
-    /// Duration to record, in seconds
+    /// Duration to record, in seconds; omit to stream until interrupted
     #[arg(long)]
-    duration: f32,
+    duration: Option<f32>,
 
     /// Output WAV file path; omit to stream WAV to stdout
     #[arg(short = 'o', long)]
     output: Option<PathBuf>,
+
+    /// Write headerless interleaved PCM instead of WAV
+    #[arg(long)]
+    raw: bool,
+
+    /// Master seed for stochastic UGens constructed without a seed, making renders
+    /// reproducible
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Report graph warnings and errors without recording; exits with an error if any
+    /// errors are found
+    #[arg(long)]
+    check: bool,
 }
 
 fn build_graph_from_input(
@@ -118,8 +132,25 @@ fn resolve_output_labels(
     Ok(selected)
 }
 
+/// Print the issues of `graph` when rendering `labels`, one per line; fails if any is an
+/// error.
+fn check_graph(graph: &mut GenGraph, labels: &[String]) -> Result<(), String> {
+    let issues = graph.validate(labels)?;
+    for issue in &issues {
+        println!("{issue}");
+    }
+    let errors = issues
+        .iter()
+        .filter(|i| i.severity() == Severity::Error)
+        .count();
+    if errors > 0 {
+        return Err(format!("Graph check found {errors} error(s)"));
+    }
+    Ok(())
+}
+
 fn run(cli: Cli) -> Result<(), String> {
-    if cli.duration <= 0.0 {
+    if cli.duration.is_some_and(|d| d <= 0.0) {
         return Err("duration must be > 0".to_string());
     }
     if cli.sample_rate <= 0.0 {
@@ -127,24 +158,47 @@ fn run(cli: Cli) -> Result<(), String> {
     }
     let mut graph =
         build_graph_from_input(&cli.input, cli.sample_rate, DEFAULT_BUFFER_SIZE)?;
+    if let Some(seed) = cli.seed {
+        graph.set_seed(seed);
+    }
     let labels = resolve_output_labels(&mut graph, cli.node.as_deref(), &cli.outputs)?;
+    if cli.check {
+        return check_graph(&mut graph, &labels);
+    }
+    graph.compile(&labels)?;
+
+    let total_samples = cli.duration.map(|d| (d * cli.sample_rate).round() as usize);
+    let format = WavFormat::try_from(cli.bit_depth)?;
 
-    let recorder = Recorder::from_duration(graph, Some(labels), cli.duration);
-    let format = WavFormat::try_from(cli.bit_depth).expect("invalid bit depth");
-    match cli.output {
+    let (mut sink, dest): (Box<dyn AudioSink>, String) = match cli.output {
         None => {
-            let stdout = std::io::stdout();
-            recorder
-                .to_wav_write(BufWriter::new(stdout.lock()), format)
-                .map_err(|e| format!("Failed to write WAV to stdout: {e}"))?;
+            let w = BufWriter::new(std::io::stdout().lock());
+            let sink: Box<dyn AudioSink> = if cli.raw {
+                Box::new(RawSink::new(w, format))
+            } else {
+                Box::new(WavPipeSink::new(w, format, total_samples))
+            };
+            (sink, "stdout".to_string())
         }
         Some(ref path) => {
-            recorder
-                .to_wav(path, format)
-                .map_err(|e| format!("Failed to write WAV '{}': {e}", path.display()))?;
+            let file = std::fs::File::create(path)
+                .map_err(|e| format!("Failed to create '{}': {e}", path.display()))?;
+            let w = BufWriter::new(file);
+            let sink: Box<dyn AudioSink> = if cli.raw {
+                Box::new(RawSink::new(w, format))
+            } else {
+                Box::new(WavSink::new(w, format))
+            };
+            (sink, format!("'{}'", path.display()))
         }
+    };
+
+    match stream_to_sink(&mut graph, &labels, sink.as_mut(), total_samples) {
+        Ok(_) => Ok(()),
+        // the reader of a pipe (e.g. `aplay`) has exited
+        Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
+        Err(e) => Err(format!("Failed to write to {dest}: {e}")),
     }
-    Ok(())
 }
 
 fn main() {
@@ -198,4 +252,24 @@ mod tests {
             resolve_output_labels(&mut g, Some("c"), &["out".to_string()]).unwrap();
         assert_eq!(labels, vec!["c.out".to_string()]);
     }
+
+    #[test]
+    fn test_check_graph() {
+        let mut g = build_graph_from_input(
+            "Sine(@freq=2) => a | Sine(@freq=3) => b",
+            44_100.0,
+            128,
+        )
+        .unwrap();
+        let labels = resolve_output_labels(&mut g, Some("b"), &[]).unwrap();
+        // a dangling node is only a warning
+        assert!(check_graph(&mut g, &labels).is_ok());
+
+        let mut g = build_graph_from_input("AsHz() => h", 44_100.0, 128).unwrap();
+        let labels = resolve_output_labels(&mut g, None, &[]).unwrap();
+        assert_eq!(
+            check_graph(&mut g, &labels),
+            Err("Graph check found 1 error(s)".to_string())
+        );
+    }
 }
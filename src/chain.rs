@@ -8,19 +8,31 @@
 ///
 /// ```text
 /// chain            = segment ("|" segment)*
-/// segment          = addmul_expr
-/// addmul_expr      = arrow_chain (("+" | "*" | "^") arrow_chain)*
-/// arrow_chain      = named_atom (("->" port_spec? | "&>" multi_port_spec?) named_atom)*
+/// segment          = def | port_decl | addmul_expr
+/// def              = "def" Ident ("(" (param ("," param)*)? ")")? "{" chain "}"
+/// param            = Ident ("=" value)?
+/// port_decl        = ("in" | "out") Ident "=" Ident (":" Ident)?
+/// addmul_expr      = mul_expr (("+" | "-") mul_expr)*
+/// mul_expr         = arrow_chain (("*" | "/" | "%" | "^") arrow_chain)*
+/// arrow_chain      = named_atom (("->" port_spec? | "~>" port_spec?
+///                    | "&>" multi_port_spec?) named_atom)*
 /// named_atom       = atom ("=>" Ident)?
-/// atom             = ugen_call | Ident | Number | "(" addmul_expr ")"
-/// ugen_call        = Ident ("(" args ")")?
+/// atom             = "-" atom | ugen_call | macro_call | Ident | Number
+///                    | "(" addmul_expr ")"
+/// ugen_call        = Ident ("(" args ")")? ("{" ugen_call "}")?
+/// macro_call       = Ident ("(" args ")")?
 /// args             = (arg_pair ("," arg_pair)*)?
-/// arg_pair         = Ident "=" (Number | Ident)
+/// arg_pair         = "@"? Ident "=" value
+/// value            = Number | Ident | String | list
 /// port_spec        = (Ident)? ":" (Ident)?
 /// multi_port_spec  = portpair ("," portpair)*
 /// portpair         = (Ident)? ":" (Ident)?
 /// ```
 ///
+/// Whitespace separates tokens, and `#` starts a comment that runs to the end
+/// of the line. An error is reported at its line and column, with the source
+/// line and a `^` caret under the offending text.
+///
 /// The `&>` operator connects multiple outputs of the source to multiple inputs
 /// of the destination in contiguous order.  Without a port spec all outputs of
 /// the source (which must have more than one) are connected to the first N
@@ -28,7 +40,42 @@
 /// `src_out:dst_in` is connected individually; omitting the output name in a
 /// pair defaults to the n-th contiguous output of the source, and omitting the
 /// input name defaults to the n-th contiguous input of the destination.
+///
+/// A `def` segment defines a macro: a reusable sub-chain with parameters. A
+/// call such as `Voice(freq=220) => v1` expands the body with each parameter
+/// identifier replaced by its value (in argument values and as numeric atoms),
+/// registering every node under the `v1.` namespace (e.g. `v1.osc`). Inside a
+/// body, `in name = node:port` and `out name = node:port` declare the ports the
+/// instance exposes; connections to `v1` use these (the first declared is the
+/// default). A `def` needs no `|` after its closing `}`.
+///
+/// A UGen that wraps another, such as `Oversample`, takes the wrapped UGen in
+/// braces after its arguments: `Oversample(factor=4) { BassDrum() }`.
+///
+/// A string literal is written in double quotes, with `\"`, `\\`, `\n`, `\t`
+/// and `\r` escapes; it passes text, such as a file path, as an argument
+/// value: `Sample(path="drums/kick 1.wav")`. An identifier is also read as
+/// text (`mode=Midi`), unless it names a macro parameter.
+///
+/// An argument named `@input` sets the default of that input rather than a
+/// construction argument: `Sine(@freq=220)` reads 220 on `freq` while it is
+/// unconnected, without a `Const` node.
+///
+/// A list literal given to an `@input` expands the UGen to one node per
+/// channel: `Sine(@freq=[220, 330]) => osc` registers `osc.0` and `osc.1`
+/// (shorter lists wrap around). A UGen after a multichannel `->`, or an
+/// operator with a multichannel operand, is replicated in the same way and
+/// connected channel by channel; a single-channel source is connected to
+/// every channel. A new UGen after `&>` with fewer inputs than the source has
+/// outputs is replicated per output.
+///
+/// The `~>` operator is like `->` but creates a feedback connection: the
+/// destination reads the source output from the previous block, so it may
+/// close a loop back to an already-named node (e.g. `osc ~>:phase osc`).
 use std::collections::HashMap;
+use std::rc::Rc;
+
+use strum::VariantNames;
 
 use crate::graph_facade::Facade;
 use crate::graph_facade::UGFacade;
@@ -42,176 +89,498 @@ enum Token {
     Arrow,         // ->
     FatArrow,      // =>
     SnakeArrow,    // &>
+    TildeArrow,    // ~>
     Colon,         // :
     LParen,        // (
     RParen,        // )
     Comma,         // ,
     Assign,        // =
     Plus,          // +
+    Minus,         // -
     Star,          // *
+    Slash,         // /
+    Percent,       // %
     Caret,         // ^
     LBracket,      // [
     RBracket,      // ]
+    LBrace,        // {
+    RBrace,        // }
+    At,            // @
     Ident(String), // identifier
     Number(f32),   // numeric literal
+    Str(String),   // string literal, unescaped
 }
 
-fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+/// A byte range of the source string.
+type Span = std::ops::Range<usize>;
+
+/// A token with the byte range of the source it was read from.
+#[derive(Debug, Clone, PartialEq)]
+struct Spanned {
+    token: Token,
+    span: Span,
+}
+
+impl Token {
+    /// Returns `true` if the token can end an operand, so that a `-` after it
+    /// is a subtraction rather than the sign of a number.
+    fn ends_operand(&self) -> bool {
+        matches!(
+            self,
+            Token::Ident(_)
+                | Token::Number(_)
+                | Token::Str(_)
+                | Token::RParen
+                | Token::RBracket
+                | Token::RBrace
+        )
+    }
+}
+
+/// Split `input` into tokens. On failure, returns the error message and the
+/// span of the offending text.
+fn tokenize(input: &str) -> Result<Vec<Spanned>, (String, Span)> {
     let chars: Vec<char> = input.chars().collect();
+    // byte offset of each char, and of the end of input
+    let offsets: Vec<usize> = input
+        .char_indices()
+        .map(|(b, _)| b)
+        .chain(std::iter::once(input.len()))
+        .collect();
     let mut tokens = Vec::new();
     let mut i = 0;
 
     while i < chars.len() {
-        match chars[i] {
+        let start = i;
+        let token = match chars[i] {
             c if c.is_ascii_whitespace() => {
                 i += 1;
+                continue;
+            }
+            // Line comment, to the end of the line
+            '#' => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                continue;
             }
             '|' => {
-                tokens.push(Token::Pipe);
                 i += 1;
+                Token::Pipe
             }
             '(' => {
-                tokens.push(Token::LParen);
                 i += 1;
+                Token::LParen
             }
             ')' => {
-                tokens.push(Token::RParen);
                 i += 1;
+                Token::RParen
             }
             ',' => {
-                tokens.push(Token::Comma);
                 i += 1;
+                Token::Comma
             }
             ':' => {
-                tokens.push(Token::Colon);
                 i += 1;
+                Token::Colon
             }
             '+' => {
-                tokens.push(Token::Plus);
                 i += 1;
+                Token::Plus
             }
             '*' => {
-                tokens.push(Token::Star);
                 i += 1;
+                Token::Star
+            }
+            '/' => {
+                i += 1;
+                Token::Slash
+            }
+            '%' => {
+                i += 1;
+                Token::Percent
             }
             '^' => {
-                tokens.push(Token::Caret);
                 i += 1;
+                Token::Caret
             }
             '[' => {
-                tokens.push(Token::LBracket);
                 i += 1;
+                Token::LBracket
             }
             ']' => {
-                tokens.push(Token::RBracket);
                 i += 1;
+                Token::RBracket
+            }
+            '{' => {
+                i += 1;
+                Token::LBrace
+            }
+            '}' => {
+                i += 1;
+                Token::RBrace
+            }
+            '@' => {
+                i += 1;
+                Token::At
             }
             '-' if i + 1 < chars.len() && chars[i + 1] == '>' => {
-                tokens.push(Token::Arrow);
                 i += 2;
+                Token::Arrow
             }
             '&' if i + 1 < chars.len() && chars[i + 1] == '>' => {
-                tokens.push(Token::SnakeArrow);
                 i += 2;
+                Token::SnakeArrow
+            }
+            '~' if i + 1 < chars.len() && chars[i + 1] == '>' => {
+                i += 2;
+                Token::TildeArrow
             }
             '=' if i + 1 < chars.len() && chars[i + 1] == '>' => {
-                tokens.push(Token::FatArrow);
                 i += 2;
+                Token::FatArrow
             }
             '=' => {
-                tokens.push(Token::Assign);
                 i += 1;
+                Token::Assign
             }
             // Number starting with a digit or a lone '.'
             c if c.is_ascii_digit() || c == '.' => {
-                let start = i;
                 while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
                     i += 1;
                 }
                 let num_str: String = chars[start..i].iter().collect();
-                let n: f32 = num_str
-                    .parse()
-                    .map_err(|_| format!("Invalid number: '{num_str}'"))?;
-                tokens.push(Token::Number(n));
+                let n: f32 = num_str.parse().map_err(|_| {
+                    (
+                        format!("Invalid number: '{num_str}'"),
+                        offsets[start]..offsets[i],
+                    )
+                })?;
+                Token::Number(n)
             }
-            // Negative number: '-' followed by digit (and NOT '>')
-            '-' if i + 1 < chars.len() && chars[i + 1].is_ascii_digit() => {
-                let start = i;
+            // Negative number: '-' followed by digit (and NOT '>'), unless it
+            // follows an operand, as in `lfo -1`, where it is a subtraction
+            '-' if i + 1 < chars.len()
+                && chars[i + 1].is_ascii_digit()
+                && !tokens
+                    .last()
+                    .is_some_and(|t: &Spanned| t.token.ends_operand()) =>
+            {
                 i += 1; // consume '-'
                 while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
                     i += 1;
                 }
                 let num_str: String = chars[start..i].iter().collect();
-                let n: f32 = num_str
-                    .parse()
-                    .map_err(|_| format!("Invalid number: '{num_str}'"))?;
-                tokens.push(Token::Number(n));
+                let n: f32 = num_str.parse().map_err(|_| {
+                    (
+                        format!("Invalid number: '{num_str}'"),
+                        offsets[start]..offsets[i],
+                    )
+                })?;
+                Token::Number(n)
+            }
+            // String literal, with `\"`, `\\`, `\n`, `\t` and `\r` escapes
+            '"' => {
+                let unterminated = || {
+                    (
+                        "Unterminated string".to_string(),
+                        offsets[start]..input.len(),
+                    )
+                };
+                let mut s = String::new();
+                i += 1; // consume '"'
+                loop {
+                    match chars.get(i) {
+                        Some('"') => break,
+                        Some('\\') => {
+                            s.push(match chars.get(i + 1) {
+                                Some('"') => '"',
+                                Some('\\') => '\\',
+                                Some('n') => '\n',
+                                Some('t') => '\t',
+                                Some('r') => '\r',
+                                Some(c) => {
+                                    return Err((
+                                        format!("Unknown escape '\\{c}'"),
+                                        offsets[i]..offsets[i + 2],
+                                    ));
+                                }
+                                None => return Err(unterminated()),
+                            });
+                            i += 2;
+                        }
+                        Some(c) => {
+                            s.push(*c);
+                            i += 1;
+                        }
+                        None => return Err(unterminated()),
+                    }
+                }
+                i += 1; // consume '"'
+                Token::Str(s)
             }
             // Identifier
             c if c.is_alphabetic() || c == '_' => {
-                let start = i;
-                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                // '.' allows references to nodes inside a macro instance, e.g. `v1.osc`
+                while i < chars.len()
+                    && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '.')
+                {
                     i += 1;
                 }
-                let ident: String = chars[start..i].iter().collect();
-                tokens.push(Token::Ident(ident));
+                Token::Ident(chars[start..i].iter().collect())
+            }
+            '-' => {
+                i += 1;
+                Token::Minus
             }
             c => {
-                return Err(format!("Unexpected character: '{c}' at position {i}"));
+                return Err((
+                    format!("Unexpected character: '{c}'"),
+                    offsets[i]..offsets[i + 1],
+                ));
             }
-        }
+        };
+        tokens.push(Spanned {
+            token,
+            span: offsets[start]..offsets[i],
+        });
     }
 
     Ok(tokens)
 }
 
+/// Prefix `message` with the line and column (both from 1) at which `span`
+/// starts in `source`, and append that source line with the span underlined.
+fn locate(source: &str, span: &Span, message: &str) -> String {
+    let line_start = source[..span.start].rfind('\n').map_or(0, |b| b + 1);
+    let line_end = source[span.start..]
+        .find('\n')
+        .map_or(source.len(), |b| span.start + b);
+    let line = source[..span.start].matches('\n').count() + 1;
+    let column = source[line_start..span.start].chars().count() + 1;
+    // keep tabs so the caret lines up with the text above it
+    let indent: String = source[line_start..span.start]
+        .chars()
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let width = source[span.start..span.end.min(line_end)]
+        .chars()
+        .count()
+        .max(1);
+    let text = source[line_start..line_end].trim_end_matches('\r');
+    let gutter = " ".repeat(line.to_string().len());
+    format!(
+        "{line}:{column}: {message}\n{gutter} |\n{line} | {text}\n{gutter} | {indent}{}",
+        "^".repeat(width)
+    )
+}
+
+/// The candidate closest to `name` by edit distance, if it is close enough to be a likely
+/// misspelling.
+fn closest<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let limit = (name.chars().count() / 3).max(1);
+    candidates
+        .map(|c| (edit_distance(name, c), c))
+        .filter(|(d, _)| *d <= limit)
+        .min()
+        .map(|(_, c)| c)
+}
+
+/// The Levenshtein distance between `a` and `b`, in chars.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Write text as a Chain DSL argument value: unchanged if it reads back as the same
+/// identifier, else as a quoted string literal.
+pub(crate) fn quote_value(s: &str) -> String {
+    if matches!(tokenize(s).as_deref(), Ok([Spanned { token: Token::Ident(ident), .. }]) if ident == s)
+    {
+        return s.to_string();
+    }
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
 // ---------------------------------------------------------------------------
 // Parser
 
 /// Result of parsing an atom: either a new node not yet registered
 /// (Pending) or an already-registered name (Registered).
+/// A UGen call expanded to several channels (Group) is registered as one node
+/// per channel. A macro call (Macro) is expanded once its instance name is known.
 enum Atom {
-    Pending { facade: Facade, fallback: String },
+    Pending {
+        facade: Facade,
+        fallback: String,
+    },
+    Group {
+        facades: Vec<Facade>,
+        fallback: String,
+    },
     Registered(String),
+    Macro {
+        def: String,
+        args: Args,
+        fallback: String,
+    },
+}
+
+type Args = HashMap<String, serde_json::Value>;
+
+/// Maximum nesting of macro expansions, guarding against recursive `def`s.
+const MAX_MACRO_DEPTH: usize = 16;
+
+/// Multichannel expansion: if any `@input` argument is a list literal, the
+/// arguments of each channel, taking the `k`-th item of each list (lists
+/// shorter than the longest wrap around). Other arguments are the same on
+/// every channel. Returns `None` if there are no lists.
+fn expand_channels(args: &Args) -> Result<Option<Vec<Args>>, String> {
+    let mut channels = None;
+    for (key, value) in args {
+        if let (true, Some(items)) = (key.starts_with('@'), value.as_array()) {
+            if items.is_empty() {
+                return Err(format!("Input default '{key}' is an empty list"));
+            }
+            channels = Some(channels.unwrap_or(0).max(items.len()));
+        }
+    }
+    let Some(channels) = channels else {
+        return Ok(None);
+    };
+    let expanded = (0..channels)
+        .map(|k| {
+            args.iter()
+                .map(|(key, value)| match value.as_array() {
+                    Some(items) if key.starts_with('@') => {
+                        (key.clone(), items[k % items.len()].clone())
+                    }
+                    _ => (key.clone(), value.clone()),
+                })
+                .collect()
+        })
+        .collect();
+    Ok(Some(expanded))
+}
+
+/// A `def` macro: parameters (with optional defaults) and its unparsed body,
+/// with the source it was defined in.
+#[derive(Debug, Clone)]
+struct MacroDef {
+    params: Vec<(String, Option<serde_json::Value>)>,
+    body: Vec<Spanned>,
+    source: Rc<str>,
+}
+
+/// The ports a macro exposes, as (port name, node label) pairs in declaration order.
+#[derive(Debug, Default)]
+struct MacroPorts {
+    inputs: Vec<(String, String)>,
+    outputs: Vec<(String, String)>,
 }
 
 struct ChainParser {
-    tokens: Vec<Token>,
+    source: Rc<str>,
+    tokens: Vec<Spanned>,
     pos: usize,
+    // Span of the last consumed token, where an error is reported
+    last: Span,
+    // Set once an error has been located in a macro body
+    located: bool,
     pub register: HashMap<String, Facade>,
     pub connect: Vec<(String, String)>,
+    pub feedback: Vec<(String, String)>,
     counter: usize,
+    defs: HashMap<String, MacroDef>,
+    // Expanded macro instances by name, with their exposed ports
+    instances: HashMap<String, MacroPorts>,
+    // Parameter values while expanding a macro body
+    params: Args,
+    // Ports declared with `in`/`out` while expanding a macro body
+    ports: MacroPorts,
+    // Multichannel groups by name, with their channel count; channel `k` is the node
+    // `name.k`
+    groups: HashMap<String, usize>,
+    depth: usize,
 }
 
 impl ChainParser {
-    fn new(tokens: Vec<Token>) -> Self {
+    fn new(source: Rc<str>, tokens: Vec<Spanned>) -> Self {
         Self {
+            source,
             tokens,
             pos: 0,
+            last: 0..0,
+            located: false,
             register: HashMap::new(),
             connect: Vec::new(),
+            feedback: Vec::new(),
             counter: 0,
+            defs: HashMap::new(),
+            instances: HashMap::new(),
+            params: HashMap::new(),
+            ports: MacroPorts::default(),
+            groups: HashMap::new(),
+            depth: 0,
         }
     }
 
+    /// Tokenize `source` into a new parser.
+    fn from_source(source: &str) -> Result<Self, String> {
+        let tokens = tokenize(source).map_err(|(e, span)| locate(source, &span, &e))?;
+        Ok(Self::new(Rc::from(source), tokens))
+    }
+
     fn peek(&self) -> Option<&Token> {
-        self.tokens.get(self.pos)
+        self.tokens.get(self.pos).map(|t| &t.token)
     }
 
     fn peek_at(&self, offset: usize) -> Option<&Token> {
-        self.tokens.get(self.pos + offset)
+        self.tokens.get(self.pos + offset).map(|t| &t.token)
     }
 
     fn consume(&mut self) -> Option<Token> {
         if self.pos < self.tokens.len() {
             let t = self.tokens[self.pos].clone();
             self.pos += 1;
-            Some(t)
+            self.last = t.span;
+            Some(t.token)
         } else {
+            // at the end of input, errors point just past the last token
+            let end = self.tokens.last().map_or(0, |t| t.span.end);
+            self.last = end..end;
             None
         }
     }
 
+    /// Consume the next token and return an error that it is not `expected`.
+    fn unexpected(&mut self, expected: &str) -> String {
+        let t = self.consume();
+        format!("Expected {expected}, got {t:?}")
+    }
+
     fn gen_name(&mut self, prefix: &str) -> String {
         self.counter += 1;
         let mut h: u64 = 0xcbf29ce484222325;
@@ -230,12 +599,74 @@ impl ChainParser {
         }
     }
 
+    /// Resolve `node.port` to a label, mapping the exposed ports of macro instances.
+    ///
+    /// With no `port`, the default output (or input) is used.
+    fn port_label(
+        &self,
+        node: &str,
+        port: Option<&str>,
+        output: bool,
+    ) -> Result<String, String> {
+        if let Some(n) = self.groups.get(node) {
+            return Err(format!(
+                "'{node}' has {n} channels; name one channel, as in '{node}.0'"
+            ));
+        }
+        if let Some(ports) = self.instances.get(node) {
+            let (list, kind) = if output {
+                (&ports.outputs, "output")
+            } else {
+                (&ports.inputs, "input")
+            };
+            let found = match port {
+                Some(port) => list.iter().find(|(name, _)| name == port),
+                None => list.first(),
+            };
+            return found
+                .map(|(_, label)| label.clone())
+                .ok_or_else(|| match port {
+                    Some(port) => {
+                        format!("Macro instance '{node}' has no {kind} '{port}'")
+                    }
+                    None => format!("Macro instance '{node}' has no {kind}s"),
+                });
+        }
+        match (port, output) {
+            (Some(port), _) => Ok(format!("{node}.{port}")),
+            (None, true) => self.default_output(node),
+            (None, false) => self.default_input(node),
+        }
+    }
+
+    /// All output (or input) port names of a node or macro instance.
+    fn port_names(&self, node: &str, output: bool) -> Result<Vec<String>, String> {
+        if let Some(ports) = self.instances.get(node) {
+            let list = if output {
+                &ports.outputs
+            } else {
+                &ports.inputs
+            };
+            return Ok(list.iter().map(|(name, _)| name.clone()).collect());
+        }
+        let facade = self
+            .register
+            .get(node)
+            .ok_or_else(|| format!("Unknown node: '{node}'"))?;
+        let ugen = facade.try_to_ugen()?;
+        Ok(if output {
+            ugen.output_names().to_vec()
+        } else {
+            ugen.input_names().to_vec()
+        })
+    }
+
     fn default_output(&self, name: &str) -> Result<String, String> {
         let facade = self
             .register
             .get(name)
             .ok_or_else(|| format!("Unknown node: '{name}'"))?;
-        let ugen = facade.to_ugen();
+        let ugen = facade.try_to_ugen()?;
         let port = ugen
             .first_output()
             .ok_or_else(|| format!("UGen '{name}' has no outputs"))?;
@@ -247,30 +678,57 @@ impl ChainParser {
             .register
             .get(name)
             .ok_or_else(|| format!("Unknown node: '{name}'"))?;
-        let ugen = facade.to_ugen();
+        let ugen = facade.try_to_ugen()?;
         let port = ugen
             .first_input()
             .ok_or_else(|| format!("UGen '{name}' has no inputs"))?;
         Ok(format!("{name}.{port}"))
     }
 
-    /// Rename an already-registered node, updating all connect entries.
+    /// Rename an already-registered node or macro instance, updating all connect entries.
     fn rename(&mut self, old: &str, new_name: &str) {
         if old == new_name {
             return;
         }
-        if let Some(facade) = self.register.remove(old) {
-            self.register.insert(new_name.to_string(), facade);
-            let old_prefix = format!("{old}.");
-            let new_prefix = format!("{new_name}.");
-            for (src, dst) in &mut self.connect {
-                if let Some(port) = src.strip_prefix(&old_prefix) {
-                    *src = format!("{new_prefix}{port}");
-                }
-                if let Some(port) = dst.strip_prefix(&old_prefix) {
-                    *dst = format!("{new_prefix}{port}");
-                }
+        let old_prefix = format!("{old}.");
+        let new_prefix = format!("{new_name}.");
+        let relabel = |label: &mut String| {
+            if let Some(rest) = label.strip_prefix(&old_prefix) {
+                *label = format!("{new_prefix}{rest}");
             }
+        };
+        if let Some(mut ports) = self.instances.remove(old) {
+            // a macro instance owns every node under its `old.` namespace
+            let names: Vec<String> = self
+                .register
+                .keys()
+                .filter(|k| k.starts_with(&old_prefix))
+                .cloned()
+                .collect();
+            for name in names {
+                let facade = self.register.remove(&name).unwrap();
+                let mut renamed = name;
+                relabel(&mut renamed);
+                self.register.insert(renamed, facade);
+            }
+            for (_, label) in ports.inputs.iter_mut().chain(ports.outputs.iter_mut()) {
+                relabel(label);
+            }
+            self.instances.insert(new_name.to_string(), ports);
+        } else if let Some(n) = self.groups.remove(old) {
+            for k in 0..n {
+                let facade = self.register.remove(&format!("{old}.{k}")).unwrap();
+                self.register.insert(format!("{new_name}.{k}"), facade);
+            }
+            self.groups.insert(new_name.to_string(), n);
+        } else if let Some(facade) = self.register.remove(old) {
+            self.register.insert(new_name.to_string(), facade);
+        } else {
+            return;
+        }
+        for (src, dst) in self.connect.iter_mut().chain(self.feedback.iter_mut()) {
+            relabel(src);
+            relabel(dst);
         }
     }
 
@@ -282,8 +740,7 @@ impl ChainParser {
             serde_json::Value::String(type_name.to_string()),
             serde_json::Value::Object(obj),
         ]);
-        serde_json::from_value(json_val)
-            .map(Facade::Full)
+        Facade::from_value(json_val)
             .map_err(|e| format!("Failed to parse UGen '{type_name}': {e}"))
     }
 
@@ -330,8 +787,9 @@ impl ChainParser {
                         items.push(v);
                     }
                     Some(Token::Ident(s)) => {
-                        items.push(serde_json::Value::String(s));
+                        items.push(self.ident_value(s));
                     }
+                    Some(Token::Str(s)) => items.push(serde_json::Value::String(s)),
                     t => return Err(format!("Expected list element, got {t:?}")),
                 }
                 match self.peek() {
@@ -339,7 +797,7 @@ impl ChainParser {
                         self.consume();
                     }
                     Some(Token::RBracket) => break,
-                    t => return Err(format!("Expected ',' or ']', got {t:?}")),
+                    _ => return Err(self.unexpected("',' or ']'")),
                 }
             }
         }
@@ -347,6 +805,36 @@ impl ChainParser {
         Ok(serde_json::Value::Array(items))
     }
 
+    /// An identifier used as a value: a macro parameter's value, or else the identifier
+    /// as a string.
+    fn ident_value(&self, s: String) -> serde_json::Value {
+        match self.params.get(&s) {
+            Some(v) => v.clone(),
+            None => serde_json::Value::String(s),
+        }
+    }
+
+    /// Parse an argument value: a number, an identifier, a string, or a list.
+    fn parse_value(&mut self) -> Result<serde_json::Value, String> {
+        if self.peek() == Some(&Token::LBracket) {
+            return self.parse_list();
+        }
+        match self.consume() {
+            Some(Token::Number(n)) => {
+                if n.fract() == 0.0 && n >= i64::MIN as f32 && n <= i64::MAX as f32 {
+                    Ok(serde_json::Value::Number((n as i64).into()))
+                } else {
+                    Ok(serde_json::Number::from_f64(n as f64)
+                        .map(serde_json::Value::Number)
+                        .unwrap_or(serde_json::Value::String(format!("{n}"))))
+                }
+            }
+            Some(Token::Ident(s)) => Ok(self.ident_value(s)),
+            Some(Token::Str(s)) => Ok(serde_json::Value::String(s)),
+            t => Err(format!("Expected argument value, got {t:?}")),
+        }
+    }
+
     /// Parse keyword args inside `(…)`.  Returns key → JSON value pairs.
     fn parse_args(&mut self) -> Result<HashMap<String, serde_json::Value>, String> {
         let mut args = HashMap::new();
@@ -356,28 +844,15 @@ impl ChainParser {
         loop {
             let key = match self.consume() {
                 Some(Token::Ident(s)) => s,
+                // `@input=value` sets an input default
+                Some(Token::At) => match self.consume() {
+                    Some(Token::Ident(s)) => format!("@{s}"),
+                    t => return Err(format!("Expected input name after '@', got {t:?}")),
+                },
                 t => return Err(format!("Expected argument name, got {t:?}")),
             };
             self.expect(&Token::Assign)?;
-            let value = match self.peek() {
-                Some(Token::LBracket) => self.parse_list()?,
-                _ => match self.consume() {
-                    Some(Token::Number(n)) => {
-                        if n.fract() == 0.0
-                            && n >= i64::MIN as f32
-                            && n <= i64::MAX as f32
-                        {
-                            serde_json::Value::Number((n as i64).into())
-                        } else {
-                            serde_json::Number::from_f64(n as f64)
-                                .map(serde_json::Value::Number)
-                                .unwrap_or(serde_json::Value::String(format!("{n}")))
-                        }
-                    }
-                    Some(Token::Ident(s)) => serde_json::Value::String(s),
-                    t => return Err(format!("Expected argument value, got {t:?}")),
-                },
-            };
+            let value = self.parse_value()?;
             args.insert(key, value);
 
             match self.peek() {
@@ -385,23 +860,49 @@ impl ChainParser {
                     self.consume();
                 }
                 Some(Token::RParen) | None => break,
-                t => {
-                    return Err(format!("Expected ',' or ')', got {t:?}"));
-                }
+                _ => return Err(self.unexpected("',' or ')'")),
             }
         }
         Ok(args)
     }
 
-    /// Parse a UGen call and return the facade and a generated fallback name,
-    /// but do not register it yet — the caller decides the final name.
-    fn parse_ugen_call(&mut self) -> Result<(Facade, String), String> {
+    /// Parse a UGen call, but do not register it yet — the caller decides the
+    /// final name. A list literal given to an `@input` expands the call to one
+    /// node per channel (a `Group`).
+    fn parse_ugen_call(&mut self) -> Result<Atom, String> {
+        let start = self.tokens[self.pos].span.start;
+        let (type_name, args) = self.parse_ugen_args()?;
+        let fallback = self.gen_name(&type_name);
+        // an invalid argument is reported at the whole call
+        Self::ugen_atom(&type_name, &args, fallback)
+            .inspect_err(|_| self.last = start..self.last.end)
+    }
+
+    /// Build the atom of a UGen call from its type name and arguments.
+    fn ugen_atom(type_name: &str, args: &Args, fallback: String) -> Result<Atom, String> {
+        match expand_channels(args)? {
+            None => Ok(Atom::Pending {
+                facade: Self::make_facade(type_name, args)?,
+                fallback,
+            }),
+            Some(channels) => Ok(Atom::Group {
+                facades: channels
+                    .iter()
+                    .map(|args| Self::make_facade(type_name, args))
+                    .collect::<Result<_, _>>()?,
+                fallback,
+            }),
+        }
+    }
+
+    /// Parse a UGen call into its type name and arguments.
+    fn parse_ugen_args(&mut self) -> Result<(String, Args), String> {
         let type_name = match self.consume() {
             Some(Token::Ident(s)) => s,
             t => return Err(format!("Expected UGen type name, got {t:?}")),
         };
 
-        let args = if self.peek() == Some(&Token::LParen) {
+        let mut args = if self.peek() == Some(&Token::LParen) {
             self.consume(); // consume '('
             let args = self.parse_args()?;
             self.expect(&Token::RParen)?;
@@ -410,9 +911,38 @@ impl ChainParser {
             HashMap::new()
         };
 
-        let facade = Self::make_facade(&type_name, &args)?;
-        let fallback = self.gen_name(&type_name);
-        Ok((facade, fallback))
+        // a wrapper UGen such as `Oversample(factor=4) { BassDrum() }`
+        if self.peek() == Some(&Token::LBrace) {
+            self.consume(); // consume '{'
+            let (inner_type, mut inner_args) = match self.peek() {
+                Some(Token::Ident(s)) if UGFacade::is_variant_name(s) => {
+                    self.parse_ugen_args()?
+                }
+                _ => {
+                    return Err(self.unexpected(&format!(
+                        "a UGen inside '{{ }}' after {type_name}"
+                    )));
+                }
+            };
+            self.expect(&Token::RBrace)?;
+            // the wrapper has the wrapped UGen's inputs, so its input defaults move to
+            // the wrapper
+            let input_keys: Vec<String> = inner_args
+                .keys()
+                .filter(|k| k.starts_with('@'))
+                .cloned()
+                .collect();
+            for key in input_keys {
+                let value = inner_args.remove(&key).expect("key is present");
+                args.entry(key).or_insert(value);
+            }
+            let inner = match Self::make_facade(&inner_type, &inner_args)? {
+                Facade::Full(facade, _) => facade.to_value(),
+                Facade::Short(_) => unreachable!("a UGen call is a full facade"),
+            };
+            args.insert("ugen".to_string(), inner);
+        }
+        Ok((type_name, args))
     }
 
     /// Attempt to read a port name (an identifier that is not a UGen type) and
@@ -495,10 +1025,7 @@ impl ChainParser {
     /// parsing a `multi_port_spec`.
     fn peek_is_port_pair_at(&self, ahead: usize) -> bool {
         matches!(
-            (
-                self.tokens.get(self.pos + ahead),
-                self.tokens.get(self.pos + ahead + 1)
-            ),
+            (self.peek_at(ahead), self.peek_at(ahead + 1)),
             (Some(Token::Colon), _) | (Some(Token::Ident(_)), Some(Token::Colon))
         )
     }
@@ -522,52 +1049,120 @@ impl ChainParser {
             }
             Some(Token::Ident(id)) => {
                 let id = id.clone();
-                if UGFacade::is_variant_name(&id) {
-                    let (facade, fallback) = self.parse_ugen_call()?;
-                    Ok(Atom::Pending { facade, fallback })
+                if let Some(value) = self.params.get(&id) {
+                    let n = value
+                        .as_f64()
+                        .ok_or_else(|| format!("Parameter '{id}' is not a number"))?;
+                    self.consume();
+                    let fallback = self.gen_name("const");
+                    Ok(Atom::Pending {
+                        facade: Facade::Short(n as f32),
+                        fallback,
+                    })
+                } else if self.defs.contains_key(&id) {
+                    self.consume();
+                    let args = if self.peek() == Some(&Token::LParen) {
+                        self.consume(); // consume '('
+                        let args = self.parse_args()?;
+                        self.expect(&Token::RParen)?;
+                        args
+                    } else {
+                        HashMap::new()
+                    };
+                    let fallback = self.gen_name(&id);
+                    Ok(Atom::Macro {
+                        def: id,
+                        args,
+                        fallback,
+                    })
+                } else if UGFacade::is_variant_name(&id) {
+                    self.parse_ugen_call()
                 } else {
                     self.consume();
-                    if !self.register.contains_key(&id) {
-                        return Err(format!("Unknown name reference: '{id}'"));
+                    if !self.register.contains_key(&id)
+                        && !self.instances.contains_key(&id)
+                        && !self.groups.contains_key(&id)
+                    {
+                        return Err(self.unknown_name(&id));
                     }
                     Ok(Atom::Registered(id))
                 }
             }
+            Some(Token::Minus) => {
+                self.consume(); // consume '-'
+                match self.parse_atom()? {
+                    // a negated number is a negative constant
+                    Atom::Pending {
+                        facade: Facade::Short(n),
+                        fallback,
+                    } => Ok(Atom::Pending {
+                        facade: Facade::Short(-n),
+                        fallback,
+                    }),
+                    atom => {
+                        let operand = self.register_atom(atom, None, 1)?;
+                        let name = self.register_op("Neg", self.width(&operand))?;
+                        self.connect_channels(&operand, None, &name, Some("in"), false)?;
+                        Ok(Atom::Registered(name))
+                    }
+                }
+            }
             Some(Token::LParen) => {
                 self.consume(); // consume '('
                 let result = self.parse_addmul_expr()?;
                 self.expect(&Token::RParen)?;
                 Ok(Atom::Registered(result))
             }
-            t => Err(format!(
-                "Expected atom (UGen, name, number, or '('), got {t:?}"
-            )),
+            _ => Err(self.unexpected("atom (UGen, name, number, or '(')")),
         }
     }
 
-    /// Parse `atom ("=>" Ident)?`.
-    /// For pending atoms, registers under the alias if `=>` is present,
-    /// otherwise under the generated fallback name.
-    /// For already-registered atoms, renames if `=>` is present.
+    /// Parse `atom ("=>" Ident)?` and register the atom; see `register_atom`.
     fn parse_named_atom(&mut self) -> Result<String, String> {
         let atom = self.parse_atom()?;
+        let alias = self.parse_alias()?;
+        self.register_atom(atom, alias, 1)
+    }
 
-        let alias = if self.peek() == Some(&Token::FatArrow) {
-            self.consume(); // consume '=>'
-            match self.consume() {
-                Some(Token::Ident(s)) => Some(s),
-                t => return Err(format!("Expected name after '=>', got {t:?}")),
-            }
-        } else {
-            None
-        };
+    /// Parse an optional `"=>" Ident` after an atom.
+    fn parse_alias(&mut self) -> Result<Option<String>, String> {
+        if self.peek() != Some(&Token::FatArrow) {
+            return Ok(None);
+        }
+        self.consume(); // consume '=>'
+        match self.consume() {
+            Some(Token::Ident(s)) => Ok(Some(s)),
+            t => Err(format!("Expected name after '=>', got {t:?}")),
+        }
+    }
 
+    /// Register a parsed atom and return its name.
+    /// Pending atoms and groups are registered under `alias` if given,
+    /// otherwise under the generated fallback name; with `copies` above one, a
+    /// pending atom is replicated into a group of that many channels.
+    /// Already-registered atoms are renamed if `alias` is given.
+    fn register_atom(
+        &mut self,
+        atom: Atom,
+        alias: Option<String>,
+        copies: usize,
+    ) -> Result<String, String> {
         match atom {
+            Atom::Pending { facade, fallback } if copies > 1 => {
+                let name = alias.unwrap_or(fallback);
+                self.register_group(&name, vec![facade; copies]);
+                Ok(name)
+            }
             Atom::Pending { facade, fallback } => {
                 let name = alias.unwrap_or(fallback);
                 self.register.insert(name.clone(), facade);
                 Ok(name)
             }
+            Atom::Group { facades, fallback } => {
+                let name = alias.unwrap_or(fallback);
+                self.register_group(&name, facades);
+                Ok(name)
+            }
             Atom::Registered(name) => {
                 if let Some(alias) = alias {
                     self.rename(&name, &alias);
@@ -576,135 +1171,381 @@ impl ChainParser {
                     Ok(name)
                 }
             }
+            Atom::Macro {
+                def,
+                args,
+                fallback,
+            } => {
+                let name = alias.unwrap_or(fallback);
+                self.expand_macro(&def, args, &name)?;
+                Ok(name)
+            }
         }
     }
 
-    /// Parse a chain of atoms joined by `->` or `&>` (with optional port specs).
+    /// Register one node per facade, as channels `name.0`, `name.1`, ... of the group
+    /// `name`.
+    fn register_group(&mut self, name: &str, facades: Vec<Facade>) {
+        self.groups.insert(name.to_string(), facades.len());
+        for (k, facade) in facades.into_iter().enumerate() {
+            self.register.insert(format!("{name}.{k}"), facade);
+        }
+    }
+
+    /// The number of channels of `node`: the size of a group, else 1.
+    fn width(&self, node: &str) -> usize {
+        self.groups.get(node).copied().unwrap_or(1)
+    }
+
+    /// The node of each channel of `node`: `node.0`, `node.1`, ... for a group, else
+    /// `node` itself.
+    fn channels(&self, node: &str) -> Vec<String> {
+        match self.groups.get(node) {
+            Some(n) => (0..*n).map(|k| format!("{node}.{k}")).collect(),
+            None => vec![node.to_string()],
+        }
+    }
+
+    /// Connect `src` to `dst` channel by channel. A single-channel source is
+    /// connected to every channel of `dst`; otherwise the channel counts must match.
+    fn connect_channels(
+        &mut self,
+        src: &str,
+        src_port: Option<&str>,
+        dst: &str,
+        dst_port: Option<&str>,
+        feedback: bool,
+    ) -> Result<(), String> {
+        let srcs = self.channels(src);
+        let dsts = self.channels(dst);
+        if srcs.len() > 1 && srcs.len() != dsts.len() {
+            return Err(format!(
+                "Cannot connect {}-channel '{src}' to {}-channel '{dst}'",
+                srcs.len(),
+                dsts.len()
+            ));
+        }
+        for (k, dst_node) in dsts.iter().enumerate() {
+            let src_node = &srcs[k % srcs.len()];
+            let pair = (
+                self.port_label(src_node, src_port, true)?,
+                self.port_label(dst_node, dst_port, false)?,
+            );
+            if feedback {
+                self.feedback.push(pair);
+            } else {
+                self.connect.push(pair);
+            }
+        }
+        Ok(())
+    }
+
+    /// Parse `def Name(param, param=default, ...) { body }` and store it; the body is
+    /// parsed when the macro is instantiated.
+    fn parse_def(&mut self) -> Result<(), String> {
+        self.consume(); // consume 'def'
+        let name = match self.consume() {
+            Some(Token::Ident(s)) if !UGFacade::is_variant_name(&s) => s,
+            Some(Token::Ident(s)) => {
+                return Err(format!("Cannot define macro '{s}': it is a UGen name"));
+            }
+            t => return Err(format!("Expected macro name after 'def', got {t:?}")),
+        };
+        let mut params = Vec::new();
+        if self.peek() == Some(&Token::LParen) {
+            self.consume(); // consume '('
+            while self.peek() != Some(&Token::RParen) {
+                let param = match self.consume() {
+                    Some(Token::Ident(s)) => s,
+                    t => return Err(format!("Expected parameter name, got {t:?}")),
+                };
+                let default = if self.peek() == Some(&Token::Assign) {
+                    self.consume(); // consume '='
+                    Some(self.parse_value()?)
+                } else {
+                    None
+                };
+                params.push((param, default));
+                match self.peek() {
+                    Some(Token::Comma) => {
+                        self.consume();
+                    }
+                    Some(Token::RParen) => {}
+                    _ => return Err(self.unexpected("',' or ')'")),
+                }
+            }
+            self.expect(&Token::RParen)?;
+        }
+        self.expect(&Token::LBrace)?;
+        let start = self.pos;
+        let mut nesting = 1;
+        while nesting > 0 {
+            match self.consume() {
+                Some(Token::LBrace) => nesting += 1,
+                Some(Token::RBrace) => nesting -= 1,
+                Some(_) => {}
+                None => return Err(format!("Unclosed '{{' in macro '{name}'")),
+            }
+        }
+        let body = self.tokens[start..self.pos - 1].to_vec();
+        let source = self.source.clone();
+        self.defs.insert(
+            name,
+            MacroDef {
+                params,
+                body,
+                source,
+            },
+        );
+        Ok(())
+    }
+
+    /// Returns `true` inside a macro body at `in name =` or `out name =`.
+    fn peek_is_port_decl(&self) -> bool {
+        self.depth > 0
+            && matches!(
+                (self.peek(), self.peek_at(1), self.peek_at(2)),
+                (Some(Token::Ident(kw)), Some(Token::Ident(_)), Some(Token::Assign))
+                    if kw == "in" || kw == "out"
+            )
+    }
+
+    /// Parse `in name = node:port` or `out name = node:port` inside a macro body,
+    /// declaring an exposed port. Without `:port` the node's default input (or output) is
+    /// used.
+    fn parse_port_decl(&mut self) -> Result<(), String> {
+        let output = self.consume() == Some(Token::Ident("out".to_string()));
+        let port = match self.consume() {
+            Some(Token::Ident(s)) => s,
+            t => return Err(format!("Expected port name, got {t:?}")),
+        };
+        self.expect(&Token::Assign)?;
+        let node = match self.consume() {
+            Some(Token::Ident(s)) => s,
+            t => return Err(format!("Expected node name, got {t:?}")),
+        };
+        let inner = if self.peek() == Some(&Token::Colon) {
+            self.consume(); // consume ':'
+            match self.consume() {
+                Some(Token::Ident(s)) => Some(s),
+                t => return Err(format!("Expected port name after ':', got {t:?}")),
+            }
+        } else {
+            None
+        };
+        let label = self.port_label(&node, inner.as_deref(), output)?;
+        if output {
+            self.ports.outputs.push((port, label));
+        } else {
+            self.ports.inputs.push((port, label));
+        }
+        Ok(())
+    }
+
+    /// Expand macro `def` as `instance`: its body is parsed with the given arguments and
+    /// every node is registered under the `instance.` namespace.
+    fn expand_macro(
+        &mut self,
+        def: &str,
+        args: Args,
+        instance: &str,
+    ) -> Result<(), String> {
+        if self.depth >= MAX_MACRO_DEPTH {
+            return Err(format!("Macro '{def}' is nested too deeply (recursive?)"));
+        }
+        let macro_def = self.defs[def].clone();
+        if let Some(key) = args
+            .keys()
+            .find(|k| !macro_def.params.iter().any(|(p, _)| p == *k))
+        {
+            return Err(format!("Macro '{def}' has no parameter '{key}'"));
+        }
+        let mut params = HashMap::new();
+        for (param, default) in macro_def.params {
+            let value =
+                args.get(&param).cloned().or(default).ok_or_else(|| {
+                    format!("Macro '{def}' requires parameter '{param}'")
+                })?;
+            params.insert(param, value);
+        }
+
+        let mut sub = ChainParser::new(macro_def.source, macro_def.body);
+        sub.defs = self.defs.clone();
+        sub.params = params;
+        sub.depth = self.depth + 1;
+        sub.parse_located().map_err(|e| {
+            self.located = true;
+            format!("In macro '{def}': {e}")
+        })?;
+
+        let scoped = |label: String| format!("{instance}.{label}");
+        for (name, facade) in sub.register {
+            self.register.insert(scoped(name), facade);
+        }
+        for (src, dst) in sub.connect {
+            self.connect.push((scoped(src), scoped(dst)));
+        }
+        for (src, dst) in sub.feedback {
+            self.feedback.push((scoped(src), scoped(dst)));
+        }
+        for (name, n) in sub.groups {
+            self.groups.insert(scoped(name), n);
+        }
+        let ports = MacroPorts {
+            inputs: sub
+                .ports
+                .inputs
+                .into_iter()
+                .map(|(port, label)| (port, scoped(label)))
+                .collect(),
+            outputs: sub
+                .ports
+                .outputs
+                .into_iter()
+                .map(|(port, label)| (port, scoped(label)))
+                .collect(),
+        };
+        self.instances.insert(instance.to_string(), ports);
+        Ok(())
+    }
+
+    /// Parse a chain of atoms joined by `->`, `~>` or `&>` (with optional port specs).
     /// Adds connections and returns the name of the rightmost node.
     ///
-    /// `->` creates a single connection with an optional `port_spec`.
+    /// `->` creates a single connection with an optional `port_spec`; `~>` does
+    /// the same but records a feedback connection. From a multichannel group,
+    /// a new UGen is replicated per channel and connected channel by channel.
     /// `&>` creates multiple connections (one per output of the source) with an
     /// optional `multi_port_spec`.  When no port spec follows `&>` the source
     /// must have more than one output; all source outputs are connected to the
-    /// first N inputs of the destination in order.
+    /// first N inputs of the destination in order. A new destination UGen with
+    /// fewer than N inputs is instead replicated per output, each copy reading
+    /// one output on its first input. The outputs of a group are the default
+    /// outputs of its channels.
     fn parse_arrow_chain(&mut self) -> Result<String, String> {
         let mut current = self.parse_named_atom()?;
 
         loop {
             match self.peek() {
-                Some(Token::Arrow) => {
-                    self.consume(); // consume '->'
+                Some(Token::Arrow) | Some(Token::TildeArrow) => {
+                    let is_feedback = self.consume() == Some(Token::TildeArrow);
                     let (src_port, dst_port) = self.parse_port_spec_opt()?;
-                    let next = self.parse_named_atom()?;
-
-                    let src_str = match src_port {
-                        Some(port) => format!("{current}.{port}"),
-                        None => self.default_output(&current)?,
-                    };
-                    let dst_str = match dst_port {
-                        Some(port) => format!("{next}.{port}"),
-                        None => self.default_input(&next)?,
-                    };
-
-                    self.connect.push((src_str, dst_str));
+                    let atom = self.parse_atom()?;
+                    let alias = self.parse_alias()?;
+                    let next = self.register_atom(atom, alias, self.width(&current))?;
+                    self.connect_channels(
+                        &current,
+                        src_port.as_deref(),
+                        &next,
+                        dst_port.as_deref(),
+                        is_feedback,
+                    )?;
                     current = next;
                 }
                 Some(Token::SnakeArrow) => {
                     self.consume(); // consume '&>'
                     let port_pairs = self.parse_multi_port_spec_opt()?;
-                    let next = self.parse_named_atom()?;
+                    let atom = self.parse_atom()?;
+                    let alias = self.parse_alias()?;
+
+                    // the source outputs, by port name, or by channel for a group
+                    let grouped = self.groups.contains_key(&current);
+                    let src_outputs = if grouped {
+                        self.channels(&current)
+                    } else {
+                        self.port_names(&current, true)?
+                    };
+                    let src_label = |parser: &Self, idx: usize, port: Option<&str>| {
+                        if grouped {
+                            let channel = format!("{current}.{idx}");
+                            parser.port_label(&channel, port, true)
+                        } else {
+                            let port = port.unwrap_or(&src_outputs[idx]);
+                            parser.port_label(&current, Some(port), true)
+                        }
+                    };
 
                     if port_pairs.is_empty() {
                         // Automatic: connect all outputs of `current` to the
                         // first N inputs of `next` in contiguous order.
-                        let src_outputs: Vec<String> = {
-                            let facade = self
-                                .register
-                                .get(&current)
-                                .ok_or_else(|| format!("Unknown node: '{current}'"))?;
-                            let ugen = facade.to_ugen();
-                            let outputs = ugen.output_names();
-                            if outputs.len() <= 1 {
-                                return Err(format!(
-                                    "'&>' requires source '{current}' to have more than \
-                                     one output, but it has {} output(s); use '->' instead",
-                                    outputs.len()
-                                ));
+                        if src_outputs.len() <= 1 {
+                            return Err(format!(
+                                "'&>' requires source '{current}' to have more than \
+                                 one output, but it has {} output(s); use '->' instead",
+                                src_outputs.len()
+                            ));
+                        }
+                        let copies = match &atom {
+                            Atom::Pending { facade, .. }
+                                if facade.try_to_ugen()?.input_names().len()
+                                    < src_outputs.len() =>
+                            {
+                                src_outputs.len()
                             }
-                            outputs.to_vec()
+                            _ => 1,
                         };
-                        let dst_inputs: Vec<String> =
-                            {
-                                let facade = self
-                                    .register
-                                    .get(&next)
-                                    .ok_or_else(|| format!("Unknown node: '{next}'"))?;
-                                let ugen = facade.to_ugen();
-                                ugen.get_n_inputs(src_outputs.len())
-                                .ok_or_else(|| format!(
-                                    "'&>' destination '{next}' has {} input(s), but \
-                                     source '{current}' has {} outputs",
-                                    ugen.input_names().len(),
-                                    src_outputs.len()
-                                ))?
-                                .into_iter()
-                                .map(|s| s.to_string())
-                                .collect()
-                            };
-                        for (i, src_port) in src_outputs.iter().enumerate() {
+                        let next = self.register_atom(atom, alias, copies)?;
+                        if copies > 1 {
+                            for idx in 0..copies {
+                                self.connect.push((
+                                    src_label(self, idx, None)?,
+                                    self.port_label(
+                                        &format!("{next}.{idx}"),
+                                        None,
+                                        false,
+                                    )?,
+                                ));
+                            }
+                            current = next;
+                            continue;
+                        }
+                        let dst_inputs = self.dst_port_names(&next)?;
+                        if dst_inputs.len() < src_outputs.len() {
+                            return Err(format!(
+                                "'&>' destination '{next}' has {} input(s), but \
+                                 source '{current}' has {} outputs",
+                                dst_inputs.len(),
+                                src_outputs.len()
+                            ));
+                        }
+                        for (idx, dst_port) in
+                            dst_inputs.iter().enumerate().take(src_outputs.len())
+                        {
                             self.connect.push((
-                                format!("{current}.{src_port}"),
-                                format!("{next}.{}", dst_inputs[i]),
+                                src_label(self, idx, None)?,
+                                self.port_label(&next, Some(dst_port), false)?,
                             ));
                         }
+                        current = next;
                     } else {
                         // Explicit port pairs: resolve omitted names from the
                         // n-th contiguous output / input respectively.
-                        let src_outputs: Vec<String> = {
-                            let facade = self
-                                .register
-                                .get(&current)
-                                .ok_or_else(|| format!("Unknown node: '{current}'"))?;
-                            let ugen = facade.to_ugen();
-                            ugen.output_names().to_vec()
-                        };
-                        let dst_inputs: Vec<String> = {
-                            let facade = self
-                                .register
-                                .get(&next)
-                                .ok_or_else(|| format!("Unknown node: '{next}'"))?;
-                            let ugen = facade.to_ugen();
-                            ugen.input_names().to_vec()
-                        };
+                        let next = self.register_atom(atom, alias, 1)?;
+                        let dst_inputs = self.dst_port_names(&next)?;
                         for (idx, (src_port, dst_port)) in port_pairs.iter().enumerate() {
-                            let src_str = match src_port {
-                                Some(port) => format!("{current}.{port}"),
-                                None => {
-                                    let port = src_outputs.get(idx).ok_or_else(|| {
-                                        format!(
-                                            "'&>' source '{current}' does not have \
-                                                 an output at position {idx}"
-                                        )
-                                    })?;
-                                    format!("{current}.{port}")
-                                }
-                            };
-                            let dst_str = match dst_port {
-                                Some(port) => format!("{next}.{port}"),
-                                None => {
-                                    let port = dst_inputs.get(idx).ok_or_else(|| {
-                                        format!(
-                                            "'&>' destination '{next}' does not have \
-                                                 an input at position {idx}"
-                                        )
-                                    })?;
-                                    format!("{next}.{port}")
-                                }
+                            if (grouped || src_port.is_none()) && idx >= src_outputs.len()
+                            {
+                                return Err(format!(
+                                    "'&>' source '{current}' does not have \
+                                     an output at position {idx}"
+                                ));
+                            }
+                            let dst_port = match dst_port {
+                                Some(port) => port,
+                                None => dst_inputs.get(idx).ok_or_else(|| {
+                                    format!(
+                                        "'&>' destination '{next}' does not have \
+                                         an input at position {idx}"
+                                    )
+                                })?,
                             };
-                            self.connect.push((src_str, dst_str));
+                            self.connect.push((
+                                src_label(self, idx, src_port.as_deref())?,
+                                self.port_label(&next, Some(dst_port), false)?,
+                            ));
                         }
+                        current = next;
                     }
-                    current = next;
                 }
                 _ => break,
             }
@@ -713,45 +1554,117 @@ impl ChainParser {
         Ok(current)
     }
 
-    /// Parse `arrow_chain (("+" | "*" | "^") arrow_chain)*`.
-    /// `+` creates a `Sum` UGen; `*` creates a `Mult` UGen; `^` creates a `Fade` UGen.
-    /// For `+` and `*` the two operands are connected to `in1` and `in2` of the new node.
-    /// For `^` the left operand is connected to `in1` and the right operand to `level`.
+    /// The input port names of the destination of `&>`, which cannot be a group.
+    fn dst_port_names(&self, node: &str) -> Result<Vec<String>, String> {
+        if let Some(n) = self.groups.get(node) {
+            return Err(format!(
+                "'&>' destination '{node}' has {n} channels; use '->' to connect channel by channel"
+            ));
+        }
+        self.port_names(node, false)
+    }
+
+    /// Parse `mul_expr (("+" | "-") mul_expr)*`; see `binary_op`.
     fn parse_addmul_expr(&mut self) -> Result<String, String> {
-        let mut lhs = self.parse_arrow_chain()?;
+        let mut lhs = self.parse_mul_expr()?;
+        while matches!(self.peek(), Some(Token::Plus) | Some(Token::Minus)) {
+            let op = self.consume().unwrap();
+            let rhs = self.parse_mul_expr()?;
+            lhs = self.binary_op(&op, &lhs, &rhs)?;
+        }
+        Ok(lhs)
+    }
 
+    /// Parse `arrow_chain (("*" | "/" | "%" | "^") arrow_chain)*`, which binds
+    /// tighter than `+` and `-`; see `binary_op`.
+    fn parse_mul_expr(&mut self) -> Result<String, String> {
+        let mut lhs = self.parse_arrow_chain()?;
         while matches!(
             self.peek(),
-            Some(Token::Plus) | Some(Token::Star) | Some(Token::Caret)
+            Some(Token::Star)
+                | Some(Token::Slash)
+                | Some(Token::Percent)
+                | Some(Token::Caret)
         ) {
             let op = self.consume().unwrap();
             let rhs = self.parse_arrow_chain()?;
+            lhs = self.binary_op(&op, &lhs, &rhs)?;
+        }
+        Ok(lhs)
+    }
 
-            let type_name = match op {
-                Token::Plus => "Sum",
-                Token::Star => "Mult",
-                Token::Caret => "Fade",
-                _ => unreachable!(),
-            };
+    /// Create the node of a binary operator and return its name.
+    /// `+` creates a `Sum` UGen, `-` a `Sub`, `*` a `Mult`, `/` a `Div`, `%` a
+    /// `Mod` and `^` a `Fade`. The left operand is connected to `in1`, and the
+    /// right operand to `in2`, or to `level` for `^`.
+    fn binary_op(&mut self, op: &Token, lhs: &str, rhs: &str) -> Result<String, String> {
+        let type_name = match op {
+            Token::Plus => "Sum",
+            Token::Minus => "Sub",
+            Token::Star => "Mult",
+            Token::Slash => "Div",
+            Token::Percent => "Mod",
+            Token::Caret => "Fade",
+            _ => unreachable!(),
+        };
+        // with a multichannel operand, the operator is replicated per channel
+        let width = self.width(lhs).max(self.width(rhs));
+        let op_name = self.register_op(type_name, width)?;
 
-            let op_name = self.gen_name(type_name);
-            let facade = Self::make_facade_no_args(type_name)?;
-            self.register.insert(op_name.clone(), facade);
+        let rhs_input = if matches!(op, Token::Caret) {
+            "level"
+        } else {
+            "in2"
+        };
+        self.connect_channels(lhs, None, &op_name, Some("in1"), false)?;
+        self.connect_channels(rhs, None, &op_name, Some(rhs_input), false)?;
+        Ok(op_name)
+    }
 
-            let lhs_out = self.default_output(&lhs)?;
-            let rhs_out = self.default_output(&rhs)?;
-            if matches!(op, Token::Caret) {
-                self.connect.push((lhs_out, format!("{op_name}.in1")));
-                self.connect.push((rhs_out, format!("{op_name}.level")));
-            } else {
-                self.connect.push((lhs_out, format!("{op_name}.in1")));
-                self.connect.push((rhs_out, format!("{op_name}.in2")));
-            }
+    /// Register the implicit node of an operator under a generated name,
+    /// replicated to a group if `width` is above one, and return its name.
+    fn register_op(&mut self, type_name: &str, width: usize) -> Result<String, String> {
+        let name = self.gen_name(type_name);
+        let facade = Self::make_facade_no_args(type_name)?;
+        if width > 1 {
+            self.register_group(&name, vec![facade; width]);
+        } else {
+            self.register.insert(name.clone(), facade);
+        }
+        Ok(name)
+    }
 
-            lhs = op_name;
+    /// An error for a reference to an unknown name, suggesting a known name
+    /// (or, before `(`, a UGen or macro) that `id` may be a misspelling of.
+    fn unknown_name(&self, id: &str) -> String {
+        let mut message = if self.peek() == Some(&Token::LParen) {
+            format!("Unknown UGen or macro '{id}'")
+        } else {
+            format!("Unknown name reference: '{id}'")
+        };
+        let names = UGFacade::VARIANTS
+            .iter()
+            .copied()
+            .chain(self.defs.keys().map(String::as_str))
+            .chain(self.register.keys().map(String::as_str))
+            .chain(self.instances.keys().map(String::as_str))
+            .chain(self.groups.keys().map(String::as_str));
+        if let Some(name) = closest(id, names) {
+            message.push_str(&format!("; did you mean '{name}'?"));
         }
+        message
+    }
 
-        Ok(lhs)
+    /// Parse the full chain, prefixing an error with its line and column in the
+    /// source and appending the source line with the error underlined.
+    fn parse_located(&mut self) -> Result<(), String> {
+        self.parse().map_err(|e| {
+            if self.located {
+                e
+            } else {
+                locate(&self.source, &self.last, &e)
+            }
+        })
     }
 
     /// Parse the full chain: one or more segments separated by `|`.
@@ -761,16 +1674,29 @@ impl ChainParser {
         }
 
         loop {
-            self.parse_addmul_expr()?;
+            if self.peek() == Some(&Token::Ident("def".to_string())) {
+                self.parse_def()?;
+                // a definition needs no '|' after its closing '}'
+                if self.peek() == Some(&Token::Pipe) {
+                    self.consume();
+                }
+                if self.peek().is_none() {
+                    break;
+                }
+                continue;
+            }
+            if self.peek_is_port_decl() {
+                self.parse_port_decl()?;
+            } else {
+                self.parse_addmul_expr()?;
+            }
 
             match self.peek() {
                 Some(Token::Pipe) => {
                     self.consume();
                 }
                 None => break,
-                t => {
-                    return Err(format!("Expected '|' or end of input, got {t:?}"));
-                }
+                _ => return Err(self.unexpected("'|' or end of input")),
             }
         }
 
@@ -783,16 +1709,48 @@ impl ChainParser {
 
 type PortPairs = Vec<(Option<String>, Option<String>)>;
 
-pub type ChainResult = (HashMap<String, Facade>, Vec<(String, String)>);
+/// The containers produced by parsing a Chain DSL string.
+#[derive(Debug)]
+pub struct ChainResult {
+    pub register: HashMap<String, Facade>,
+    pub connect: Vec<(String, String)>,
+    pub feedback: Vec<(String, String)>,
+}
 
-/// Parse a Chain DSL string and return the resulting `register` and `connect`
-/// containers that can be passed to `GraphFacade::from_chain` /
+/// Parse a Chain DSL string and return the resulting `register`, `connect`
+/// and `feedback` containers that can be passed to `GraphFacade::from_chain` /
 /// `register_and_connect`.
 pub fn parse_chain(input: &str) -> Result<ChainResult, String> {
-    let tokens = tokenize(input)?;
-    let mut parser = ChainParser::new(tokens);
-    parser.parse()?;
-    Ok((parser.register, parser.connect))
+    parse_chain_with_defs(&[], input)
+}
+
+/// Like `parse_chain`, but first loads a library of `def` macro sources, each
+/// containing only definitions, which `input` may then instantiate.
+pub fn parse_chain_with_defs(
+    defs: &[String],
+    input: &str,
+) -> Result<ChainResult, String> {
+    let mut library = HashMap::new();
+    for source in defs {
+        let mut lib = ChainParser::from_source(source)?;
+        if lib.peek() != Some(&Token::Ident("def".to_string())) {
+            return Err(format!("Expected a 'def' definition, got: '{source}'"));
+        }
+        lib.defs = library;
+        lib.parse_located()?;
+        if !lib.register.is_empty() {
+            return Err(format!("Definitions may only contain 'def's: '{source}'"));
+        }
+        library = lib.defs;
+    }
+    let mut parser = ChainParser::from_source(input)?;
+    parser.defs = library;
+    parser.parse_located()?;
+    Ok(ChainResult {
+        register: parser.register,
+        connect: parser.connect,
+        feedback: parser.feedback,
+    })
 }
 
 // ---------------------------------------------------------------------------
@@ -806,7 +1764,17 @@ mod tests {
     // Helper: parse a chain and return (register, connect) with sorted connect
     // for deterministic comparisons.
     fn parse(chain: &str) -> (HashMap<String, Facade>, Vec<(String, String)>) {
-        parse_chain(chain).expect("chain parse failed")
+        let result = parse_chain(chain).expect("chain parse failed");
+        (result.register, result.connect)
+    }
+
+    // Helper: tokenize a string, dropping the spans.
+    fn lex(input: &str) -> Vec<Token> {
+        tokenize(input)
+            .expect("tokenize failed")
+            .into_iter()
+            .map(|t| t.token)
+            .collect()
     }
 
     // ---------------------------------------------------------------------------
@@ -814,7 +1782,7 @@ mod tests {
 
     #[test]
     fn test_tokenize_basic() {
-        let tokens = tokenize("White() => noise -> LowPass()").unwrap();
+        let tokens = lex("White() => noise -> LowPass()");
         assert!(tokens.contains(&Token::Arrow));
         assert!(tokens.contains(&Token::FatArrow));
         assert!(tokens.contains(&Token::LParen));
@@ -826,18 +1794,61 @@ mod tests {
 
     #[test]
     fn test_tokenize_number_forms() {
-        let tokens = tokenize("4000 .5 -12.3").unwrap();
+        let tokens = lex("4000, .5, -12.3");
         assert_eq!(tokens[0], Token::Number(4000.0));
-        assert_eq!(tokens[1], Token::Number(0.5));
-        assert_eq!(tokens[2], Token::Number(-12.3));
+        assert_eq!(tokens[2], Token::Number(0.5));
+        assert_eq!(tokens[4], Token::Number(-12.3));
+        // after an operand, '-' is a subtraction
+        assert_eq!(
+            lex("lfo -1 - -2"),
+            vec![
+                Token::Ident("lfo".to_string()),
+                Token::Minus,
+                Token::Number(1.0),
+                Token::Minus,
+                Token::Number(-2.0),
+            ]
+        );
     }
 
     #[test]
     fn test_tokenize_pipe_and_operators() {
-        let tokens = tokenize("a | b + c * d").unwrap();
+        let tokens = lex("a | b + c * d");
         assert!(tokens.contains(&Token::Pipe));
         assert!(tokens.contains(&Token::Plus));
         assert!(tokens.contains(&Token::Star));
+        let tokens = lex("a - b / c % d");
+        assert!(tokens.contains(&Token::Minus));
+        assert!(tokens.contains(&Token::Slash));
+        assert!(tokens.contains(&Token::Percent));
+    }
+
+    #[test]
+    fn test_tokenize_string_literals() {
+        let tokens = lex(r#"Sample(path="a \"b\" \\ c\n.wav")"#);
+        assert_eq!(tokens[4], Token::Str("a \"b\" \\ c\n.wav".to_string()));
+        assert_eq!(tokens[5], Token::RParen);
+        assert_eq!(lex(r#""""#), vec![Token::Str(String::new())]);
+
+        assert_eq!(
+            tokenize(r#"Sample(path="kick.wav)"#),
+            Err(("Unterminated string".to_string(), 12..22))
+        );
+        assert_eq!(
+            tokenize(r#""a\qb""#),
+            Err(("Unknown escape '\\q'".to_string(), 2..4))
+        );
+    }
+
+    #[test]
+    fn test_quote_value() {
+        assert_eq!(quote_value("Midi"), "Midi");
+        assert_eq!(quote_value("kick.wav"), "kick.wav");
+        for text in ["drums/kick 1.wav", "440", "", "say \"hi\"\\\n"] {
+            let quoted = quote_value(text);
+            assert!(quoted.starts_with('"'));
+            assert_eq!(lex(&quoted), vec![Token::Str(text.to_string())]);
+        }
     }
 
     // ---------------------------------------------------------------------------
@@ -947,7 +1958,7 @@ mod tests {
     }
 
     // ---------------------------------------------------------------------------
-    // Binary operators (+ and *)
+    // Binary operators (+, -, *, /, % and ^) and unary minus
 
     #[test]
     fn test_chain_sum_operator() {
@@ -1002,6 +2013,68 @@ mod tests {
         assert!(to_level.is_some(), "expected connection to faded.level");
     }
 
+    #[test]
+    fn test_chain_sub_div_mod_operators() {
+        let (reg, conn) = parse(
+            "White() => a | White() => b | (a - b) => d | (a / b) => q | (a % b) => m",
+        );
+        assert!(matches!(reg["d"], Facade::Full(UGFacade::Sub {}, _)));
+        assert!(matches!(reg["q"], Facade::Full(UGFacade::Div {}, _)));
+        assert!(matches!(reg["m"], Facade::Full(UGFacade::Mod {}, _)));
+        for name in ["d", "q", "m"] {
+            assert!(conn.contains(&("a.out".to_string(), format!("{name}.in1"))));
+            assert!(conn.contains(&("b.out".to_string(), format!("{name}.in2"))));
+        }
+    }
+
+    #[test]
+    fn test_chain_operator_precedence() {
+        // `*` binds tighter than `+`: a + (b * c)
+        let (_, conn) =
+            parse("White() => a | White() => b | White() => c | (a + b * c) => s");
+        let product = &conn.iter().find(|(src, _)| src == "b.out").unwrap().1;
+        assert!(product.ends_with(".in1"));
+        let product = product.trim_end_matches(".in1");
+        assert!(conn.contains(&("a.out".to_string(), "s.in1".to_string())));
+        assert!(conn.contains(&(format!("{product}.out"), "s.in2".to_string())));
+        // operators of equal precedence group to the left: (a - b) - c
+        let (_, conn) =
+            parse("White() => a | White() => b | White() => c | (a - b - c) => s");
+        assert!(conn.contains(&("c.out".to_string(), "s.in2".to_string())));
+    }
+
+    #[test]
+    fn test_chain_unary_minus() {
+        // a negated number is a constant
+        let (reg, conn) = parse("- 2 => k");
+        assert!(matches!(reg["k"], Facade::Short(v) if v == -2.0));
+        assert!(conn.is_empty());
+        // a negated node reads it through a Neg node
+        let (reg, conn) = parse("White() => a | -a => n | -White() -> Round() => r");
+        assert!(matches!(reg["n"], Facade::Full(UGFacade::Neg {}, _)));
+        assert!(conn.contains(&("a.out".to_string(), "n.in".to_string())));
+        assert_eq!(reg.len(), 5);
+        assert_eq!(conn.len(), 3);
+    }
+
+    #[test]
+    fn test_chain_arithmetic_integration() {
+        let chain = "(1 - 2 * 3) => a | (-a / 4) => b | (7 % -3) => c | (7.5 % 2) => d \
+                     | (1 / 0) => z | (2 - 1 - 1) => e";
+        let mut g = crate::graph_from_chain_expression(chain, 8.0, 8).unwrap();
+        g.process();
+        for (label, value) in [
+            ("a.out", -5.0),
+            ("b.out", 1.25),
+            ("c.out", -2.0),
+            ("d.out", 1.5),
+            ("z.out", 0.0),
+            ("e.out", 0.0),
+        ] {
+            assert_eq!(g.get_output_by_label(label), vec![value; 8], "{label}");
+        }
+    }
+
     // ---------------------------------------------------------------------------
     // Complex example from the issue
 
@@ -1068,6 +2141,90 @@ mod tests {
         assert!(conn.is_empty());
     }
 
+    // ---------------------------------------------------------------------------
+    // Comments and error locations
+
+    #[test]
+    fn test_tokenize_comments_and_spans() {
+        let tokens = tokenize("# a kick\nBassDrum() # into\n-> out # end").unwrap();
+        let spans: Vec<(Token, Span)> =
+            tokens.into_iter().map(|t| (t.token, t.span)).collect();
+        assert_eq!(
+            spans,
+            vec![
+                (Token::Ident("BassDrum".to_string()), 9..17),
+                (Token::LParen, 17..18),
+                (Token::RParen, 18..19),
+                (Token::Arrow, 27..29),
+                (Token::Ident("out".to_string()), 30..33),
+            ]
+        );
+        // a '#' in a string is not a comment; spans are in bytes
+        let tokens = tokenize("\"é#1\" x").unwrap();
+        assert_eq!(tokens[0].token, Token::Str("é#1".to_string()));
+        assert_eq!(tokens[1].span, 7..8);
+    }
+
+    #[test]
+    fn test_chain_comments() {
+        let chain = "# a filtered noise\nWhite() => noise # the source\n\
+                     -> LowPass() => lpf # cut the highs\n";
+        let (reg, conn) = parse(chain);
+        assert_eq!(reg.len(), 2);
+        assert_eq!(conn, vec![("noise.out".to_string(), "lpf.in".to_string())]);
+    }
+
+    #[test]
+    fn test_chain_error_location() {
+        let err = parse_chain("White() => noise\n  -> Sinee(@freq=2)").unwrap_err();
+        assert_eq!(
+            err,
+            "2:6: Unknown UGen or macro 'Sinee'; did you mean 'Sine'?\n  \
+             |\n2 |   -> Sinee(@freq=2)\n  |      ^^^^^"
+        );
+        let err = parse_chain("White() => noise | noize -> LowPass()").unwrap_err();
+        assert!(
+            err.starts_with(
+                "1:20: Unknown name reference: 'noize'; did you mean 'noise'?"
+            ),
+            "{err}"
+        );
+        // no suggestion for a name unlike any other
+        let err = parse_chain("White() | xyzzy").unwrap_err();
+        assert!(
+            err.starts_with("1:11: Unknown name reference: 'xyzzy'\n"),
+            "{err}"
+        );
+        // an invalid argument underlines the whole call
+        let err = parse_chain("Sine() | Round(places=x)").unwrap_err();
+        assert!(err.ends_with("\n  |          ^^^^^^^^^^^^^^^"), "{err}");
+        // the end of input is located after the last token
+        let err = parse_chain("Sine() ->  # nothing follows").unwrap_err();
+        assert!(err.starts_with("1:10: Expected atom"), "{err}");
+        // tokenizer errors are located too
+        let err = parse_chain("Sine() $").unwrap_err();
+        assert!(err.starts_with("1:8: Unexpected character: '$'"), "{err}");
+    }
+
+    #[test]
+    fn test_chain_error_location_in_macro() {
+        let chain =
+            "def V(freq) {\n  Sine(@freq=freq) -> Rond() => out\n}\nV(freq=2) => v";
+        let err = parse_chain(chain).unwrap_err();
+        assert!(
+            err.starts_with(
+                "In macro 'V': 2:23: Unknown UGen or macro 'Rond'; did you mean 'Round'?\n"
+            ),
+            "{err}"
+        );
+        assert!(
+            err.ends_with(
+                "2 |   Sine(@freq=freq) -> Rond() => out\n  |                       ^^^^"
+            ),
+            "{err}"
+        );
+    }
+
     // ---------------------------------------------------------------------------
     // Integration: from_chain builds a working GenGraph
 
@@ -1237,10 +2394,383 @@ mod tests {
     #[test]
     fn test_chain_ampersand_arrow_tokenizer() {
         // Ensure '&>' is tokenized correctly.
-        let tokens = tokenize("a &> b").unwrap();
+        let tokens = lex("a &> b");
         assert!(
             tokens.contains(&Token::SnakeArrow),
             "expected SnakeArrow token"
         );
     }
+
+    // ---------------------------------------------------------------------------
+    // Feedback connections (~> operator)
+
+    #[test]
+    fn test_chain_feedback_arrow() {
+        let result = parse_chain("Sum() => acc | 1 ->:in1 acc | acc ~>:in2 acc").unwrap();
+        assert!(result.register.contains_key("acc"));
+        assert_eq!(
+            result.feedback,
+            vec![("acc.out".to_string(), "acc.in2".to_string())]
+        );
+        assert!(!result.connect.iter().any(|(_, dst)| dst == "acc.in2"));
+    }
+
+    #[test]
+    fn test_chain_feedback_integration() {
+        use crate::graph_facade::GraphFacade;
+        let chain = "Sum() => acc | 1 ->:in1 acc | acc ~>:in2 acc";
+        let gf = GraphFacade::from_chain(chain).expect("from_chain failed");
+        let mut g = GenGraph::new(8.0, 8);
+        gf.register_and_connect(&mut g)
+            .expect("register_and_connect failed");
+        let r = Recorder::from_samples(g, Some(vec!["acc.out".to_string()]), 24);
+        let out = r.get_output_by_label("acc.out");
+        assert_eq!(&out[..8], &[1.0; 8]);
+        assert_eq!(&out[16..], &[3.0; 8]);
+    }
+
+    // ---------------------------------------------------------------------------
+    // Macros (def)
+
+    #[test]
+    fn test_chain_macro_expansion() {
+        let (reg, conn) = parse(
+            "def Hit(rate, seed=1) { \
+                Clock(rate=rate, mode=Bpm) => clk -> HighHat(seed=seed) => drum \
+                | out out = drum \
+             } \
+             Hit(rate=120) => k1 | Hit(rate=90, seed=7) => k2 | k1 + k2",
+        );
+        for name in ["k1.clk", "k1.drum", "k2.clk", "k2.drum"] {
+            assert!(reg.contains_key(name), "missing {name}");
+        }
+        assert!(matches!(
+            reg["k2.drum"],
+            Facade::Full(UGFacade::HighHat { seed: Some(7) }, _)
+        ));
+        assert!(matches!(
+            reg["k1.clk"],
+            Facade::Full(UGFacade::Clock { rate, .. }, _) if rate == 120.0
+        ));
+        assert!(conn.contains(&("k1.clk.out".to_string(), "k1.drum.gate".to_string())));
+        assert!(
+            conn.iter()
+                .any(|(src, dst)| src == "k2.drum.out" && dst.ends_with(".in2"))
+        );
+        assert_eq!(reg.len(), 5);
+    }
+
+    #[test]
+    fn test_chain_macro_ports_and_params() {
+        let (reg, conn) = parse(
+            "def Voice(freq) { \
+                Sine() => osc | freq ->:freq osc | (osc * 0.5) => amp \
+                | in phase = osc:phase | out wave = amp \
+             } \
+             | Voice(freq=220) => v | 0.25 ->:phase v | v -> Round()",
+        );
+        let (freq_src, _) = conn
+            .iter()
+            .find(|(_, dst)| dst == "v.osc.freq")
+            .expect("freq connection");
+        let freq_node = freq_src.strip_suffix(".out").unwrap();
+        assert!(matches!(reg[freq_node], Facade::Short(v) if v == 220.0));
+        assert!(conn.iter().any(|(_, dst)| dst == "v.osc.phase"));
+        assert!(
+            conn.iter()
+                .any(|(src, dst)| src == "v.amp.out" && dst.ends_with(".in"))
+        );
+    }
+
+    #[test]
+    fn test_chain_macro_rename_instance() {
+        let (reg, conn) = parse(
+            "def V() { Sine() => osc | out out = osc } | (V()) => w | w -> Round()",
+        );
+        assert!(reg.contains_key("w.osc"));
+        assert!(conn.iter().any(|(src, _)| src == "w.osc.wave"));
+    }
+
+    #[test]
+    fn test_chain_macro_errors() {
+        let def = "def V(freq) { Sine() => osc | in f = osc:freq } ";
+        let err = parse_chain(&format!("{def} V()")).unwrap_err();
+        assert!(err.contains("requires parameter 'freq'"), "{err}");
+        let err = parse_chain(&format!("{def} V(freq=1, gain=2)")).unwrap_err();
+        assert!(err.contains("no parameter 'gain'"), "{err}");
+        let err =
+            parse_chain(&format!("{def} V(freq=1) => v | v -> Round()")).unwrap_err();
+        assert!(err.contains("has no outputs"), "{err}");
+        let err = parse_chain(&format!("{def} V(freq=1) => v | 1 ->:amp v")).unwrap_err();
+        assert!(err.contains("has no input 'amp'"), "{err}");
+        let err = parse_chain("def A() { A() } | A()").unwrap_err();
+        assert!(err.contains("nested too deeply"), "{err}");
+        let err = parse_chain("def Sine() { 1 }").unwrap_err();
+        assert!(err.contains("is a UGen name"), "{err}");
+    }
+
+    #[test]
+    fn test_chain_macro_library() {
+        let defs = vec!["def Half(x) { x * 0.5 => h | out out = h }".to_string()];
+        let result = parse_chain_with_defs(&defs, "Half(x=4) => a").unwrap();
+        assert!(result.register.contains_key("a.h"));
+        assert!(parse_chain_with_defs(&["Sine()".to_string()], "1").is_err());
+    }
+
+    // ---------------------------------------------------------------------------
+    // Wrapper UGens
+
+    #[test]
+    fn test_chain_oversample() {
+        let (reg, conn) =
+            parse("Clock(rate=2, mode=Hz) -> Oversample(factor=4) { BassDrum() } => bd");
+        assert!(matches!(
+            &reg["bd"],
+            Facade::Full(UGFacade::Oversample { factor: 4, ugen }, _)
+                if matches!(**ugen, UGFacade::BassDrum {})
+        ));
+        assert!(conn.iter().any(|(_, dst)| dst == "bd.gate"));
+
+        // macro parameters reach the wrapped UGen
+        let (reg, _) = parse(
+            "def P(n) { Oversample() { Round(places=n) } => r | out out = r } | P(n=2) => p",
+        );
+        assert!(matches!(
+            &reg["p.r"],
+            Facade::Full(UGFacade::Oversample { factor: 2, ugen }, _)
+                if matches!(**ugen, UGFacade::Round { places: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_chain_oversample_errors() {
+        let err = parse_chain("Oversample(factor=4) { 1 }").unwrap_err();
+        assert!(err.contains("Expected a UGen inside"), "{err}");
+        let err = parse_chain("Oversample(factor=4)").unwrap_err();
+        assert!(err.contains("missing field `ugen`"), "{err}");
+    }
+
+    // ---------------------------------------------------------------------------
+    // Input defaults
+
+    #[test]
+    fn test_chain_input_defaults() {
+        let (reg, conn) = parse("Sine(@freq=220, @min=0) => s");
+        assert_eq!(
+            reg["s"].input_defaults(),
+            &[("freq".to_string(), 220.0), ("min".to_string(), 0.0)]
+        );
+        assert!(conn.is_empty());
+
+        // macro parameters reach input defaults
+        let (reg, _) =
+            parse("def V(f) { Sine(@freq=f) => osc | out out = osc } | V(f=110) => v");
+        assert_eq!(
+            reg["v.osc"].input_defaults(),
+            &[("freq".to_string(), 110.0)]
+        );
+
+        // defaults of a wrapped UGen move to the wrapper
+        let (reg, _) = parse("Oversample() { Lfo(wave=Square, @rate=3) } => o");
+        assert!(matches!(
+            &reg["o"],
+            Facade::Full(UGFacade::Oversample { .. }, _)
+        ));
+        assert_eq!(reg["o"].input_defaults(), &[("rate".to_string(), 3.0)]);
+    }
+
+    #[test]
+    fn test_chain_input_defaults_errors() {
+        let err = parse_chain("Sine(@=1)").unwrap_err();
+        assert!(err.contains("Expected input name after '@'"), "{err}");
+        let err = parse_chain("Sine(@freq=fast)").unwrap_err();
+        assert!(
+            err.contains("Input default '@freq' should be a number"),
+            "{err}"
+        );
+    }
+
+    // ---------------------------------------------------------------------------
+    // Multichannel expansion
+
+    fn defaults(
+        reg: &HashMap<String, Facade>,
+        name: &str,
+    ) -> Vec<(String, crate::Sample)> {
+        reg[name].input_defaults().to_vec()
+    }
+
+    #[test]
+    fn test_chain_multichannel_list_a() {
+        let (reg, conn) = parse("Sine(@freq=[220, 330, 440], @min=[-1, 0]) => osc");
+        assert_eq!(reg.len(), 3);
+        assert!(!reg.contains_key("osc"));
+        assert_eq!(
+            defaults(&reg, "osc.0"),
+            vec![("freq".to_string(), 220.0), ("min".to_string(), -1.0)]
+        );
+        assert_eq!(
+            defaults(&reg, "osc.1"),
+            vec![("freq".to_string(), 330.0), ("min".to_string(), 0.0)]
+        );
+        // shorter lists wrap around
+        assert_eq!(
+            defaults(&reg, "osc.2"),
+            vec![("freq".to_string(), 440.0), ("min".to_string(), -1.0)]
+        );
+        assert!(conn.is_empty());
+
+        // defaults of a wrapped UGen expand the wrapper
+        let (reg, _) = parse("Oversample() { Sine(@freq=[220, 330]) } => os");
+        assert!(matches!(
+            &reg["os.1"],
+            Facade::Full(UGFacade::Oversample { .. }, _)
+        ));
+        assert_eq!(defaults(&reg, "os.1"), vec![("freq".to_string(), 330.0)]);
+    }
+
+    #[test]
+    fn test_chain_multichannel_arrow_a() {
+        // a UGen after a group is replicated per channel; a single node fans out to every
+        // channel
+        let (reg, conn) = parse(
+            "Sine(@freq=[220, 330]) => osc -> LowPass(@cutoff=800) => lp \
+             | Sine(@freq=0.5) => lfo ->:cutoff lp",
+        );
+        assert!(reg.contains_key("lp.0") && reg.contains_key("lp.1"));
+        assert_eq!(defaults(&reg, "lp.1"), vec![("cutoff".to_string(), 800.0)]);
+        for pair in [
+            ("osc.0.wave", "lp.0.in"),
+            ("osc.1.wave", "lp.1.in"),
+            ("lfo.wave", "lp.0.cutoff"),
+            ("lfo.wave", "lp.1.cutoff"),
+        ] {
+            assert!(
+                conn.contains(&(pair.0.to_string(), pair.1.to_string())),
+                "{pair:?} not in {conn:?}"
+            );
+        }
+        // a channel can be named directly
+        let (_, conn) = parse("Sine(@freq=[220, 330]) => osc | osc.1 -> Round() => r");
+        assert_eq!(conn, vec![("osc.1.wave".to_string(), "r.in".to_string())]);
+    }
+
+    #[test]
+    fn test_chain_multichannel_ampersand_arrow_a() {
+        // a UGen with fewer inputs than the source has outputs is replicated per output
+        let (reg, conn) = parse("Pan(outputs=3) => pan &> Round(places=1) => r");
+        assert_eq!(reg.len(), 4);
+        assert_eq!(
+            conn,
+            vec![
+                ("pan.out1".to_string(), "r.0.in".to_string()),
+                ("pan.out2".to_string(), "r.1.in".to_string()),
+                ("pan.out3".to_string(), "r.2.in".to_string()),
+            ]
+        );
+        // a group is a multi-output source, one output per channel
+        let (_, conn) = parse("Sine(@freq=[220, 330]) => osc &> Reverb() => rev");
+        assert_eq!(
+            conn,
+            vec![
+                ("osc.0.wave".to_string(), "rev.in_l".to_string()),
+                ("osc.1.wave".to_string(), "rev.in_r".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_chain_multichannel_operators_a() {
+        let (reg, conn) = parse("(Sine(@freq=[220, 330]) => osc * 0.5) => amp");
+        assert!(reg.contains_key("amp.0") && reg.contains_key("amp.1"));
+        assert!(conn.contains(&("osc.1.wave".to_string(), "amp.1.in1".to_string())));
+        let half = conn
+            .iter()
+            .filter(|(_, dst)| dst.ends_with(".in2"))
+            .map(|(src, _)| src.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(half.len(), 2);
+        assert_eq!(half[0], half[1]);
+    }
+
+    #[test]
+    fn test_chain_string_arguments() {
+        let path = |reg: &HashMap<String, Facade>, name: &str| match &reg[name] {
+            Facade::Full(UGFacade::Sample { path, .. }, _) => path.clone(),
+            f => panic!("expected a Sample, got {f:?}"),
+        };
+        let (reg, _) = parse(r#"Sample(path="drums/kick 1.wav", interp=Cubic) => s"#);
+        assert_eq!(path(&reg, "s"), "drums/kick 1.wav");
+
+        // strings pass through macro parameters and list literals
+        let (reg, _) = parse(
+            r#"def Hit(file="a b.wav") { Sample(path=file) => s }
+            | Hit() => h1 | Hit(file="c:\\d.wav") => h2"#,
+        );
+        assert_eq!(path(&reg, "h1.s"), "a b.wav");
+        assert_eq!(path(&reg, "h2.s"), "c:\\d.wav");
+        let err = parse_chain(r#"Select(values=["x", 2], mode=Cycle)"#)
+            .err()
+            .unwrap();
+        assert!(err.contains(r#"invalid type: string "x""#), "{err}");
+
+        let err = parse_chain(r#"Sine(@freq="440")"#).err().unwrap();
+        assert!(err.contains("should be a number"), "{err}");
+    }
+
+    #[test]
+    fn test_chain_multichannel_errors() {
+        let err = parse_chain("Sine(@freq=[])").unwrap_err();
+        assert!(
+            err.contains("Input default '@freq' is an empty list"),
+            "{err}"
+        );
+        let err = parse_chain(
+            "Sine(@freq=[1, 2]) => a | Sine(@freq=[1, 2, 3]) => b | a ->:freq b",
+        )
+        .unwrap_err();
+        assert!(
+            err.contains("Cannot connect 2-channel 'a' to 3-channel 'b'"),
+            "{err}"
+        );
+        let err =
+            parse_chain("Sine(@freq=[1, 2]) => a | Sum() => s | a -> s").unwrap_err();
+        assert!(
+            err.contains("Cannot connect 2-channel 'a' to 1-channel 's'"),
+            "{err}"
+        );
+        let err =
+            parse_chain("def M() { Sine(@freq=[1, 2]) => a | out o = a } | M() => m")
+                .unwrap_err();
+        assert!(err.contains("'a' has 2 channels"), "{err}");
+    }
+
+    #[test]
+    fn test_chain_multichannel_integration() {
+        let mut g = crate::graph_from_chain_expression(
+            "Sine(@freq=[1000, 2000]) => osc -> Round(places=1) => r",
+            8000.0,
+            8,
+        )
+        .unwrap();
+        g.process();
+        for (channel, freq) in [("r.0.out", 1000), ("r.1.out", 2000)] {
+            let mut mono = crate::graph_from_chain_expression(
+                &format!("Sine(@freq={freq}) -> Round(places=1) => r"),
+                8000.0,
+                8,
+            )
+            .unwrap();
+            mono.process();
+            assert_eq!(
+                g.get_output_by_label(channel),
+                mono.get_output_by_label("r.out")
+            );
+        }
+        // channels are written back as separate nodes
+        let chain = g.to_chain().unwrap();
+        assert!(chain.contains("Sine(@freq=2000) => osc.1"), "{chain}");
+        let mut g2 = crate::graph_from_chain_expression(&chain, 8000.0, 8).unwrap();
+        assert_eq!(g2.to_facade_json().unwrap(), g.to_facade_json().unwrap());
+    }
 }
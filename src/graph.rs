@@ -1,15 +1,158 @@
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
-use std::collections::HashMap;
 use std::collections::VecDeque;
+use std::collections::{HashMap, HashSet};
 
-use crate::ugen_core::UGen;
+use crate::graph_facade::{graph_to_chain, graph_to_facade_json};
+use crate::schedule::{Automation, AutomationPort, GraphEvent};
+use crate::ugen_core::{Input, Rate, UGen};
 use crate::util::Sample;
+use crate::util::derive_seed;
+use crate::util::recycle;
 use crate::util::split_name;
+use std::fmt;
 use std::io::Write;
+use std::ops::Range;
 use std::path::Path;
 use std::process::Command;
 use tempfile::NamedTempFile;
 //------------------------------------------------------------------------------
+/// Errors returned by the fallible (`try_`) `GenGraph` construction methods.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GraphError {
+    /// A node with this name is already registered.
+    DuplicateNode(String),
+    /// No node is registered with this name.
+    UnknownNode(String),
+    /// The node has no input or output with this name.
+    UnknownPort { node: String, port: String },
+    /// The input already has a connection; only one connection per input is allowed.
+    InputAlreadyConnected { node: String, input: String },
+    /// A label that is not in the form `name.port`.
+    MalformedLabel(String),
+    /// Connecting `src` to `dst` would close a cycle of non-feedback connections.
+    Cycle { src: String, dst: String },
+    /// The node cannot be written as a JSON or Chain DSL definition.
+    Unserializable { node: String, reason: String },
+    /// There is no connection from `src` to `dst` to remove.
+    NotConnected { src: String, dst: String },
+    /// A snapshot entry does not fit the node of the same name.
+    InvalidState { node: String, reason: String },
+    /// The UGen of a node in a graph definition cannot be constructed.
+    InvalidUGen { node: String, reason: String },
+}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphError::DuplicateNode(name) => {
+                write!(f, "Node name {name} already exists.")
+            }
+            GraphError::UnknownNode(name) => write!(f, "Unknown node name: {name}"),
+            GraphError::UnknownPort { node, port } => {
+                write!(f, "For {node}, invalid port name: {port}")
+            }
+            GraphError::InputAlreadyConnected { node, input } => write!(
+                f,
+                "Input {node}.{input} is already connected. \
+                 Only one connection per input is allowed."
+            ),
+            GraphError::MalformedLabel(label) => {
+                write!(f, "Expected 'name.port', got: '{label}'")
+            }
+            GraphError::Cycle { src, dst } => write!(
+                f,
+                "Connecting {src} to {dst} would create a cycle. \
+                 Use a feedback connection to close a loop with a one-block delay."
+            ),
+            GraphError::Unserializable { node, reason } => {
+                write!(f, "Node {node} cannot be serialized: {reason}")
+            }
+            GraphError::NotConnected { src, dst } => {
+                write!(f, "No connection from {src} to {dst}")
+            }
+            GraphError::InvalidState { node, reason } => {
+                write!(f, "Cannot restore state of node {node}: {reason}")
+            }
+            GraphError::InvalidUGen { node, reason } => {
+                write!(f, "Cannot construct node {node}: {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for GraphError {}
+
+impl From<GraphError> for String {
+    fn from(e: GraphError) -> Self {
+        e.to_string()
+    }
+}
+
+/// How serious a `GraphIssue` is: errors mark graphs that do not render as intended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// A problem found by `GenGraph::validate`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GraphIssue {
+    /// The node is not in the execution order, as it is in or downstream of a cycle of
+    /// non-feedback connections, and is never processed.
+    Unordered { node: String },
+    /// The input has no connection, no input default, and no UGen default, so it reads
+    /// zero.
+    MissingInput { node: String, input: String },
+    /// No output of the node is read by another node or rendered.
+    Dangling { node: String },
+    /// The node computes a constant from other nodes; `GenGraph::compile` folds it into
+    /// input defaults.
+    ConstantSubgraph { node: String },
+}
+
+impl GraphIssue {
+    pub fn severity(&self) -> Severity {
+        match self {
+            GraphIssue::Unordered { .. } | GraphIssue::MissingInput { .. } => {
+                Severity::Error
+            }
+            GraphIssue::Dangling { .. } | GraphIssue::ConstantSubgraph { .. } => {
+                Severity::Warning
+            }
+        }
+    }
+}
+
+impl fmt::Display for GraphIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.severity() {
+            Severity::Warning => write!(f, "warning: ")?,
+            Severity::Error => write!(f, "error: ")?,
+        }
+        match self {
+            GraphIssue::Unordered { node } => write!(
+                f,
+                "Node {node} is in or after a cycle and is never processed. \
+                 Use a feedback connection to close a loop."
+            ),
+            GraphIssue::MissingInput { node, input } => write!(
+                f,
+                "Input {node}.{input} has no connection and no default, and reads zero."
+            ),
+            GraphIssue::Dangling { node } => {
+                write!(f, "No output of node {node} is used.")
+            }
+            GraphIssue::ConstantSubgraph { node } => {
+                write!(f, "Node {node} computes a constant from constant inputs.")
+            }
+        }
+    }
+}
+
 //------------------------------------------------------------------------------
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -19,6 +162,79 @@ pub struct NodeEdge {
     pub(crate) src: NodeId,
     pub(crate) output_index: usize, // output in src
     pub(crate) input_index: usize,  // index in the Node's inputs
+    // For feedback edges, the src output from the previous block; `None` for ordinary
+    // edges. Feedback edges are ignored by the topological sort, so they may close a
+    // cycle.
+    pub(crate) delayed: Option<Vec<Sample>>,
+}
+
+impl NodeEdge {
+    pub(crate) fn is_feedback(&self) -> bool {
+        self.delayed.is_some()
+    }
+}
+
+// A node and one of its output indices
+type PortRef = (NodeId, usize);
+
+// A node's name, UGen, and input defaults as (input name, value) pairs
+pub(crate) type LiveNode<'a> = (&'a str, &'a dyn UGen, Vec<(&'a str, Sample)>);
+
+/// The running state of a `GenGraph`, as returned by `GenGraph::snapshot`: the current
+/// time and, per named node, the UGen state and the previous block held by feedback
+/// connections into it. Configuration (nodes, connections, input defaults) and scheduled
+/// events are not included.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphSnapshot {
+    pub time_sample: usize,
+    pub nodes: Vec<NodeSnapshot>,
+}
+
+/// The running state of one node in a `GraphSnapshot`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeSnapshot {
+    pub name: String,
+    pub type_name: String,
+    /// The value returned by `UGen::describe_config`, which `restore` requires to match.
+    #[serde(default)]
+    pub config: Option<String>,
+    /// The value returned by `UGen::save_state`; `None` for stateless UGens.
+    pub state: Option<Value>,
+    /// Delayed blocks of feedback connections, by input name.
+    pub feedback: Vec<(String, Vec<Sample>)>,
+}
+
+/// Crossfade applied to a node for a window of samples after a hot swap.
+pub(crate) struct Crossfade {
+    // Inputs whose source changed: input index, previous source (node, output) if any,
+    // and the blended buffer
+    inputs: Vec<(usize, Option<PortRef>, Vec<Sample>)>,
+    // A retiring node of the same name whose outputs fade out as this node's outputs fade
+    // in
+    outputs_from: Option<NodeId>,
+    pos: usize,
+    len: usize,
+}
+
+/// Gain of the new signal for sample `k` of a block starting `pos` samples into a
+/// crossfade of `len` samples.
+fn crossfade_gain(pos: usize, len: usize, k: usize) -> Sample {
+    ((pos + k + 1) as Sample / len as Sample).min(1.0)
+}
+
+impl Crossfade {
+    /// Gain of the new signal for sample `k` of the current block.
+    fn gain(&self, k: usize) -> Sample {
+        crossfade_gain(self.pos, self.len, k)
+    }
+
+    /// The previous sources this node reads from.
+    fn deps(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.inputs
+            .iter()
+            .filter_map(|(_, prev, _)| prev.map(|(src, _)| src))
+            .chain(self.outputs_from)
+    }
 }
 
 pub struct GraphNode {
@@ -27,21 +243,320 @@ pub struct GraphNode {
     pub(crate) node: Box<dyn UGen>,
     // `inputs` are unsorted `NodeEdge``, defining another NodeId, an output index of that node to read from, and the input index to apply to this node. Every input is another node's output.
     pub(crate) inputs: Vec<NodeEdge>,
-    // Output samples from `process()` are stored in the `GraphNode`
+    // Output samples from `process()` are stored in the `GraphNode`; a control-rate
+    // output holds its one value in every sample
     pub(crate) outputs: Vec<Vec<Sample>>,
+    // The rate of each output in the last processed block
+    pub(crate) output_rates: Vec<Rate>,
+    // Per output, the control-rate value that fills its whole buffer, if any; reset with
+    // the execution order
+    held: Vec<Option<Sample>>,
     pub(crate) name_to_output_index: HashMap<String, usize>,
+    // Set for the duration of a hot-swap crossfade
+    pub(crate) fade: Option<Crossfade>,
+    // A node removed by a hot swap, still processed (but not named) until crossfades end
+    pub(crate) retiring: bool,
+    // A node removed by `remove_node`: an empty slot kept so that other `NodeId`s do not
+    // shift, until `add_node` reuses it; never processed
+    pub(crate) removed: bool,
+    // Input defaults set with `set_input_default`: input index and value, passed as a
+    // control-rate input while the input is unconnected
+    pub(crate) input_defaults: Vec<(usize, Sample)>,
+    // Per input, where it reads from; resolved with the execution order
+    pub(crate) input_sources: Vec<InputSource>,
+    // Higher than the rank of every node this node depends on, so that a connection from
+    // a lower-ranked node cannot close a cycle; ranks need not be distinct
+    rank: f64,
+    // Empty scratch tables for the inputs and outputs passed to `process`, kept to reuse
+    // their allocations
+    input_scratch: Vec<Input<'static>>,
+    output_scratch: Vec<&'static mut [Sample]>,
+}
+
+impl GraphNode {
+    /// A node with no UGen, ports or edges, such as the slot of a removed node.
+    fn vacant() -> Self {
+        Self {
+            id: NodeId(0),
+            name: String::new(),
+            node: Box::new(Removed),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            output_rates: Vec::new(),
+            held: Vec::new(),
+            name_to_output_index: HashMap::new(),
+            fade: None,
+            retiring: false,
+            removed: false,
+            input_defaults: Vec::new(),
+            input_sources: Vec::new(),
+            rank: 0.0,
+            input_scratch: Vec::new(),
+            output_scratch: Vec::new(),
+        }
+    }
+
+    /// The nodes this node must run after: the sources of its non-feedback inputs and,
+    /// during a hot-swap crossfade, the previous sources it fades from.
+    fn dependencies(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.inputs
+            .iter()
+            .filter(|e| !e.is_feedback())
+            .map(|e| e.src)
+            .chain(self.fade.iter().flat_map(|f| f.deps()))
+    }
+
+    /// Set the input default of input `i`, replacing any previous one.
+    fn set_input_default(&mut self, i: usize, value: Sample) {
+        match self.input_defaults.iter_mut().find(|(j, _)| *j == i) {
+            Some((_, v)) => *v = value,
+            None => self.input_defaults.push((i, value)),
+        }
+    }
+}
+
+/// Where an input of a `GraphNode` reads from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum InputSource {
+    /// An output of another node: node and output index.
+    Output(NodeId, usize),
+    /// The delayed block of the feedback edge at this index of the node's `inputs`.
+    Feedback(usize),
+    /// A constant: an input default set on the node, or zero for an input without a
+    /// default.
+    Constant(Sample),
+    /// Unconnected, so the UGen applies its own default.
+    Unconnected,
+}
+
+/// The default of input `i`: an override from `input_defaults` if set, else the UGen's
+/// own default.
+fn input_default(
+    ugen: &dyn UGen,
+    input_defaults: &[(usize, Sample)],
+    i: usize,
+) -> Option<Sample> {
+    match input_defaults.iter().find(|(j, _)| *j == i) {
+        Some((_, value)) => Some(*value),
+        None => ugen.default_input(&ugen.input_names()[i]),
+    }
+}
+
+/// The UGen left in the slot of a removed node.
+struct Removed;
+
+impl UGen for Removed {
+    fn type_name(&self) -> &'static str {
+        "Removed"
+    }
+
+    fn input_names(&self) -> &[String] {
+        &[]
+    }
+
+    fn output_names(&self) -> &[String] {
+        &[]
+    }
+
+    fn process(
+        &mut self,
+        _inputs: &[Input],
+        _outputs: &mut [&mut [Sample]],
+        _sample_rate: f32,
+        _time_sample: usize,
+    ) {
+    }
+}
+
+/// Map each output name of `node` to its index.
+fn output_indices(node: &dyn UGen) -> HashMap<String, usize> {
+    node.output_names()
+        .iter()
+        .enumerate()
+        .map(|(i, out_name)| (out_name.to_string(), i))
+        .collect()
+}
+fn split_label(label: &str) -> Result<(&str, &str), GraphError> {
+    split_name(label).ok_or_else(|| GraphError::MalformedLabel(label.to_string()))
+}
+
+/// Timing of the block being processed.
+struct Block {
+    sample_rate: f32,
+    buffer_size: usize,
+    time_sample: usize,
+}
+
+// A node's output buffers and their rates
+type NodeOutputs<'a> = (&'a [Vec<Sample>], &'a [Rate]);
+
+/// A node of an execution level, with the range of its lanes in `GenGraph::automation`.
+struct LevelTask {
+    node: NodeId,
+    lanes: Range<usize>,
+}
+
+/// Process `node` for one block.
+///
+/// `src` returns the outputs of another node, and is only called for nodes that this node
+/// must run after; `lanes` are the automation lanes of this node.
+fn process_node<'a>(
+    node: &mut GraphNode,
+    src: impl Fn(NodeId) -> NodeOutputs<'a>,
+    lanes: &mut [Automation],
+    block: &Block,
+) {
+    let mut inputs: Vec<Input> = recycle(std::mem::take(&mut node.input_scratch));
+    for source in &node.input_sources {
+        inputs.push(match *source {
+            InputSource::Output(id, output) => {
+                let (outputs, rates) = src(id);
+                match rates[output] {
+                    Rate::Audio => Input::Audio(&outputs[output]),
+                    Rate::Control => Input::Control(outputs[output][0]),
+                }
+            }
+            InputSource::Feedback(edge) => {
+                Input::Audio(node.inputs[edge].delayed.as_deref().unwrap_or(&[]))
+            }
+            InputSource::Constant(value) => Input::Control(value),
+            InputSource::Unconnected => Input::Unconnected,
+        });
+    }
+
+    // after a hot swap, blend inputs whose source changed from the previous source
+    if let Some(fade) = node.fade.as_mut() {
+        let (pos, len) = (fade.pos, fade.len);
+        for (i, prev, buffer) in fade.inputs.iter_mut() {
+            let default = input_default(node.node.as_ref(), &node.input_defaults, *i)
+                .unwrap_or(0.0);
+            let prev_slice: &[Sample] = match *prev {
+                Some((id, out)) if id != node.id => &src(id).0[out],
+                _ => &[],
+            };
+            for (k, v) in buffer.iter_mut().enumerate() {
+                let a = prev_slice.get(k).copied().unwrap_or(default);
+                let b = inputs[*i].value_or(k, default);
+                *v = a + (b - a) * crossfade_gain(pos, len, k);
+            }
+        }
+    }
+    if let Some(fade) = &node.fade {
+        for (i, _, buffer) in &fade.inputs {
+            inputs[*i] = Input::Audio(buffer);
+        }
+    }
+
+    // render automated inputs, then substitute those that are active
+    for lane in lanes.iter_mut() {
+        if let AutomationPort::Input(i) = lane.port {
+            let default =
+                input_default(node.node.as_ref(), &node.input_defaults, i).unwrap_or(0.0);
+            lane.render(block.time_sample, inputs[i], default);
+        }
+    }
+    for lane in lanes.iter().filter(|a| a.active) {
+        if let AutomationPort::Input(i) = lane.port {
+            inputs[i] = Input::Audio(&lane.buffer);
+        }
+    }
+
+    // outputs blended by a crossfade or overwritten by automation vary within the block,
+    // so are rendered at audio rate
+    let fade_outputs = node.fade.as_ref().is_some_and(|f| f.outputs_from.is_some());
+    for (o, rate) in node.output_rates.iter_mut().enumerate() {
+        let automated = lanes.iter().any(|a| a.port == AutomationPort::Output(o));
+        *rate = if fade_outputs || automated {
+            Rate::Audio
+        } else {
+            node.node.output_rate(o, &inputs)
+        };
+    }
+
+    let mut output_slices: Vec<&mut [Sample]> =
+        recycle(std::mem::take(&mut node.output_scratch));
+    for (buf, rate) in node.outputs.iter_mut().zip(&node.output_rates) {
+        output_slices.push(match rate {
+            Rate::Audio => buf.as_mut_slice(),
+            Rate::Control => &mut buf[..1],
+        });
+    }
+
+    node.node.process(
+        &inputs,
+        &mut output_slices,
+        block.sample_rate,
+        block.time_sample,
+    );
+    node.input_scratch = recycle(inputs);
+    node.output_scratch = recycle(output_slices);
+
+    // hold control-rate values for the whole block, for readers of the full block; the
+    // buffer only needs filling when the value changes
+    for ((buf, rate), held) in node
+        .outputs
+        .iter_mut()
+        .zip(&node.output_rates)
+        .zip(node.held.iter_mut())
+    {
+        match rate {
+            Rate::Control if held.map(Sample::to_bits) != Some(buf[0].to_bits()) => {
+                let value = buf[0];
+                buf.fill(value);
+                *held = Some(value);
+            }
+            Rate::Control => {}
+            Rate::Audio => *held = None,
+        }
+    }
+
+    if let Some(fade) = node.fade.as_mut() {
+        if let Some(r) = fade.outputs_from {
+            for (out, prev_out) in node.outputs.iter_mut().zip(src(r).0) {
+                for (k, v) in out.iter_mut().enumerate() {
+                    *v = prev_out[k] + (*v - prev_out[k]) * fade.gain(k);
+                }
+            }
+        }
+        fade.pos += block.buffer_size;
+        if fade.pos >= fade.len {
+            node.fade = None;
+        }
+    }
+
+    for lane in lanes.iter_mut() {
+        if let AutomationPort::Output(i) = lane.port {
+            lane.render(block.time_sample, Input::Audio(&node.outputs[i]), 0.0);
+            if lane.active {
+                node.outputs[i].copy_from_slice(&lane.buffer);
+            }
+        }
+    }
 }
+
 //------------------------------------------------------------------------------
 pub struct GenGraph {
     // Store nodes as assigned, were pos is NodId
     nodes: Vec<GraphNode>,
     // Reverse mapping from node name to NodeId
     name_to_node_id: HashMap<String, NodeId>,
+    // Slots of removed nodes, reused by `add_node`
+    free_ids: Vec<NodeId>,
     // Cache execution order
     execution_order: Option<Vec<NodeId>>,
+    // Nodes grouped by topological depth, and the depth of each node; valid when
+    // `execution_order` is set
+    execution_levels: Vec<Vec<LevelTask>>,
+    node_levels: Vec<usize>,
+    // Threads that process the nodes of one level concurrently; `None` is serial
+    pool: Option<ThreadPool>,
     pub(crate) sample_rate: f32,
     pub(crate) buffer_size: usize,
     time_sample: usize,
+    // Scheduled events, one lane per automated port, sorted by node
+    automation: Vec<Automation>,
+    // Master seed from which unseeded stochastic UGens are seeded, by node name
+    seed: Option<u64>,
 }
 
 impl GenGraph {
@@ -54,45 +569,81 @@ impl GenGraph {
         Self {
             nodes: Vec::new(),
             name_to_node_id: HashMap::new(),
+            free_ids: Vec::new(),
             execution_order: None,
+            execution_levels: Vec::new(),
+            node_levels: Vec::new(),
+            pool: None,
             sample_rate,
             buffer_size,
             time_sample: 0,
+            automation: Vec::new(),
+            seed: None,
         }
     }
 
     /// Add a node given a string-convertible name and a `Box` `Ugen`.
+    ///
+    /// Panics if the name is already registered; see `try_add_node`.
     pub fn add_node<N: Into<String>>(
         &mut self,
         name_raw: N,
         node: Box<dyn UGen>,
     ) -> NodeId {
+        self.try_add_node(name_raw, node)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Add a node given a string-convertible name and a `Box` `Ugen`, returning an error
+    /// if the name is already registered.
+    pub fn try_add_node<N: Into<String>>(
+        &mut self,
+        name_raw: N,
+        mut node: Box<dyn UGen>,
+    ) -> Result<NodeId, GraphError> {
         let name: String = name_raw.into();
         if self.name_to_node_id.contains_key(&name) {
-            panic!("Node name {} already exists.", name);
+            return Err(GraphError::DuplicateNode(name));
+        }
+        if let Some(seed) = self.seed {
+            node.set_graph_seed(derive_seed(seed, &name));
         }
+        self.finish_crossfades();
         self.execution_order = None; // clear cache
 
-        // `id` is always the position in the `nodes` `Vec`
-        let id = NodeId(self.nodes.len());
+        // `id` is always the position in the `nodes` `Vec`; the slot of a removed node is
+        // reused before the `Vec` grows
+        let id = self.free_ids.pop().unwrap_or(NodeId(self.nodes.len()));
         let output_count = node.output_names().len();
-
-        let mut name_to_output_index = HashMap::new();
-        for (i, out_name) in node.output_names().iter().enumerate() {
-            name_to_output_index.insert(out_name.to_string(), i);
-        }
+        let name_to_output_index = output_indices(node.as_ref());
 
         self.name_to_node_id.insert(name.clone(), id);
-        self.nodes.push(GraphNode {
+        let node = GraphNode {
             id,
             name,
             node,
             inputs: Vec::new(),
             outputs: vec![vec![0.0; self.buffer_size]; output_count], // allocate output storage
+            output_rates: vec![Rate::Audio; output_count],
             name_to_output_index,
-        });
+            fade: None,
+            retiring: false,
+            removed: false,
+            input_defaults: Vec::new(),
+            input_sources: Vec::new(),
+            input_scratch: Vec::new(),
+            output_scratch: Vec::new(),
+            held: Vec::new(),
+            // no dependencies yet, so any rank will do
+            rank: self.nodes.len() as f64,
+        };
+        if id.0 == self.nodes.len() {
+            self.nodes.push(node);
+        } else {
+            self.nodes[id.0] = node;
+        }
 
-        id
+        Ok(id)
     }
 
     fn connect_ids(
@@ -101,14 +652,21 @@ impl GenGraph {
         output_index: usize,
         dst: NodeId,
         input_index: usize,
-    ) {
+        feedback: bool,
+    ) -> Result<(), GraphError> {
+        if !feedback && !self.rank_before(src, dst) {
+            return Err(GraphError::Cycle {
+                src: self.nodes[src.0].name.clone(),
+                dst: self.nodes[dst.0].name.clone(),
+            });
+        }
+        let buffer_size = self.buffer_size;
         if let Some(dst_node) = self.nodes.get_mut(dst.0) {
             if dst_node.inputs.iter().any(|e| e.input_index == input_index) {
-                panic!(
-                    "Input {} on node {:?} is already connected. \
-                     Only one connection per input is allowed.",
-                    input_index, dst
-                );
+                return Err(GraphError::InputAlreadyConnected {
+                    node: dst_node.name.clone(),
+                    input: dst_node.node.input_names()[input_index].clone(),
+                });
             }
             self.execution_order = None; // clear cache
             // connect the src.out to dst.in
@@ -116,17 +674,86 @@ impl GenGraph {
                 src,
                 output_index,
                 input_index,
+                delayed: feedback.then(|| vec![0.0; buffer_size]),
             });
         }
+        Ok(())
     }
 
-    /// Given string representations of src.output and dst.input, lookup NodeIDs and input / output indices, and create a connection.
-    pub fn connect(&mut self, src: &str, dst: &str) {
-        let (src_name, output_name) = split_name(src);
-        let (dst_name, input_name) = split_name(dst);
+    /// Make `src` rank below `dst`, as needed for `dst` to depend on `src`. Returns
+    /// false, changing nothing, if `dst` already reaches `src`, so the dependency would
+    /// close a cycle.
+    ///
+    /// Only the nodes that `src` depends on and that rank no lower than `dst` can lie on
+    /// a path from `dst`; those are searched, and moved below `dst` in their current
+    /// order. Connecting nodes in the order they were added searches nothing.
+    fn rank_before(&mut self, src: NodeId, dst: NodeId) -> bool {
+        let limit = self.nodes[dst.0].rank;
+        if self.nodes[src.0].rank < limit {
+            return true;
+        }
+        let mut moved = Vec::new();
+        let mut seen = HashSet::from([src]);
+        let mut stack = vec![src];
+        // the highest rank of a dependency of the moved nodes that is not moved
+        let mut floor = f64::NEG_INFINITY;
+        while let Some(nid) = stack.pop() {
+            if nid == dst {
+                return false;
+            }
+            moved.push(nid);
+            for dep in self.nodes[nid.0].dependencies() {
+                let rank = self.nodes[dep.0].rank;
+                if rank < limit {
+                    floor = floor.max(rank);
+                } else if seen.insert(dep) {
+                    stack.push(dep);
+                }
+            }
+        }
+
+        moved.sort_by(|a, b| self.nodes[a.0].rank.total_cmp(&self.nodes[b.0].rank));
+        let n = moved.len() as f64;
+        let (base, step) = if floor.is_finite() {
+            (floor, (limit - floor) / (n + 1.0))
+        } else {
+            (limit - n - 1.0, 1.0)
+        };
+        let ranks: Vec<f64> = (1..=moved.len()).map(|k| base + step * k as f64).collect();
+        let spaced = ranks
+            .iter()
+            .try_fold(floor, |prev, &r| (prev < r && r < limit).then_some(r))
+            .is_some();
+        if !spaced {
+            // repeated moves into the same gap exhausted its precision; start over with
+            // whole-number ranks
+            self.update_ranks();
+            return self.rank_before(src, dst);
+        }
+        for (nid, rank) in moved.into_iter().zip(ranks) {
+            self.nodes[nid.0].rank = rank;
+        }
+        true
+    }
+
+    /// Rank every node by its position in the execution order.
+    fn update_ranks(&mut self) {
+        for (i, nid) in self.topological_order().into_iter().enumerate() {
+            self.nodes[nid.0].rank = i as f64;
+        }
+    }
 
-        let dst_id = self.name_to_node_id[dst_name];
-        let src_id = self.name_to_node_id[src_name];
+    /// Resolve `src` (node.output) and `dst` (node.input) labels to ids and port indices.
+    fn resolve_ports(
+        &self,
+        src: &str,
+        dst: &str,
+    ) -> Result<(NodeId, usize, NodeId, usize), GraphError> {
+        let (src_name, output_name) = split_label(src)?;
+        let (dst_name, input_name) = split_label(dst)?;
+
+        let dst_id = self.node_id(dst_name)?;
+        let src_id = self.node_id(src_name)?;
         let dst_node = &self.nodes[dst_id.0];
         let src_node = &self.nodes[src_id.0];
 
@@ -135,147 +762,1134 @@ impl GenGraph {
             .input_names()
             .iter()
             .position(|name| name == input_name)
-            .unwrap_or_else(|| {
-                panic!("For {dst_name}, invalid input name: {input_name}")
-            });
-
-        let output_index = src_node
-            .name_to_output_index
-            .get(output_name)
-            .unwrap_or_else(|| {
-                panic!("For {src_name}, invalid output name: {output_name}")
-            });
+            .ok_or_else(|| GraphError::UnknownPort {
+                node: dst_name.to_string(),
+                port: input_name.to_string(),
+            })?;
+
+        let output_index =
+            src_node
+                .name_to_output_index
+                .get(output_name)
+                .ok_or_else(|| GraphError::UnknownPort {
+                    node: src_name.to_string(),
+                    port: output_name.to_string(),
+                })?;
+
+        Ok((src_id, *output_index, dst_id, input_index))
+    }
 
-        self.connect_ids(src_id, *output_index, dst_id, input_index);
+    fn node_id(&self, name: &str) -> Result<NodeId, GraphError> {
+        self.name_to_node_id
+            .get(name)
+            .copied()
+            .ok_or_else(|| GraphError::UnknownNode(name.to_string()))
     }
 
-    // dependency-respecting order (DAG topological sort):
-    pub fn update_execution_node_ids(&mut self) {
-        // if not None, and reuse
-        if self.execution_order.is_some() {
-            return;
-        }
-        let mut indegree = vec![0; self.nodes.len()];
-        for node in &self.nodes {
-            indegree[node.id.0] = node.inputs.len();
-        }
+    /// Set the value that the input `label` (node.input) reads while it is unconnected,
+    /// replacing the UGen's own default, as if it were connected to a `UGConst`. Panics
+    /// on an unknown label; see `try_set_input_default`.
+    pub fn set_input_default(&mut self, label: &str, value: Sample) {
+        self.try_set_input_default(label, value)
+            .unwrap_or_else(|e| panic!("{e}"));
+    }
 
-        let mut queue: VecDeque<NodeId> = indegree
+    /// Like `set_input_default`, but returns a `GraphError` instead of panicking.
+    pub fn try_set_input_default(
+        &mut self,
+        label: &str,
+        value: Sample,
+    ) -> Result<(), GraphError> {
+        let (node_name, input_name) = split_label(label)?;
+        let node_id = self.node_id(node_name)?;
+        let node = &mut self.nodes[node_id.0];
+        let i = node
+            .node
+            .input_names()
             .iter()
-            .enumerate()
-            .filter(|&(_, d)| *d == 0)
-            .map(|(i, _)| NodeId(i))
-            .collect();
-
-        let mut order = Vec::new();
-
-        while let Some(nid) = queue.pop_front() {
-            order.push(nid);
-            for target in &self.nodes {
-                for edge in &target.inputs {
-                    if edge.src == nid {
-                        indegree[target.id.0] -= 1;
-                        if indegree[target.id.0] == 0 {
-                            queue.push_back(target.id);
-                        }
-                    }
-                }
-            }
-        }
-        self.execution_order = Some(order.clone());
+            .position(|name| name == input_name)
+            .ok_or_else(|| GraphError::UnknownPort {
+                node: node_name.to_string(),
+                port: input_name.to_string(),
+            })?;
+        node.set_input_default(i, value);
+        self.execution_order = None; // input sources are resolved with the order
+        Ok(())
     }
 
-    pub fn get_execution_names(&mut self) -> Vec<String> {
-        self.update_execution_node_ids();
+    /// Input defaults set on node `nid` with `set_input_default`, as (input name, value)
+    /// pairs in input order.
+    fn input_defaults(&self, nid: NodeId) -> Vec<(&str, Sample)> {
+        let node = &self.nodes[nid.0];
+        let mut defaults = node.input_defaults.clone();
+        defaults.sort_by_key(|(i, _)| *i);
+        defaults
+            .into_iter()
+            .map(|(i, v)| (node.node.input_names()[i].as_str(), v))
+            .collect()
+    }
 
-        let mut post = Vec::new();
-        for &node_id in self.execution_order.as_ref().unwrap() {
-            post.push(self.nodes[node_id.0].name.clone());
-        }
-        post
+    /// Given string representations of src.output and dst.input, lookup NodeIDs and
+    /// input / output indices, and create a connection. Panics on an unknown name or
+    /// port, an already-connected input, or if the connection would create a cycle; see
+    /// `try_connect`.
+    pub fn connect(&mut self, src: &str, dst: &str) {
+        self.try_connect(src, dst).unwrap_or_else(|e| panic!("{e}"));
     }
 
-    pub fn is_empty(&self) -> bool {
-        self.nodes.is_empty()
+    /// Like `connect`, but returns a `GraphError` instead of panicking. The graph is
+    /// unchanged on error.
+    pub fn try_connect(&mut self, src: &str, dst: &str) -> Result<(), GraphError> {
+        let (src_id, output_index, dst_id, input_index) = self.resolve_ports(src, dst)?;
+        self.connect_ids(src_id, output_index, dst_id, input_index, false)
     }
 
-    pub fn len(&self) -> usize {
-        self.nodes.len()
+    /// Create a feedback connection: `dst` reads the output `src` produced in the
+    /// previous block. Feedback connections may close a cycle (including a node feeding
+    /// itself); on the first block the input reads zeros.
+    pub fn connect_feedback(&mut self, src: &str, dst: &str) {
+        self.try_connect_feedback(src, dst)
+            .unwrap_or_else(|e| panic!("{e}"));
     }
 
-    pub fn process(&mut self) {
-        self.update_execution_node_ids();
+    /// Like `connect_feedback`, but returns a `GraphError` instead of panicking.
+    pub fn try_connect_feedback(
+        &mut self,
+        src: &str,
+        dst: &str,
+    ) -> Result<(), GraphError> {
+        let (src_id, output_index, dst_id, input_index) = self.resolve_ports(src, dst)?;
+        self.connect_ids(src_id, output_index, dst_id, input_index, true)
+    }
 
-        for &nid in self.execution_order.as_ref().unwrap() {
-            let node_index = nid.0;
-            let (left, right) = self.nodes.split_at_mut(node_index);
-            let (node, rest) = right.split_first_mut().expect("valid index");
+    /// Remove the connection from `src` (node.output) to `dst` (node.input), ordinary or
+    /// feedback; the input falls back to its default. Panics if there is no such
+    /// connection; see `try_disconnect`.
+    pub fn disconnect(&mut self, src: &str, dst: &str) {
+        self.try_disconnect(src, dst)
+            .unwrap_or_else(|e| panic!("{e}"));
+    }
 
-            let mut input_slices: Vec<&[Sample]> =
-                vec![&[]; node.node.input_names().len()];
+    /// Like `disconnect`, but returns a `GraphError` instead of panicking. The graph is
+    /// unchanged on error.
+    pub fn try_disconnect(&mut self, src: &str, dst: &str) -> Result<(), GraphError> {
+        let (src_id, output_index, dst_id, input_index) = self.resolve_ports(src, dst)?;
+        let pos = self.nodes[dst_id.0]
+            .inputs
+            .iter()
+            .position(|e| {
+                e.src == src_id
+                    && e.output_index == output_index
+                    && e.input_index == input_index
+            })
+            .ok_or_else(|| GraphError::NotConnected {
+                src: src.to_string(),
+                dst: dst.to_string(),
+            })?;
+        self.finish_crossfades();
+        self.execution_order = None; // clear cache
+        self.nodes[dst_id.0].inputs.remove(pos);
+        Ok(())
+    }
 
-            // for each input edge, as the appropriate output
-            for edge in &node.inputs {
-                input_slices[edge.input_index] = if edge.src.0 < node_index {
-                    &left[edge.src.0].outputs[edge.output_index]
-                } else {
-                    &rest[edge.src.0 - node_index - 1].outputs[edge.output_index]
-                };
-            }
+    /// Remove the node `name` with all of its connections and scheduled events; inputs it
+    /// fed fall back to their defaults. Panics on an unknown name; see `try_remove_node`.
+    pub fn remove_node(&mut self, name: &str) {
+        self.try_remove_node(name).unwrap_or_else(|e| panic!("{e}"));
+    }
 
-            let mut output_slices: Vec<&mut [Sample]> = node
-                .outputs
-                .iter_mut()
-                .map(|buf| buf.as_mut_slice())
-                .collect();
+    /// Like `remove_node`, but returns a `GraphError` instead of panicking.
+    ///
+    /// The node's slot is kept as an empty tombstone, so the `NodeId`s of all other nodes
+    /// are unchanged. The name may be reused by `add_node`, which also fills the slot
+    /// before growing the graph; the removed node's `NodeId` then names the new node.
+    pub fn try_remove_node(&mut self, name: &str) -> Result<(), GraphError> {
+        let id = self.node_id(name)?;
+        self.finish_crossfades();
+        self.execution_order = None; // clear cache
 
-            node.node.process(
-                &input_slices,
-                &mut output_slices,
-                self.sample_rate,
-                self.time_sample,
-            );
+        self.name_to_node_id.remove(name);
+        for node in self.nodes.iter_mut() {
+            node.inputs.retain(|e| e.src != id);
         }
+        self.automation.retain(|a| a.node != id);
 
-        self.time_sample += self.buffer_size;
+        self.nodes[id.0] = GraphNode {
+            id,
+            removed: true,
+            ..GraphNode::vacant()
+        };
+        self.free_ids.push(id);
+        Ok(())
     }
 
-    // Given a two-part name node.output, return a slice of the samples for that node and output,
-    pub fn get_output_by_label(&self, label: &str) -> &[Sample] {
-        let (node_name, output_name) = split_name(label);
-        let node_id = self.name_to_node_id[node_name];
-        let node = &self.nodes[node_id.0];
-
-        let index = node
-            .name_to_output_index
-            .get(output_name)
-            .unwrap_or_else(|| panic!("Invalid output name: {output_name}"));
-
-        &node.outputs[*index]
+    /// Replace the UGen of node `name` with `node`, keeping its name and `NodeId`.
+    ///
+    /// Connections and scheduled events on ports whose names exist on the new UGen are
+    /// kept; the others are dropped. The new UGen starts from its own initial state.
+    /// Panics on an unknown name; see `try_replace_node`.
+    pub fn replace_node(&mut self, name: &str, node: Box<dyn UGen>) {
+        self.try_replace_node(name, node)
+            .unwrap_or_else(|e| panic!("{e}"));
     }
 
-    // NOTE: this is a bit heavy as we create a Vec for each call
-    pub fn get_outputs(&mut self) -> Vec<(String, &Vec<Sample>)> {
-        self.update_execution_node_ids();
-
-        let mut result = Vec::new();
+    /// Like `replace_node`, but returns a `GraphError` instead of panicking.
+    pub fn try_replace_node(
+        &mut self,
+        name: &str,
+        mut node: Box<dyn UGen>,
+    ) -> Result<(), GraphError> {
+        let id = self.node_id(name)?;
+        if let Some(seed) = self.seed {
+            node.set_graph_seed(derive_seed(seed, name));
+        }
+        self.finish_crossfades();
+        self.execution_order = None; // clear cache
 
-        for &node_id in self.execution_order.as_ref().unwrap() {
-            let node = &self.nodes[node_id.0];
-            let name = &node.name;
-            let output_names = node.node.output_names();
+        // old port index to new port index, by name
+        fn port_map(old: &[String], new: &[String]) -> Vec<Option<usize>> {
+            old.iter()
+                .map(|n| new.iter().position(|m| m == n))
+                .collect()
+        }
+        let buffer_size = self.buffer_size;
+        let target = &mut self.nodes[id.0];
+        let inputs = port_map(target.node.input_names(), node.input_names());
+        let outputs = port_map(target.node.output_names(), node.output_names());
+        target.outputs = vec![vec![0.0; buffer_size]; node.output_names().len()];
+        target.output_rates = vec![Rate::Audio; node.output_names().len()];
+        target.name_to_output_index = output_indices(node.as_ref());
+        target.node = node;
+        target.input_defaults.retain_mut(|(i, _)| {
+            let Some(j) = inputs[*i] else {
+                return false;
+            };
+            *i = j;
+            true
+        });
 
-            for (i, output_name) in output_names.iter().enumerate() {
-                let label = format!("{}.{}", name, output_name);
-                result.push((label, &node.outputs[i]));
-            }
+        for (nid, node) in self.nodes.iter_mut().enumerate() {
+            node.inputs.retain_mut(|e| {
+                if nid == id.0 {
+                    let Some(i) = inputs[e.input_index] else {
+                        return false;
+                    };
+                    e.input_index = i;
+                }
+                if e.src == id {
+                    let Some(o) = outputs[e.output_index] else {
+                        return false;
+                    };
+                    e.output_index = o;
+                }
+                true
+            });
         }
-        result
+        self.automation.retain_mut(|lane| {
+            if lane.node != id {
+                return true;
+            }
+            let remapped = match lane.port {
+                AutomationPort::Input(i) => inputs[i].map(AutomationPort::Input),
+                AutomationPort::Output(i) => outputs[i].map(AutomationPort::Output),
+            };
+            match remapped {
+                Some(port) => {
+                    lane.port = port;
+                    true
+                }
+                None => false,
+            }
+        });
+        Ok(())
     }
 
-    // Return all node.output labels
-    pub fn get_output_names_for(&self, node_name: &str) -> Option<Vec<String>> {
-        let node = self.nodes.iter().find(|n| n.name == node_name)?;
+    /// Prepare the graph to render only the outputs `labels` (node.output).
+    ///
+    /// Constant subgraphs are folded: a `UGConst`, or a pure UGen (such as `UGSum`,
+    /// `UGMult` or `UGAsHz`) whose inputs are all constant, is evaluated once and its
+    /// values become input defaults of the nodes it feeds. Nodes with scheduled events
+    /// are not folded. Nodes that no requested output depends on, including folded nodes,
+    /// are then removed, and the requested outputs render the same values as before.
+    /// Returns the names of the removed nodes in execution order.
+    pub fn compile(&mut self, labels: &[String]) -> Result<Vec<String>, GraphError> {
+        let mut roots = Vec::with_capacity(labels.len());
+        for label in labels {
+            self.try_get_output(label)?;
+            let (node_name, _) = split_label(label)?;
+            roots.push(self.node_id(node_name)?);
+        }
+        self.finish_crossfades();
+        let order = self.live_order();
+
+        // per node, its output values if constant, in order so that sources are evaluated
+        // first
+        let mut values: Vec<Option<Vec<Sample>>> = vec![None; self.nodes.len()];
+        for &nid in &order {
+            values[nid.0] = self.constant_outputs(nid, &values);
+        }
+        for &nid in &order {
+            let node = &mut self.nodes[nid.0];
+            let mut folded = Vec::new();
+            node.inputs.retain(|e| {
+                let value = values[e.src.0].as_ref().filter(|_| !roots.contains(&e.src));
+                match value {
+                    Some(v) if !e.is_feedback() => {
+                        folded.push((e.input_index, v[e.output_index]));
+                        false
+                    }
+                    _ => true,
+                }
+            });
+            for (i, value) in folded {
+                node.set_input_default(i, value);
+            }
+        }
+        self.execution_order = None;
+
+        // keep nodes that a requested output reads from, through ordinary or feedback
+        // edges
+        let mut keep = vec![false; self.nodes.len()];
+        let mut stack = roots;
+        while let Some(nid) = stack.pop() {
+            if !std::mem::replace(&mut keep[nid.0], true) {
+                stack.extend(self.nodes[nid.0].inputs.iter().map(|e| e.src));
+            }
+        }
+        let removed: Vec<String> = order
+            .iter()
+            .filter(|nid| !keep[nid.0])
+            .map(|nid| self.nodes[nid.0].name.clone())
+            .collect();
+        for name in &removed {
+            self.try_remove_node(name)?;
+        }
+        Ok(removed)
+    }
+
+    /// Check the graph before rendering the outputs `labels` (node.output), returning its
+    /// issues in the order nodes were added. Errors are nodes excluded from the execution
+    /// order and inputs with no connection and no default; warnings are nodes with no
+    /// used output and constant subgraphs that `compile` would fold. The graph is not
+    /// changed.
+    pub fn validate(&mut self, labels: &[String]) -> Result<Vec<GraphIssue>, GraphError> {
+        let mut rendered = vec![false; self.nodes.len()];
+        for label in labels {
+            self.try_get_output(label)?;
+            let (node_name, _) = split_label(label)?;
+            rendered[self.node_id(node_name)?.0] = true;
+        }
+        let order = self.live_order();
+        let mut ordered = vec![false; self.nodes.len()];
+        let mut values: Vec<Option<Vec<Sample>>> = vec![None; self.nodes.len()];
+        for &nid in &order {
+            ordered[nid.0] = true;
+            values[nid.0] = self.constant_outputs(nid, &values);
+        }
+
+        let live = || self.nodes.iter().filter(|n| !n.removed && !n.retiring);
+        // per node, whether any live node reads it, and whether a node that is not
+        // constant does
+        let mut read = vec![false; self.nodes.len()];
+        let mut read_by_signal = vec![false; self.nodes.len()];
+        for node in live() {
+            for edge in &node.inputs {
+                read[edge.src.0] = true;
+                read_by_signal[edge.src.0] |= values[node.id.0].is_none();
+            }
+        }
+
+        let mut issues = Vec::new();
+        for node in live() {
+            let id = node.id.0;
+            if !ordered[id] {
+                issues.push(GraphIssue::Unordered {
+                    node: node.name.clone(),
+                });
+            }
+            for (i, input) in node.node.input_names().iter().enumerate() {
+                if !node.inputs.iter().any(|e| e.input_index == i)
+                    && input_default(node.node.as_ref(), &node.input_defaults, i)
+                        .is_none()
+                {
+                    issues.push(GraphIssue::MissingInput {
+                        node: node.name.clone(),
+                        input: input.clone(),
+                    });
+                }
+            }
+            if !read[id] && !rendered[id] {
+                issues.push(GraphIssue::Dangling {
+                    node: node.name.clone(),
+                });
+            }
+            // report the last node of each constant subgraph of more than a lone constant
+            if values[id].is_some()
+                && !node.inputs.is_empty()
+                && (rendered[id] || read_by_signal[id])
+            {
+                issues.push(GraphIssue::ConstantSubgraph {
+                    node: node.name.clone(),
+                });
+            }
+        }
+        Ok(issues)
+    }
+
+    /// The output values of node `nid` if it is pure, not automated, and all of its
+    /// inputs are constant; `values` holds the outputs of constant nodes evaluated so
+    /// far. Requires resolved input sources.
+    fn constant_outputs(
+        &mut self,
+        nid: NodeId,
+        values: &[Option<Vec<Sample>>],
+    ) -> Option<Vec<Sample>> {
+        if self.automation.iter().any(|a| a.node == nid) {
+            return None;
+        }
+        let node = &mut self.nodes[nid.0];
+        if !node.node.is_pure() {
+            return None;
+        }
+        let inputs = node
+            .input_sources
+            .iter()
+            .map(|source| match source {
+                InputSource::Output(src, o) => {
+                    values[src.0].as_ref().map(|v| Input::Control(v[*o]))
+                }
+                InputSource::Feedback(_) => None,
+                InputSource::Constant(v) => Some(Input::Control(*v)),
+                InputSource::Unconnected => Some(Input::Unconnected),
+            })
+            .collect::<Option<Vec<Input>>>()?;
+        let count = node.outputs.len();
+        if (0..count).any(|o| node.node.output_rate(o, &inputs) != Rate::Control) {
+            return None;
+        }
+        let mut out = vec![0.0; count];
+        let mut slices: Vec<&mut [Sample]> = out.chunks_mut(1).collect();
+        node.node
+            .process(&inputs, &mut slices, self.sample_rate, self.time_sample);
+        Some(out)
+    }
+
+    // dependency-respecting order (DAG topological sort):
+    pub fn update_execution_node_ids(&mut self) {
+        // if not None, and reuse
+        if self.execution_order.is_some() {
+            return;
+        }
+        let order = self.topological_order();
+        self.update_execution_levels(&order);
+        self.update_input_sources();
+        self.execution_order = Some(order);
+    }
+
+    /// Order the nodes so that each runs after its dependencies, by Kahn's algorithm.
+    /// Feedback edges read the previous block and do not constrain order; a node
+    /// crossfading after a hot swap runs after the previous sources it fades from.
+    fn topological_order(&self) -> Vec<NodeId> {
+        let mut indegree = vec![0; self.nodes.len()];
+        let mut dependents: Vec<Vec<NodeId>> = vec![Vec::new(); self.nodes.len()];
+        for node in &self.nodes {
+            for src in node.dependencies() {
+                indegree[node.id.0] += 1;
+                dependents[src.0].push(node.id);
+            }
+        }
+
+        let mut queue: VecDeque<NodeId> = indegree
+            .iter()
+            .enumerate()
+            .filter(|&(i, d)| *d == 0 && !self.nodes[i].removed)
+            .map(|(i, _)| NodeId(i))
+            .collect();
+
+        let mut order = Vec::with_capacity(self.nodes.len());
+        while let Some(nid) = queue.pop_front() {
+            order.push(nid);
+            for &target in &dependents[nid.0] {
+                indegree[target.0] -= 1;
+                if indegree[target.0] == 0 {
+                    queue.push_back(target);
+                }
+            }
+        }
+        order
+    }
+
+    /// Resolve where each input of each node reads from, so that `process` does not
+    /// search edges or defaults.
+    fn update_input_sources(&mut self) {
+        for node in self.nodes.iter_mut() {
+            // an unconnected input with a default is left unconnected, so the UGen
+            // applies its default; inputs without a default (such as those left by
+            // `remove_node` or `disconnect`) read zero
+            let mut sources: Vec<InputSource> = node
+                .node
+                .input_names()
+                .iter()
+                .map(|name| match node.node.default_input(name) {
+                    Some(_) => InputSource::Unconnected,
+                    None => InputSource::Constant(0.0),
+                })
+                .collect();
+            for (i, value) in &node.input_defaults {
+                sources[*i] = InputSource::Constant(*value);
+            }
+            for (e, edge) in node.inputs.iter().enumerate() {
+                sources[edge.input_index] = if edge.is_feedback() {
+                    InputSource::Feedback(e)
+                } else {
+                    InputSource::Output(edge.src, edge.output_index)
+                };
+            }
+            node.input_scratch = Vec::with_capacity(sources.len());
+            node.output_scratch = Vec::with_capacity(node.outputs.len());
+            node.held = vec![None; node.outputs.len()];
+            node.input_sources = sources;
+        }
+    }
+
+    /// Group `order` by depth: a node's level is one more than the deepest node it must
+    /// run after, so nodes in the same level never read each other.
+    fn update_execution_levels(&mut self, order: &[NodeId]) {
+        // removed nodes are in no level
+        let mut node_levels = vec![usize::MAX; self.nodes.len()];
+        let mut levels: Vec<Vec<LevelTask>> = Vec::new();
+        for &nid in order {
+            let level = self.nodes[nid.0]
+                .dependencies()
+                .map(|src| node_levels[src.0] + 1)
+                .max()
+                .unwrap_or(0);
+            node_levels[nid.0] = level;
+            if level == levels.len() {
+                levels.push(Vec::new());
+            }
+            // lanes are sorted by node
+            let start = self.automation.partition_point(|a| a.node.0 < nid.0);
+            let end = self.automation.partition_point(|a| a.node.0 <= nid.0);
+            levels[level].push(LevelTask {
+                node: nid,
+                lanes: start..end,
+            });
+        }
+        for level in levels.iter_mut() {
+            level.sort_by_key(|t| t.node.0);
+        }
+        self.execution_levels = levels;
+        self.node_levels = node_levels;
+    }
+
+    /// Set the master seed for unseeded stochastic UGens, so renders are reproducible.
+    ///
+    /// Every stochastic UGen constructed without a seed (such as `UGWhite`, `UGSelect` or
+    /// `UGString`) is reseeded with a seed derived from `seed` and its node name, as are
+    /// nodes added later. Nodes constructed with a seed keep it. Call before processing,
+    /// as reseeding restarts random sequences.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+        for node in self.nodes.iter_mut().filter(|n| !n.removed) {
+            node.node.set_graph_seed(derive_seed(seed, &node.name));
+        }
+    }
+
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// Set the number of threads used by `process`. With more than one, the nodes of each
+    /// topological level run concurrently on a thread pool of that size; results are
+    /// identical to serial processing. Defaults to 1.
+    pub fn set_threads(&mut self, threads: usize) {
+        assert!(threads > 0, "threads must be at least 1");
+        if threads != self.threads() {
+            self.pool = (threads > 1).then(|| {
+                ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .build()
+                    .expect("cannot start graph threads")
+            });
+        }
+    }
+
+    pub fn threads(&self) -> usize {
+        self.pool.as_ref().map_or(1, |p| p.current_num_threads())
+    }
+
+    /// The execution order, excluding retiring nodes that only run during a hot-swap
+    /// crossfade.
+    fn live_order(&mut self) -> Vec<NodeId> {
+        self.update_execution_node_ids();
+        self.execution_order
+            .as_ref()
+            .unwrap()
+            .iter()
+            .copied()
+            .filter(|nid| !self.nodes[nid.0].retiring)
+            .collect()
+    }
+
+    /// Live nodes in execution order, as (name, UGen, input defaults set with
+    /// `set_input_default`).
+    pub(crate) fn live_nodes(&mut self) -> Vec<LiveNode<'_>> {
+        self.live_order()
+            .into_iter()
+            .map(|nid| {
+                let node = &self.nodes[nid.0];
+                (
+                    node.name.as_str(),
+                    node.node.as_ref(),
+                    self.input_defaults(nid),
+                )
+            })
+            .collect()
+    }
+
+    /// Live connections as (`src.output`, `dst.input`, is feedback) labels.
+    pub(crate) fn live_connections(&mut self) -> Vec<(String, String, bool)> {
+        let mut connections = Vec::new();
+        for nid in self.live_order() {
+            let node = &self.nodes[nid.0];
+            for edge in &node.inputs {
+                let src = &self.nodes[edge.src.0];
+                connections.push((
+                    format!(
+                        "{}.{}",
+                        src.name,
+                        src.node.output_names()[edge.output_index]
+                    ),
+                    format!(
+                        "{}.{}",
+                        node.name,
+                        node.node.input_names()[edge.input_index]
+                    ),
+                    edge.is_feedback(),
+                ));
+            }
+        }
+        connections
+    }
+
+    /// Serialize the live graph as a JSON graph definition that
+    /// `graph_from_json_definition` reads back. Fails if a UGen does not implement
+    /// `UGen::to_facade`. Scheduled events are not included.
+    pub fn to_facade_json(&mut self) -> Result<String, GraphError> {
+        graph_to_facade_json(self)
+    }
+
+    /// Serialize the live graph as a Chain DSL expression that
+    /// `graph_from_chain_expression` reads back: one named segment per node followed by
+    /// one segment per connection. Nodes are written in name order.
+    pub fn to_chain(&mut self) -> Result<String, GraphError> {
+        graph_to_chain(self)
+    }
+
+    pub fn get_execution_names(&mut self) -> Vec<String> {
+        let mut post = Vec::new();
+        for node_id in self.live_order() {
+            post.push(self.nodes[node_id.0].name.clone());
+        }
+        post
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.name_to_node_id.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.name_to_node_id.len()
+    }
+
+    /// The time (in samples) of the first sample of the next block to be processed.
+    pub fn time_sample(&self) -> usize {
+        self.time_sample
+    }
+
+    /// Capture the running state of all named nodes and the current time, to be resumed
+    /// later with `restore` on this graph or on another graph built from the same
+    /// definition. Retiring nodes of an unfinished hot-swap crossfade are not included.
+    pub fn snapshot(&self) -> GraphSnapshot {
+        let nodes = self
+            .nodes
+            .iter()
+            .filter(|n| !n.removed && !n.retiring)
+            .map(|n| NodeSnapshot {
+                name: n.name.clone(),
+                type_name: n.node.type_name().to_string(),
+                config: n.node.describe_config(),
+                state: n.node.save_state(),
+                feedback: n
+                    .inputs
+                    .iter()
+                    .filter_map(|edge| {
+                        let delayed = edge.delayed.as_ref()?;
+                        let input = &n.node.input_names()[edge.input_index];
+                        Some((input.clone(), delayed.clone()))
+                    })
+                    .collect(),
+            })
+            .collect();
+        GraphSnapshot {
+            time_sample: self.time_sample,
+            nodes,
+        }
+    }
+
+    /// Resume from a `GraphSnapshot`: set `time_sample` and load each node's state into
+    /// the node of the same name, which must have the same `type_name` and
+    /// `describe_config`. Every feedback block must match a feedback connection into that
+    /// node; nodes not in the snapshot keep their state. Scheduled events are not
+    /// changed. Any hot-swap crossfade is ended first.
+    pub fn restore(&mut self, snapshot: &GraphSnapshot) -> Result<(), GraphError> {
+        self.finish_crossfades();
+        let invalid = |node: &str, reason: String| GraphError::InvalidState {
+            node: node.to_string(),
+            reason,
+        };
+        // validate all entries before changing any state
+        let mut targets = Vec::with_capacity(snapshot.nodes.len());
+        for entry in &snapshot.nodes {
+            let nid = self.node_id(&entry.name)?;
+            let node = &self.nodes[nid.0];
+            if node.node.type_name() != entry.type_name {
+                return Err(invalid(
+                    &entry.name,
+                    format!(
+                        "expected {}, found {}",
+                        entry.type_name,
+                        node.node.type_name()
+                    ),
+                ));
+            }
+            // loading a state can replace configuration, such as the values of a
+            // `UGSelect`
+            let config = node.node.describe_config();
+            if config != entry.config {
+                return Err(invalid(
+                    &entry.name,
+                    format!(
+                        "expected configuration '{}', found '{}'",
+                        entry.config.as_deref().unwrap_or_default(),
+                        config.as_deref().unwrap_or_default()
+                    ),
+                ));
+            }
+            let mut edges = Vec::with_capacity(entry.feedback.len());
+            for (input, delayed) in &entry.feedback {
+                let edge = node.inputs.iter().position(|edge| {
+                    edge.is_feedback()
+                        && node.node.input_names()[edge.input_index] == *input
+                });
+                let Some(edge) = edge else {
+                    return Err(invalid(
+                        &entry.name,
+                        format!("no feedback connection into {input}"),
+                    ));
+                };
+                if delayed.len() != self.buffer_size {
+                    return Err(invalid(
+                        &entry.name,
+                        format!(
+                            "feedback block for {input} has {} samples, expected {}",
+                            delayed.len(),
+                            self.buffer_size
+                        ),
+                    ));
+                }
+                edges.push((edge, delayed));
+            }
+            targets.push((nid, entry, edges));
+        }
+        for (nid, entry, edges) in targets {
+            let node = &mut self.nodes[nid.0];
+            if let Some(state) = &entry.state {
+                node.node
+                    .load_state(state)
+                    .map_err(|reason| invalid(&entry.name, reason))?;
+            }
+            for (edge, delayed) in edges {
+                node.inputs[edge].delayed = Some(delayed.clone());
+            }
+        }
+        self.time_sample = snapshot.time_sample;
+        Ok(())
+    }
+
+    /// Schedule `event` on the port `label` (node.input or node.output) at the absolute
+    /// sample `time_sample`. Panics on an unknown label; see `try_schedule`.
+    pub fn schedule(&mut self, label: &str, time_sample: usize, event: GraphEvent) {
+        self.try_schedule(label, time_sample, event)
+            .unwrap_or_else(|e| panic!("{e}"));
+    }
+
+    /// Schedule `event` on the port `label` at the absolute sample `time_sample`.
+    ///
+    /// Events take effect on their exact sample within a block. An input label drives
+    /// that input: an `Override` or `Ramp` replaces any connected signal until a
+    /// `Release` hands the input back to its source. An output label, such as that of a
+    /// `UGConst`, overwrites the node's output after it is processed. Events scheduled in
+    /// the past take effect at the start of the next block.
+    pub fn try_schedule(
+        &mut self,
+        label: &str,
+        time_sample: usize,
+        event: GraphEvent,
+    ) -> Result<(), GraphError> {
+        let (node_name, port_name) = split_label(label)?;
+        let node_id = self.node_id(node_name)?;
+        let node = &self.nodes[node_id.0];
+
+        let port = if let Some(i) =
+            node.node.input_names().iter().position(|n| n == port_name)
+        {
+            AutomationPort::Input(i)
+        } else if let Some(&i) = node.name_to_output_index.get(port_name) {
+            AutomationPort::Output(i)
+        } else {
+            return Err(GraphError::UnknownPort {
+                node: node_name.to_string(),
+                port: port_name.to_string(),
+            });
+        };
+
+        let lane = match self
+            .automation
+            .iter()
+            .position(|a| a.node == node_id && a.port == port)
+        {
+            Some(pos) => &mut self.automation[pos],
+            None => {
+                // keep lanes sorted by node, so each node's lanes are contiguous
+                let pos = self.automation.partition_point(|a| a.node.0 <= node_id.0);
+                self.execution_order = None; // lane ranges are resolved with the order
+                self.automation
+                    .insert(pos, Automation::new(node_id, port, self.buffer_size));
+                &mut self.automation[pos]
+            }
+        };
+        lane.push(time_sample, event);
+        Ok(())
+    }
+
+    /// Render one block. After the first block following a change to the graph,
+    /// processing on one thread does not allocate; with more, handing a level to the
+    /// thread pool may.
+    pub fn process(&mut self) {
+        self.update_execution_node_ids();
+        let time_sample = self.time_sample;
+        let block = Block {
+            sample_rate: self.sample_rate,
+            buffer_size: self.buffer_size,
+            time_sample,
+        };
+
+        if self.pool.is_none() {
+            let order = self.execution_order.take().unwrap();
+            for &nid in &order {
+                self.process_serial(nid, &block);
+            }
+            self.execution_order = Some(order);
+        } else {
+            for li in 0..self.execution_levels.len() {
+                if let [task] = self.execution_levels[li].as_slice() {
+                    self.process_serial(task.node, &block);
+                } else {
+                    self.process_level(li, &block);
+                }
+            }
+        }
+
+        self.update_feedback_buffers();
+        self.time_sample += self.buffer_size;
+
+        // retiring nodes are always last; drop them once all crossfades are done
+        if self.nodes.last().is_some_and(|n| n.retiring)
+            && self.nodes.iter().all(|n| n.fade.is_none())
+        {
+            self.finish_crossfades();
+        }
+    }
+
+    /// Process one node on the calling thread.
+    fn process_serial(&mut self, nid: NodeId, block: &Block) {
+        let (left, right) = self.nodes.split_at_mut(nid.0);
+        let (node, rest) = right.split_first_mut().expect("valid index");
+        let (left, rest): (&[GraphNode], &[GraphNode]) = (left, rest);
+        // lanes are sorted by node
+        let start = self.automation.partition_point(|a| a.node.0 < nid.0);
+        let end = self.automation.partition_point(|a| a.node.0 <= nid.0);
+        let lanes = &mut self.automation[start..end];
+        let src = move |id: NodeId| {
+            let src = if id.0 < nid.0 {
+                &left[id.0]
+            } else {
+                &rest[id.0 - nid.0 - 1]
+            };
+            (src.outputs.as_slice(), src.output_rates.as_slice())
+        };
+        process_node(node, src, lanes, block);
+    }
+
+    /// Process the nodes of level `li` concurrently on the thread pool.
+    ///
+    /// The level's nodes are moved out of `nodes` while they run, so each thread owns the
+    /// node it processes; the sources they read are in earlier levels, and are shared
+    /// read-only.
+    fn process_level(&mut self, li: usize, block: &Block) {
+        let pool = self.pool.as_ref().expect("threads > 1");
+        let tasks = &self.execution_levels[li];
+        let mut work = Vec::with_capacity(tasks.len());
+        // tasks and lanes are both sorted by node, so each task's lanes follow the last
+        let mut lanes = self.automation.as_mut_slice();
+        let mut end = 0;
+        for task in tasks {
+            let rest = std::mem::take(&mut lanes);
+            let (own, rest) =
+                rest[task.lanes.start - end..].split_at_mut(task.lanes.len());
+            lanes = rest;
+            end = task.lanes.end;
+            let node =
+                std::mem::replace(&mut self.nodes[task.node.0], GraphNode::vacant());
+            work.push((node, own));
+        }
+
+        let nodes = &self.nodes;
+        pool.install(|| {
+            work.par_iter_mut().for_each(|(node, lanes)| {
+                let src = |id: NodeId| {
+                    let src = &nodes[id.0];
+                    (src.outputs.as_slice(), src.output_rates.as_slice())
+                };
+                process_node(node, src, lanes, block);
+            });
+        });
+        for (task, (node, _)) in tasks.iter().zip(work) {
+            self.nodes[task.node.0] = node;
+        }
+    }
+
+    /// End any hot-swap crossfade immediately, dropping retiring nodes.
+    fn finish_crossfades(&mut self) {
+        if self.nodes.iter().any(|n| n.retiring || n.fade.is_some()) {
+            self.nodes.retain(|n| !n.retiring);
+            for node in self.nodes.iter_mut() {
+                node.fade = None;
+            }
+            self.execution_order = None;
+        }
+    }
+
+    /// Replace this graph's nodes and connections with those of `new` while it runs.
+    ///
+    /// Nodes in `new` whose name, `type_name`, `describe_config` and ports match a node
+    /// here keep the running UGen, so phases, filter state and reverb tails carry over;
+    /// other nodes are added or removed. For the next `fade_samples` samples, kept nodes
+    /// crossfade inputs whose source changed, replaced nodes crossfade from the node they
+    /// replace, and removed nodes keep running unnamed to feed those crossfades.
+    /// `time_sample` and scheduled events on kept nodes are preserved.
+    pub fn hot_swap(&mut self, new: GenGraph, fade_samples: usize) {
+        assert_eq!(
+            (self.sample_rate, self.buffer_size),
+            (new.sample_rate, new.buffer_size),
+            "hot_swap requires matching sample_rate and buffer_size"
+        );
+        self.finish_crossfades();
+
+        let mut old_nodes = std::mem::take(&mut self.nodes);
+        let old_names: Vec<String> = old_nodes.iter().map(|n| n.name.clone()).collect();
+        let mut nodes = new.nodes;
+        if let Some(seed) = self.seed {
+            for node in nodes.iter_mut() {
+                node.node.set_graph_seed(derive_seed(seed, &node.name));
+            }
+        }
+        let mut old_to_new: Vec<Option<NodeId>> = vec![None; old_nodes.len()];
+        let mut kept = vec![false; old_nodes.len()];
+
+        for node in nodes.iter_mut() {
+            let Some(&oid) = self.name_to_node_id.get(&node.name) else {
+                continue;
+            };
+            let old = &mut old_nodes[oid.0];
+            if old.node.type_name() == node.node.type_name()
+                && old.node.describe_config() == node.node.describe_config()
+                && old.node.input_names() == node.node.input_names()
+                && old.node.output_names() == node.node.output_names()
+            {
+                std::mem::swap(&mut node.node, &mut old.node);
+                std::mem::swap(&mut node.outputs, &mut old.outputs);
+                old_to_new[oid.0] = Some(node.id);
+                kept[oid.0] = true;
+            }
+        }
+
+        // old nodes that are not kept become retiring nodes, appended after all live
+        // nodes
+        let live_count = nodes.len();
+        let mut previous_inputs: Vec<Vec<NodeEdge>> = Vec::with_capacity(old_nodes.len());
+        for (oid, mut old) in old_nodes.into_iter().enumerate() {
+            if kept[oid] || fade_samples == 0 || old.removed {
+                previous_inputs.push(std::mem::take(&mut old.inputs));
+                continue;
+            }
+            let id = NodeId(nodes.len());
+            old_to_new[oid] = Some(id);
+            old.id = id;
+            old.retiring = true;
+            previous_inputs.push(Vec::new());
+            nodes.push(old);
+        }
+        for node in nodes[live_count..].iter_mut() {
+            // every old node is now mapped, so retiring inputs can be remapped
+            for edge in node.inputs.iter_mut() {
+                edge.src = old_to_new[edge.src.0].expect("mapped node");
+            }
+        }
+
+        for (oid, prev_edges) in previous_inputs.iter_mut().enumerate() {
+            let Some(&nid) = new.name_to_node_id.get(&old_names[oid]) else {
+                continue;
+            };
+            let mut fade = Crossfade {
+                inputs: Vec::new(),
+                outputs_from: None,
+                pos: 0,
+                len: fade_samples,
+            };
+            if kept[oid] {
+                let node = &mut nodes[nid.0];
+                for i in 0..node.node.input_names().len() {
+                    let prev_edge = prev_edges.iter_mut().find(|e| e.input_index == i);
+                    let prev = prev_edge.as_ref().and_then(|e| {
+                        old_to_new[e.src.0].map(|src| (src, e.output_index))
+                    });
+                    let edge = node.inputs.iter_mut().find(|e| e.input_index == i);
+                    let current = edge.as_ref().map(|e| (e.src, e.output_index));
+                    if let (Some(edge), Some(prev_edge)) = (edge, prev_edge) {
+                        // an unchanged feedback edge keeps its delayed samples
+                        if prev == current
+                            && edge.is_feedback()
+                            && prev_edge.is_feedback()
+                        {
+                            edge.delayed = prev_edge.delayed.take();
+                        }
+                    }
+                    if prev != current && fade_samples > 0 {
+                        fade.inputs.push((i, prev, vec![0.0; self.buffer_size]));
+                    }
+                }
+            } else if let Some(r) = old_to_new[oid] {
+                fade.outputs_from = Some(r);
+            }
+            if !fade.inputs.is_empty() || fade.outputs_from.is_some() {
+                nodes[nid.0].fade = Some(fade);
+            }
+        }
+
+        // remap scheduled events of kept nodes; events on other nodes are dropped
+        let mut automation = new.automation;
+        for mut lane in std::mem::take(&mut self.automation) {
+            if kept[lane.node.0] {
+                lane.node = old_to_new[lane.node.0].expect("kept node");
+                automation.push(lane);
+            }
+        }
+
+        let mut fades: Vec<Option<Crossfade>> =
+            nodes.iter_mut().map(|n| n.fade.take()).collect();
+        self.nodes = nodes;
+        self.name_to_node_id = new.name_to_node_id;
+        self.free_ids = new.free_ids;
+        automation.sort_by_key(|a| a.node.0);
+        self.automation = automation;
+        self.execution_order = None;
+        self.update_ranks();
+
+        // add fades one at a time, dropping any previous source that would close a cycle
+        // (that input fades from its default, or that output switches at once)
+        for (nid, fade) in fades.iter_mut().enumerate() {
+            let Some(mut fade) = fade.take() else {
+                continue;
+            };
+            let id = NodeId(nid);
+            for (_, prev, _) in fade.inputs.iter_mut() {
+                if prev.is_some_and(|(src, _)| !self.rank_before(src, id)) {
+                    *prev = None;
+                }
+            }
+            if fade.outputs_from.is_some_and(|r| !self.rank_before(r, id)) {
+                fade.outputs_from = None;
+            }
+            self.nodes[nid].fade = Some(fade);
+        }
+    }
+
+    /// Copy this block's source outputs into the delay buffers of all feedback edges, to
+    /// be read on the next block.
+    fn update_feedback_buffers(&mut self) {
+        for node_index in 0..self.nodes.len() {
+            let (left, right) = self.nodes.split_at_mut(node_index);
+            let (node, rest) = right.split_first_mut().expect("valid index");
+            for edge in node.inputs.iter_mut() {
+                let Some(delayed) = edge.delayed.as_mut() else {
+                    continue;
+                };
+                let src = if edge.src.0 < node_index {
+                    &left[edge.src.0].outputs[edge.output_index]
+                } else if edge.src.0 == node_index {
+                    &node.outputs[edge.output_index]
+                } else {
+                    &rest[edge.src.0 - node_index - 1].outputs[edge.output_index]
+                };
+                delayed.copy_from_slice(src);
+            }
+        }
+    }
+
+    // Given a two-part name node.output, return a slice of the samples for that node and output,
+    pub fn get_output_by_label(&self, label: &str) -> &[Sample] {
+        self.try_get_output(label).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Like `get_output_by_label`, but returns a `GraphError` for a malformed label or an
+    /// unknown node or output.
+    pub fn try_get_output(&self, label: &str) -> Result<&[Sample], GraphError> {
+        let (node_name, output_name) = split_label(label)?;
+        let node = &self.nodes[self.node_id(node_name)?.0];
+
+        let index = node.name_to_output_index.get(output_name).ok_or_else(|| {
+            GraphError::UnknownPort {
+                node: node_name.to_string(),
+                port: output_name.to_string(),
+            }
+        })?;
+
+        Ok(&node.outputs[*index])
+    }
+
+    // NOTE: this is a bit heavy as we create a Vec for each call
+    pub fn get_outputs(&mut self) -> Vec<(String, &Vec<Sample>)> {
+        let mut result = Vec::new();
+
+        for node_id in self.live_order() {
+            let node = &self.nodes[node_id.0];
+            let name = &node.name;
+            let output_names = node.node.output_names();
+
+            for (i, output_name) in output_names.iter().enumerate() {
+                let label = format!("{}.{}", name, output_name);
+                result.push((label, &node.outputs[i]));
+            }
+        }
+        result
+    }
+
+    // Return all node.output labels
+    pub fn get_output_names_for(&self, node_name: &str) -> Option<Vec<String>> {
+        let node = &self.nodes[self.name_to_node_id.get(node_name)?.0];
         Some(
             node.node
                 .output_names()
@@ -286,8 +1900,7 @@ impl GenGraph {
     }
 
     pub fn get_last_node_output_names(&mut self) -> Option<Vec<String>> {
-        self.update_execution_node_ids();
-        let &node_id = self.execution_order.as_ref().unwrap().last()?;
+        let node_id = *self.live_order().last()?;
         let node = &self.nodes[node_id.0];
         let name = &node.name;
         Some(
@@ -300,10 +1913,8 @@ impl GenGraph {
     }
 
     pub fn get_node_output_names(&mut self) -> Vec<String> {
-        self.update_execution_node_ids();
-
         let mut result = Vec::new();
-        for &node_id in self.execution_order.as_ref().unwrap() {
+        for node_id in self.live_order() {
             let node = &self.nodes[node_id.0];
             let name = &node.name;
             let output_names = node.node.output_names();
@@ -317,8 +1928,6 @@ impl GenGraph {
     //--------------------------------------------------------------------------
 
     pub fn to_dot(&mut self) -> String {
-        self.update_execution_node_ids();
-
         let mut dot = String::new();
         dot.push_str("digraph GenGraph {\n");
         dot.push_str("  rankdir=TB;\n");
@@ -327,7 +1936,7 @@ impl GenGraph {
         dot.push_str("  edge [color=\"#c4c5bf\", arrowsize=0.5];\n");
 
         // Define nodes with input and output labels
-        for &node_id in self.execution_order.as_ref().unwrap() {
+        for node_id in self.live_order() {
             let node = &self.nodes[node_id.0];
 
             let inputs = node
@@ -356,22 +1965,27 @@ impl GenGraph {
 
             dot.push_str(&format!(
                 "  {} [label=\"{}\"];\n",
-                node.name.replace('-', "_"), // sanitize for dot
+                node.name.replace(['-', '.'], "_"), // sanitize for dot
                 label
             ));
         }
 
         // Define edges
-        for &node_id in self.execution_order.as_ref().unwrap() {
+        for node_id in self.live_order() {
             let node = &self.nodes[node_id.0];
             for edge in &node.inputs {
                 let src_node = &self.nodes[edge.src.0];
                 dot.push_str(&format!(
-                    "  {}:out{}:s -> {}:in{}:n;\n",
-                    src_node.name.replace('-', "_"),
+                    "  {}:out{}:s -> {}:in{}:n{};\n",
+                    src_node.name.replace(['-', '.'], "_"),
                     edge.output_index,
-                    node.name.replace('-', "_"),
-                    edge.input_index
+                    node.name.replace(['-', '.'], "_"),
+                    edge.input_index,
+                    if edge.is_feedback() {
+                        " [style=dashed]"
+                    } else {
+                        ""
+                    }
                 ));
             }
         }
@@ -401,10 +2015,8 @@ impl GenGraph {
 
     //--------------------------------------------------------------------------
     pub fn describe_json(&mut self) -> Value {
-        self.update_execution_node_ids();
-
         let mut result = Vec::new();
-        for &node_id in self.execution_order.as_ref().unwrap() {
+        for node_id in self.live_order() {
             let node = &self.nodes[node_id.0];
 
             let name = self
@@ -440,16 +2052,24 @@ impl GenGraph {
                                 .map(|s| s.as_str())
                                 .unwrap_or("???");
 
+                            let mut connected_to = json!({
+                                "node": src_name,
+                                "output": output_name
+                            });
+                            if edge.is_feedback() {
+                                connected_to["feedback"] = json!(true);
+                            }
                             json!({
                                 "name": input_name,
-                                "connected_to": {
-                                    "node": src_name,
-                                    "output": output_name
-                                }
+                                "connected_to": connected_to
                             })
                         }
                         None => {
-                            let default = node.node.default_input(input_name);
+                            let default = input_default(
+                                node.node.as_ref(),
+                                &node.input_defaults,
+                                i,
+                            );
                             json!({
                                 "name": input_name,
                                 "default": default
@@ -507,7 +2127,11 @@ impl GenGraph {
                 if let Some(obj) = input.get("connected_to") {
                     let src_node = obj["node"].as_str().unwrap_or("?");
                     let src_output = obj["output"].as_str().unwrap_or("?");
-                    lines.push(format!("{} ← {}.{}", label, src_node, src_output));
+                    if obj.get("feedback").is_some() {
+                        lines.push(format!("{} ↺ {}.{}", label, src_node, src_output));
+                    } else {
+                        lines.push(format!("{} ← {}.{}", label, src_node, src_output));
+                    }
                 } else if let Some(val) = input.get("default").and_then(|v| v.as_f64()) {
                     lines.push(format!("{} ←= {:.3}", label, val));
                 } else {
@@ -569,7 +2193,9 @@ macro_rules! connect_many {
 mod tests {
     use super::*;
     use crate::{
-        ModeRound, UGAsHz, UGClock, UGConst, UGLowPass, UGRound, UGSine, UnitRate,
+        GraphEvent, ModeRound, ModeSelect, UGAsHz, UGBassDrum, UGClock, UGConst,
+        UGLowPass, UGMult, UGReverb, UGRound, UGSelect, UGSine, UGString, UGSum, UGWhite,
+        UnitRate,
     };
 
     #[test]
@@ -622,4 +2248,1102 @@ mod tests {
             "digraph GenGraph {\n  rankdir=TB;\n  bgcolor=\"#12131E\";\n  node [shape=record, fontsize=9, fontname=\"Arial\", color=\"#c4c5bf\", fontcolor=\"#c4c5bf\"];\n  edge [color=\"#c4c5bf\", arrowsize=0.5];\n  note [label=\"{{}|UGConst: note|{<out0> out}}\"];\n  conv [label=\"{{<in0> in}|UGAsHz: conv|{<out0> out}}\"];\n  osc [label=\"{{<in0> freq|<in1> phase|<in2> min|<in3> max}|UGSine: osc|{<out0> wave|<out1> trigger}}\"];\n  note:out0:s -> conv:in0:n;\n  conv:out0:s -> osc:in0:n;\n}\n"
         );
     }
+
+    #[test]
+    fn test_feedback_accumulator_a() {
+        let mut g = GenGraph::new(8.0, 8);
+        register_many![g,
+            "one" => 1,
+            "acc" => UGSum::new(2),
+        ];
+        g.connect("one.out", "acc.in1");
+        g.connect_feedback("acc.out", "acc.in2");
+
+        g.process();
+        assert_eq!(g.get_output_by_label("acc.out"), vec![1.0; 8]);
+        g.process();
+        assert_eq!(g.get_output_by_label("acc.out"), vec![2.0; 8]);
+        g.process();
+        assert_eq!(g.get_output_by_label("acc.out"), vec![3.0; 8]);
+    }
+
+    #[test]
+    fn test_feedback_cycle_between_nodes_a() {
+        let mut g = GenGraph::new(8.0, 8);
+        register_many![g,
+            "one" => 1,
+            "a" => UGSum::new(2),
+            "b" => UGSum::new(2),
+        ];
+        g.connect("one.out", "a.in1");
+        g.connect("a.out", "b.in1");
+        g.connect("one.out", "b.in2");
+        g.connect_feedback("b.out", "a.in2");
+
+        // every node in the loop is scheduled
+        assert_eq!(g.get_execution_names(), vec!["one", "a", "b"]);
+
+        g.process();
+        assert_eq!(g.get_output_by_label("b.out"), vec![2.0; 8]);
+        g.process();
+        // a = 1 + 2 (previous b), b = a + 1
+        assert_eq!(g.get_output_by_label("b.out"), vec![4.0; 8]);
+    }
+
+    #[test]
+    #[should_panic(expected = "would create a cycle")]
+    fn test_connect_cycle_without_feedback_panics() {
+        let mut g = GenGraph::new(8.0, 8);
+        register_many![g,
+            "a" => UGSum::new(2),
+            "b" => UGSum::new(2),
+        ];
+        g.connect("a.out", "b.in1");
+        g.connect("b.out", "a.in1");
+    }
+
+    #[test]
+    #[should_panic(expected = "would create a cycle")]
+    fn test_connect_self_without_feedback_panics() {
+        let mut g = GenGraph::new(8.0, 8);
+        g.add_node("a", Box::new(UGSum::new(2)));
+        g.connect("a.out", "a.in1");
+    }
+
+    #[test]
+    fn test_feedback_describe_and_dot_a() {
+        let mut g = GenGraph::new(8.0, 8);
+        g.add_node("acc", Box::new(UGSum::new(2)));
+        g.connect_feedback("acc.out", "acc.in2");
+
+        let json = g.describe_json();
+        assert_eq!(
+            json[0]["inputs"][1]["connected_to"].to_string(),
+            r#"{"feedback":true,"node":"acc","output":"out"}"#
+        );
+        assert!(g.describe().contains("in2 ↺ acc.out"));
+        assert!(
+            g.to_dot()
+                .contains("acc:out0:s -> acc:in1:n [style=dashed];")
+        );
+    }
+
+    #[test]
+    fn test_try_add_node_duplicate() {
+        let mut g = GenGraph::new(8.0, 8);
+        assert!(g.try_add_node("a", Box::new(UGConst::new(1.0))).is_ok());
+        assert_eq!(
+            g.try_add_node("a", Box::new(UGConst::new(2.0))),
+            Err(GraphError::DuplicateNode("a".to_string()))
+        );
+        assert_eq!(g.len(), 1);
+    }
+
+    #[test]
+    fn test_try_connect_errors() {
+        let mut g = GenGraph::new(8.0, 8);
+        register_many![g,
+            "a" => UGSum::new(2),
+            "b" => UGSum::new(2),
+        ];
+        assert_eq!(
+            g.try_connect("a.out", "c.in1"),
+            Err(GraphError::UnknownNode("c".to_string()))
+        );
+        assert_eq!(
+            g.try_connect("a.foo", "b.in1"),
+            Err(GraphError::UnknownPort {
+                node: "a".to_string(),
+                port: "foo".to_string()
+            })
+        );
+        assert_eq!(
+            g.try_connect("a.out", "b.in9"),
+            Err(GraphError::UnknownPort {
+                node: "b".to_string(),
+                port: "in9".to_string()
+            })
+        );
+        assert_eq!(
+            g.try_connect("a", "b.in1"),
+            Err(GraphError::MalformedLabel("a".to_string()))
+        );
+        assert_eq!(g.try_connect("a.out", "b.in1"), Ok(()));
+        assert_eq!(
+            g.try_connect("a.out", "b.in1"),
+            Err(GraphError::InputAlreadyConnected {
+                node: "b".to_string(),
+                input: "in1".to_string()
+            })
+        );
+        assert_eq!(
+            g.try_connect("b.out", "a.in1"),
+            Err(GraphError::Cycle {
+                src: "b".to_string(),
+                dst: "a".to_string()
+            })
+        );
+        assert_eq!(g.try_connect_feedback("b.out", "a.in1"), Ok(()));
+    }
+
+    #[test]
+    fn test_connect_cycle_reverse_order_a() {
+        // each connection runs from a later node to an earlier one
+        let mut g = GenGraph::new(8.0, 8);
+        register_many![g,
+            "a" => UGSum::new(2),
+            "b" => UGSum::new(2),
+            "c" => UGSum::new(2),
+            "one" => 1,
+        ];
+        g.connect("one.out", "c.in1");
+        g.connect("c.out", "b.in1");
+        g.connect("b.out", "a.in1");
+        g.connect("one.out", "a.in2");
+        assert_eq!(
+            g.try_connect("a.out", "c.in2"),
+            Err(GraphError::Cycle {
+                src: "a".to_string(),
+                dst: "c".to_string()
+            })
+        );
+        g.connect("one.out", "b.in2");
+        g.process();
+        assert_eq!(g.get_output_by_label("a.out"), vec![3.0; 8]);
+    }
+
+    #[test]
+    fn test_connect_cycle_rank_precision_a() {
+        // each node is ranked between "one" and the node before it, halving the gap
+        // until it cannot be split
+        let mut g = GenGraph::new(8.0, 8);
+        let n = 1200;
+        g.add_node("one", Box::new(UGConst::new(1.0)));
+        for i in 0..n {
+            g.add_node(format!("s{i}"), Box::new(UGSum::new(2)));
+            g.connect("one.out", &format!("s{i}.in1"));
+            if i > 0 {
+                g.connect(&format!("s{i}.out"), &format!("s{}.in2", i - 1));
+            }
+        }
+        assert_eq!(
+            g.try_connect("s0.out", &format!("s{}.in2", n - 1)),
+            Err(GraphError::Cycle {
+                src: "s0".to_string(),
+                dst: format!("s{}", n - 1)
+            })
+        );
+        g.process();
+        assert_eq!(g.get_output_by_label("s0.out"), vec![n as Sample; 8]);
+    }
+
+    #[test]
+    fn test_remove_node_a() {
+        let mut g = GenGraph::new(8.0, 8);
+        register_many![g,
+            "a" => UGConst::new(2.0),
+            "b" => UGConst::new(3.0),
+            "s" => UGSum::new(2),
+            "m" => UGSum::new(2),
+        ];
+        g.connect("a.out", "s.in1");
+        g.connect("b.out", "s.in2");
+        g.connect("s.out", "m.in1");
+        g.connect_feedback("m.out", "m.in2");
+        g.schedule("b.out", 0, GraphEvent::Override(5.0));
+        let m_id = g.node_id("m").unwrap();
+        g.process();
+        assert_eq!(g.get_output_by_label("m.out")[0], 7.0);
+
+        g.remove_node("b");
+        assert_eq!(g.len(), 3);
+        assert_eq!(g.get_execution_names(), vec!["a", "s", "m"]);
+        assert_eq!(g.node_id("m").unwrap(), m_id);
+        assert_eq!(g.automation.len(), 0);
+        g.process();
+        // s.in2 falls back to its default of 0; m adds the previous block
+        assert_eq!(g.get_output_by_label("s.out")[0], 2.0);
+        assert_eq!(g.get_output_by_label("m.out")[0], 9.0);
+
+        // the name can be reused
+        g.add_node("b", Box::new(UGConst::new(1.0)));
+        g.connect("b.out", "s.in2");
+        g.process();
+        assert_eq!(g.get_output_by_label("s.out")[0], 3.0);
+        assert_eq!(
+            g.try_remove_node("x"),
+            Err(GraphError::UnknownNode("x".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_remove_node_churn_a() {
+        let mut g = GenGraph::new(8.0, 8);
+        register_many![g,
+            "one" => 1,
+            "sum" => UGSum::new(2),
+        ];
+        g.connect("one.out", "sum.in1");
+        for i in 0..50 {
+            let name = format!("v{i}");
+            let id = g.add_node(name.clone(), Box::new(UGConst::new(i as Sample)));
+            assert_eq!(id, NodeId(2));
+            g.connect(&format!("{name}.out"), "sum.in2");
+            let t = g.time_sample() + 4;
+            g.schedule(&format!("{name}.out"), t, GraphEvent::Override(-1.0));
+            g.process();
+            assert_eq!(g.get_output_by_label("sum.out")[0], 1.0 + i as Sample);
+            assert_eq!(g.get_output_by_label("sum.out")[4], 0.0);
+            g.remove_node(&name);
+        }
+        assert_eq!(g.nodes.len(), 3);
+        assert!(g.automation.is_empty());
+        g.process();
+        assert_eq!(g.get_output_by_label("sum.out"), vec![1.0; 8]);
+    }
+
+    #[test]
+    fn test_remove_node_parallel_and_hot_swap() {
+        let mut serial = voices(1);
+        let mut parallel = voices(3);
+        for g in [&mut serial, &mut parallel] {
+            g.process();
+            g.remove_node("osc1");
+            g.remove_node("kick");
+        }
+        let labels = serial.get_node_output_names();
+        assert!(!labels.iter().any(|l| l.starts_with("kick.")));
+        for _ in 0..3 {
+            serial.process();
+            parallel.process();
+            for label in &labels {
+                assert_eq!(
+                    serial.get_output_by_label(label),
+                    parallel.get_output_by_label(label),
+                    "{label}"
+                );
+            }
+        }
+        // removed nodes are not crossfaded out by a hot swap
+        parallel.hot_swap(voices(3), 16);
+        assert!(parallel.nodes.iter().all(|n| !n.removed));
+        assert_eq!(parallel.nodes.iter().filter(|n| n.retiring).count(), 0);
+        parallel.process();
+        assert_eq!(parallel.len(), voices(1).len());
+    }
+
+    #[test]
+    fn test_disconnect_a() {
+        let mut g = GenGraph::new(8.0, 8);
+        register_many![g,
+            "a" => UGConst::new(2.0),
+            "s" => UGSum::new(2),
+        ];
+        g.connect("a.out", "s.in1");
+        g.connect_feedback("s.out", "s.in2");
+        assert_eq!(
+            g.try_disconnect("a.out", "s.in2"),
+            Err(GraphError::NotConnected {
+                src: "a.out".to_string(),
+                dst: "s.in2".to_string()
+            })
+        );
+        g.disconnect("s.out", "s.in2");
+        g.process();
+        g.process();
+        assert_eq!(g.get_output_by_label("s.out")[0], 2.0);
+        g.disconnect("a.out", "s.in1");
+        g.process();
+        assert_eq!(g.get_output_by_label("s.out")[0], 0.0);
+        // the input can be connected again
+        g.connect("a.out", "s.in2");
+        g.process();
+        assert_eq!(g.get_output_by_label("s.out")[0], 2.0);
+    }
+
+    #[test]
+    fn test_replace_node_a() {
+        let mut g = GenGraph::new(8.0, 8);
+        register_many![g,
+            "a" => UGConst::new(2.0),
+            "b" => UGConst::new(3.0),
+            "op" => UGSum::new(2),
+            "r" => UGRound::new(0, ModeRound::Round),
+        ];
+        g.connect("a.out", "op.in1");
+        g.connect("b.out", "op.in2");
+        g.connect("op.out", "r.in");
+        g.process();
+        assert_eq!(g.get_output_by_label("r.out")[0], 5.0);
+
+        // in1, in2 and out exist on the new UGen, so all connections are kept
+        g.replace_node("op", Box::new(crate::UGMult::new(2)));
+        g.process();
+        assert_eq!(g.get_output_by_label("r.out")[0], 6.0);
+
+        // a UGen without in1 or in2 drops those connections but keeps `out`
+        g.replace_node("op", Box::new(UGConst::new(4.0)));
+        assert_eq!(g.get_output_names_for("op").unwrap(), vec!["op.out"]);
+        g.process();
+        assert_eq!(g.get_output_by_label("r.out")[0], 4.0);
+        assert_eq!(g.live_connections().len(), 1);
+        assert!(
+            g.try_replace_node("x", Box::new(UGConst::new(0.0)))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_set_input_default_a() {
+        let mut g = GenGraph::new(8.0, 8);
+        register_many![g,
+            "s" => UGSine::new(),
+            "a" => UGConst::new(2.0),
+            "sum" => UGSum::new(2),
+        ];
+        g.set_input_default("s.freq", 2.0);
+        g.set_input_default("s.min", 0.0);
+        g.set_input_default("sum.in2", 3.0);
+        g.connect("a.out", "sum.in1");
+        g.process();
+        // 2 Hz at 8 Hz: a quarter period per sample
+        let wave: Vec<Sample> = g
+            .get_output_by_label("s.wave")
+            .iter()
+            .map(|v| (v * 10.0).round() / 10.0)
+            .collect();
+        assert_eq!(wave, vec![1.0, 0.5, 0.0, 0.5, 1.0, 0.5, 0.0, 0.5]);
+        assert_eq!(g.get_output_by_label("sum.out"), vec![5.0; 8]);
+
+        let inputs = &g.describe_json()[0]["inputs"];
+        assert_eq!(inputs[0]["default"], json!(2.0));
+        assert_eq!(inputs[1]["default"], json!(0.0));
+
+        // a connection takes precedence, and setting again replaces the value
+        g.set_input_default("sum.in1", 100.0);
+        g.set_input_default("sum.in2", 4.0);
+        g.process();
+        assert_eq!(g.get_output_by_label("sum.out"), vec![6.0; 8]);
+
+        // the default is kept by `replace_node` when the input exists
+        g.replace_node("sum", Box::new(crate::UGMult::new(2)));
+        g.process();
+        assert_eq!(g.get_output_by_label("sum.out"), vec![8.0; 8]);
+
+        assert_eq!(
+            g.try_set_input_default("s.wave", 1.0),
+            Err(GraphError::UnknownPort {
+                node: "s".to_string(),
+                port: "wave".to_string()
+            })
+        );
+    }
+
+    fn compilable() -> GenGraph {
+        let mut g = GenGraph::new(8000.0, 8);
+        register_many![g,
+            "note" => UGConst::new(57.0),
+            "octave" => UGConst::new(12.0),
+            "pitch" => UGSum::new(2),
+            "hz" => UGAsHz::new(UnitRate::Midi),
+            "half" => UGConst::new(0.5),
+            "s" => UGSine::new(),
+            "amp" => UGMult::new(2),
+            "noise" => UGWhite::new(Some(3)),
+            "lpf" => UGLowPass::new(12.0),
+        ];
+        g.connect("note.out", "pitch.in1");
+        g.connect("octave.out", "pitch.in2");
+        g.connect("pitch.out", "hz.in");
+        g.connect("hz.out", "s.freq");
+        g.connect("s.wave", "amp.in1");
+        g.connect("half.out", "amp.in2");
+        g.connect("noise.out", "lpf.in");
+        g
+    }
+
+    #[test]
+    fn test_compile_a() {
+        let mut g1 = compilable();
+        let mut g2 = compilable();
+        let labels = vec!["amp.out".to_string()];
+        let removed = g2.compile(&labels).unwrap();
+        assert_eq!(
+            removed,
+            vec!["note", "octave", "half", "noise", "pitch", "lpf", "hz"]
+        );
+        assert_eq!(g2.get_execution_names(), vec!["s", "amp"]);
+
+        // constants are now input defaults
+        let inputs = &g2.describe_json()[0]["inputs"];
+        assert_eq!(inputs[0]["default"], json!(440.0));
+        for _ in 0..4 {
+            g1.process();
+            g2.process();
+            assert_eq!(
+                g1.get_output_by_label("amp.out"),
+                g2.get_output_by_label("amp.out")
+            );
+        }
+    }
+
+    #[test]
+    fn test_compile_b() {
+        // a requested constant is kept, and an automated constant is not folded
+        let mut g = compilable();
+        g.schedule("half.out", 8, GraphEvent::Override(0.25));
+        let labels = vec!["amp.out".to_string(), "pitch.out".to_string()];
+        let removed = g.compile(&labels).unwrap();
+        assert_eq!(removed, vec!["note", "octave", "noise", "lpf", "hz"]);
+        assert_eq!(g.get_execution_names(), vec!["pitch", "half", "s", "amp"]);
+        g.process();
+        assert_eq!(g.get_output_by_label("pitch.out"), vec![69.0; 8]);
+        g.process();
+        assert_eq!(g.get_output_by_label("half.out"), vec![0.25; 8]);
+
+        assert_eq!(
+            g.compile(&["s.out".to_string()]),
+            Err(GraphError::UnknownPort {
+                node: "s".to_string(),
+                port: "out".to_string()
+            })
+        );
+    }
+
+    fn noisy(seed: u64) -> GenGraph {
+        let mut g = GenGraph::new(8000.0, 8);
+        g.set_seed(seed);
+        register_many![g,
+            "a" => UGWhite::new(None),
+            "b" => UGWhite::new(None),
+            "c" => UGWhite::new(Some(3)),
+            "s" => UGString::new(440.0, 0.99, None),
+        ];
+        g.process();
+        g
+    }
+
+    #[test]
+    fn test_set_seed_a() {
+        let (g1, g2, g3) = (noisy(7), noisy(7), noisy(8));
+        assert_eq!(g1.seed(), Some(7));
+        for label in ["a.out", "b.out", "c.out", "s.out"] {
+            assert_eq!(g1.get_output_by_label(label), g2.get_output_by_label(label));
+        }
+        // seeds differ by node name and by master seed
+        assert_ne!(
+            g1.get_output_by_label("a.out"),
+            g1.get_output_by_label("b.out")
+        );
+        assert_ne!(
+            g1.get_output_by_label("a.out"),
+            g3.get_output_by_label("a.out")
+        );
+        // a node constructed with a seed keeps it
+        assert_eq!(
+            g1.get_output_by_label("c.out"),
+            g3.get_output_by_label("c.out")
+        );
+    }
+
+    #[test]
+    fn test_set_seed_b() {
+        // seeding before or after adding nodes gives the same seeds
+        let mut g1 = GenGraph::new(8000.0, 8);
+        g1.add_node("a", Box::new(UGWhite::new(None)));
+        g1.set_seed(7);
+        g1.process();
+        let g2 = noisy(7);
+        assert_eq!(
+            g1.get_output_by_label("a.out"),
+            g2.get_output_by_label("a.out")
+        );
+
+        // replaced and hot-swapped nodes are seeded by name
+        let mut g3 = GenGraph::new(8000.0, 8);
+        g3.set_seed(7);
+        g3.add_node("a", Box::new(UGConst::new(0.0)));
+        g3.replace_node("a", Box::new(UGWhite::new(None)));
+        g3.process();
+        assert_eq!(
+            g3.get_output_by_label("a.out"),
+            g2.get_output_by_label("a.out")
+        );
+
+        let mut g4 = GenGraph::new(8000.0, 8);
+        g4.set_seed(7);
+        let mut new = GenGraph::new(8000.0, 8);
+        new.add_node("a", Box::new(UGWhite::new(None)));
+        g4.hot_swap(new, 0);
+        g4.process();
+        assert_eq!(
+            g4.get_output_by_label("a.out"),
+            g2.get_output_by_label("a.out")
+        );
+    }
+
+    #[test]
+    fn test_validate_a() {
+        let mut g = compilable();
+        let issues = g.validate(&["amp.out".to_string()]).unwrap();
+        assert_eq!(
+            issues,
+            vec![
+                GraphIssue::ConstantSubgraph {
+                    node: "hz".to_string()
+                },
+                GraphIssue::Dangling {
+                    node: "lpf".to_string()
+                },
+            ]
+        );
+        assert!(issues.iter().all(|i| i.severity() == Severity::Warning));
+        assert_eq!(
+            issues[1].to_string(),
+            "warning: No output of node lpf is used."
+        );
+        // nothing is folded or removed
+        assert_eq!(g.len(), 9);
+        assert_eq!(g.validate(&[]).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_validate_b() {
+        let mut g = GenGraph::new(8000.0, 8);
+        register_many![g,
+            "hz" => UGAsHz::new(UnitRate::Midi),
+            "s" => UGSine::new(),
+            "a" => UGSum::new(2),
+            "b" => UGSum::new(2),
+        ];
+        g.connect("hz.out", "s.freq");
+        g.connect("s.wave", "a.in1");
+        g.connect("a.out", "b.in1");
+        g.set_input_default("a.in2", 0.0);
+        g.set_input_default("b.in2", 0.0);
+        // a cycle cannot be connected, so close one directly
+        let (a, b) = (g.node_id("a").unwrap(), g.node_id("b").unwrap());
+        g.nodes[a.0].inputs.retain(|e| e.input_index != 1);
+        g.nodes[a.0].inputs.push(NodeEdge {
+            src: b,
+            output_index: 0,
+            input_index: 1,
+            delayed: None,
+        });
+        g.execution_order = None;
+
+        let issues = g.validate(&["s.wave".to_string()]).unwrap();
+        assert_eq!(
+            issues,
+            vec![
+                GraphIssue::MissingInput {
+                    node: "hz".to_string(),
+                    input: "in".to_string()
+                },
+                GraphIssue::Unordered {
+                    node: "a".to_string()
+                },
+                GraphIssue::Unordered {
+                    node: "b".to_string()
+                },
+            ]
+        );
+        assert_eq!(issues[0].severity(), Severity::Error);
+        assert_eq!(
+            issues[0].to_string(),
+            "error: Input hz.in has no connection and no default, and reads zero."
+        );
+        assert_eq!(
+            g.validate(&["x.out".to_string()]),
+            Err(GraphError::UnknownNode("x".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_control_rate_a() {
+        let mut g = GenGraph::new(8000.0, 8);
+        register_many![g,
+            "note" => 69,
+            "hz" => UGAsHz::new(UnitRate::Midi),
+            "osc" => UGSine::new(),
+            "sum" => UGSum::new(2),
+        ];
+        g.connect("note.out", "hz.in");
+        g.connect("hz.out", "osc.freq");
+        g.connect("hz.out", "sum.in1");
+        g.connect("osc.wave", "sum.in2");
+        g.process();
+
+        let rates = |g: &GenGraph, name: &str| {
+            g.nodes[g.name_to_node_id[name].0].output_rates.clone()
+        };
+        assert_eq!(rates(&g, "note"), vec![Rate::Control]);
+        assert_eq!(rates(&g, "hz"), vec![Rate::Control]);
+        assert_eq!(rates(&g, "osc"), vec![Rate::Audio, Rate::Audio]);
+        assert_eq!(rates(&g, "sum"), vec![Rate::Audio]);
+        // control-rate outputs read as a full block
+        assert_eq!(g.get_output_by_label("hz.out"), vec![440.0; 8]);
+        let sum = g.get_output_by_label("sum.out").to_vec();
+        let wave = g.get_output_by_label("osc.wave");
+        for (s, w) in sum.iter().zip(wave) {
+            assert_eq!(*s, 440.0 + w);
+        }
+    }
+
+    #[test]
+    fn test_control_rate_automated_output_a() {
+        let mut g = GenGraph::new(8.0, 8);
+        register_many![g,
+            "c" => 1,
+            "hz" => UGAsHz::new(UnitRate::Hz),
+        ];
+        g.connect("c.out", "hz.in");
+        g.schedule("c.out", 4, GraphEvent::Override(2.0));
+        g.process();
+        let rates = &g.nodes[g.name_to_node_id["c"].0].output_rates;
+        assert_eq!(rates, &vec![Rate::Audio]);
+        assert_eq!(
+            g.get_output_by_label("hz.out"),
+            vec![1.0, 1.0, 1.0, 1.0, 2.0, 2.0, 2.0, 2.0]
+        );
+    }
+
+    #[test]
+    fn test_control_rate_held_a() {
+        let mut g = GenGraph::new(8.0, 8);
+        g.add_node("hz", Box::new(UGAsHz::new(UnitRate::Hz)));
+        g.set_input_default("hz.in", 2.0);
+        for _ in 0..2 {
+            g.process();
+            assert_eq!(g.get_output_by_label("hz.out"), vec![2.0; 8]);
+        }
+        // a new value refills the whole block
+        g.set_input_default("hz.in", 4.0);
+        g.process();
+        assert_eq!(g.get_output_by_label("hz.out"), vec![4.0; 8]);
+        assert_eq!(g.nodes[0].held, vec![Some(4.0)]);
+    }
+
+    fn stateful() -> GenGraph {
+        let mut g = GenGraph::new(8000.0, 64);
+        g.add_node("clock", Box::new(UGClock::new(20.0, UnitRate::Hz)));
+        g.add_node(
+            "sel",
+            Box::new(UGSelect::new(
+                vec![110.0, 220.0, 330.0],
+                ModeSelect::Random,
+                Some(7),
+            )),
+        );
+        g.add_node("osc", Box::new(UGSine::new()));
+        g.add_node("noise", Box::new(UGWhite::new(Some(3))));
+        g.add_node("str", Box::new(UGString::new(220.0, 0.99, Some(5))));
+        g.add_node("mix", Box::new(UGSum::new(4)));
+        g.add_node("lpf", Box::new(UGLowPass::new(12.0)));
+        g.add_node("verb", Box::new(UGReverb::new()));
+        g.connect("clock.out", "sel.trigger");
+        g.connect("sel.out", "osc.freq");
+        g.connect("clock.out", "str.trigger");
+        g.connect("osc.wave", "mix.in1");
+        g.connect("noise.out", "mix.in2");
+        g.connect("str.out", "mix.in3");
+        g.connect_feedback("lpf.out", "mix.in4");
+        g.connect("mix.out", "lpf.in");
+        g.connect("lpf.out", "verb.in_l");
+        g
+    }
+
+    fn render(g: &mut GenGraph, blocks: usize) -> Vec<Sample> {
+        let mut out = Vec::new();
+        for _ in 0..blocks {
+            g.process();
+            out.extend_from_slice(g.get_output_by_label("verb.out_l"));
+        }
+        out
+    }
+
+    #[test]
+    fn test_snapshot_restore_fork_a() {
+        let mut g1 = stateful();
+        render(&mut g1, 20);
+        let snapshot = g1.snapshot();
+        assert_eq!(snapshot.time_sample, 20 * 64);
+
+        let mut g2 = stateful();
+        g2.restore(&snapshot).unwrap();
+        assert_eq!(g2.time_sample(), 20 * 64);
+        assert_eq!(render(&mut g1, 20), render(&mut g2, 20));
+    }
+
+    #[test]
+    fn test_snapshot_restore_json_a() {
+        let mut g = stateful();
+        render(&mut g, 10);
+        let json = serde_json::to_string(&g.snapshot()).unwrap();
+        let expected = render(&mut g, 10);
+
+        // rewind the same graph
+        let snapshot: GraphSnapshot = serde_json::from_str(&json).unwrap();
+        g.restore(&snapshot).unwrap();
+        assert_eq!(render(&mut g, 10), expected);
+    }
+
+    #[test]
+    fn test_snapshot_feedback_a() {
+        let mut g = GenGraph::new(8.0, 8);
+        g.add_node("one", Box::new(UGConst::new(1.0)));
+        g.add_node("acc", Box::new(UGSum::new(2)));
+        g.connect("one.out", "acc.in1");
+        g.connect_feedback("acc.out", "acc.in2");
+        g.process();
+        g.process();
+        let snapshot = g.snapshot();
+        assert_eq!(
+            snapshot.nodes[1].feedback,
+            vec![("in2".to_string(), vec![2.0; 8])]
+        );
+        assert_eq!(snapshot.nodes[1].state, None);
+        g.process();
+        g.restore(&snapshot).unwrap();
+        g.process();
+        assert_eq!(g.get_output_by_label("acc.out"), vec![3.0; 8]);
+    }
+
+    #[test]
+    fn test_restore_errors() {
+        let mut g = stateful();
+        let snapshot = g.snapshot();
+
+        let mut other = GenGraph::new(8000.0, 64);
+        other.add_node("clock", Box::new(UGConst::new(1.0)));
+        let Err(err) = other.restore(&snapshot) else {
+            panic!("restore should fail")
+        };
+        assert_eq!(
+            err.to_string(),
+            "Cannot restore state of node clock: expected UGClock, found UGConst"
+        );
+
+        let mut other = GenGraph::new(8000.0, 64);
+        other.add_node("clock", Box::new(UGClock::new(30.0, UnitRate::Hz)));
+        let Err(err) = other.restore(&snapshot) else {
+            panic!("restore should fail")
+        };
+        assert_eq!(
+            err.to_string(),
+            "Cannot restore state of node clock: expected configuration \
+             'rate = 20, mode = Hz', found 'rate = 30, mode = Hz'"
+        );
+
+        let mut other = GenGraph::new(8000.0, 64);
+        other.add_node("clock", Box::new(UGClock::new(20.0, UnitRate::Hz)));
+        let Err(err) = other.restore(&snapshot) else {
+            panic!("restore should fail")
+        };
+        assert_eq!(err, GraphError::UnknownNode("sel".to_string()));
+
+        let mut broken = snapshot.clone();
+        broken.nodes[1].state = Some(json!({"index": "x"}));
+        let Err(err) = g.restore(&broken) else {
+            panic!("restore should fail")
+        };
+        assert!(
+            matches!(err, GraphError::InvalidState { ref node, .. } if node == "sel")
+        );
+        // validation precedes loading: nothing was changed before the failure
+        let mut broken = snapshot.clone();
+        broken.nodes.last_mut().unwrap().feedback = vec![("in_r".to_string(), vec![])];
+        let time = g.time_sample();
+        assert!(g.restore(&broken).is_err());
+        assert_eq!(g.time_sample(), time);
+    }
+
+    #[test]
+    fn test_try_get_output() {
+        let mut g = GenGraph::new(8.0, 8);
+        g.add_node("a", Box::new(UGConst::new(3.0)));
+        g.process();
+        assert_eq!(g.try_get_output("a.out").unwrap(), &[3.0; 8]);
+        assert_eq!(
+            g.try_get_output("b.out"),
+            Err(GraphError::UnknownNode("b".to_string()))
+        );
+        assert_eq!(
+            g.try_get_output("a-out"),
+            Err(GraphError::MalformedLabel("a-out".to_string()))
+        );
+        assert_eq!(
+            GraphError::MalformedLabel("a-out".to_string()).to_string(),
+            "Expected 'name.port', got: 'a-out'"
+        );
+    }
+
+    #[test]
+    fn test_schedule_override_input_a() {
+        let mut g = GenGraph::new(8.0, 8);
+        register_many![g,
+            "one" => 1,
+            "sum" => UGSum::new(2),
+        ];
+        g.connect("one.out", "sum.in1");
+        g.schedule("sum.in2", 3, GraphEvent::Override(5.0));
+        g.schedule("sum.in2", 9, GraphEvent::Override(-1.0));
+        g.process();
+        assert_eq!(
+            g.get_output_by_label("sum.out"),
+            vec![1.0, 1.0, 1.0, 6.0, 6.0, 6.0, 6.0, 6.0]
+        );
+        g.process();
+        assert_eq!(
+            g.get_output_by_label("sum.out"),
+            vec![6.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]
+        );
+    }
+
+    #[test]
+    fn test_schedule_override_connected_a() {
+        let mut g = GenGraph::new(8.0, 8);
+        register_many![g,
+            "one" => 1,
+            "two" => 2,
+            "sum" => UGSum::new(2),
+        ];
+        g.connect("one.out", "sum.in1");
+        g.connect("two.out", "sum.in2");
+        g.schedule("sum.in2", 3, GraphEvent::Override(5.0));
+        g.schedule("sum.in2", 6, GraphEvent::Release);
+        g.process();
+        assert_eq!(
+            g.get_output_by_label("sum.out"),
+            vec![3.0, 3.0, 3.0, 6.0, 6.0, 6.0, 3.0, 3.0]
+        );
+        g.process();
+        assert_eq!(g.get_output_by_label("sum.out"), vec![3.0; 8]);
+    }
+
+    #[test]
+    fn test_schedule_const_output_a() {
+        let mut g = GenGraph::new(8.0, 8);
+        g.add_node("c", Box::new(UGConst::new(1.0)));
+        g.schedule(
+            "c.out",
+            10,
+            GraphEvent::Ramp {
+                value: 3.0,
+                samples: 2,
+            },
+        );
+        g.process();
+        assert_eq!(g.get_output_by_label("c.out"), vec![1.0; 8]);
+        g.process();
+        assert_eq!(
+            g.get_output_by_label("c.out"),
+            vec![1.0, 1.0, 2.0, 3.0, 3.0, 3.0, 3.0, 3.0]
+        );
+        assert_eq!(g.time_sample(), 16);
+    }
+
+    #[test]
+    fn test_schedule_trigger_gate_a() {
+        let mut g = GenGraph::new(44100.0, 64);
+        g.add_node("bd", Box::new(UGBassDrum::new()));
+        g.schedule("bd.gate", 37, GraphEvent::Trigger);
+        g.process();
+        let out = g.get_output_by_label("bd.out");
+        assert!(out[..37].iter().all(|&s| s == 0.0));
+        assert!(out[37..].iter().any(|&s| s != 0.0));
+    }
+
+    #[test]
+    fn test_schedule_unknown_port() {
+        let mut g = GenGraph::new(8.0, 8);
+        g.add_node("c", Box::new(UGConst::new(1.0)));
+        assert_eq!(
+            g.try_schedule("c.value", 0, GraphEvent::Trigger),
+            Err(GraphError::UnknownPort {
+                node: "c".to_string(),
+                port: "value".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_hot_swap_identical_is_seamless() {
+        fn build() -> GenGraph {
+            let mut g = GenGraph::new(100.0, 8);
+            register_many![g,
+                "fq" => 3.0,
+                "osc" => UGSine::new(),
+            ];
+            g.connect("fq.out", "osc.freq");
+            g
+        }
+        let mut reference = build();
+        let mut g = build();
+        reference.process();
+        g.process();
+        g.hot_swap(build(), 16);
+        for _ in 0..4 {
+            reference.process();
+            g.process();
+            assert_eq!(
+                g.get_output_by_label("osc.wave"),
+                reference.get_output_by_label("osc.wave")
+            );
+        }
+    }
+
+    #[test]
+    fn test_hot_swap_replaced_node_crossfades_output() {
+        let mut g = GenGraph::new(8.0, 8);
+        g.add_node("c", Box::new(UGConst::new(1.0)));
+        g.process();
+
+        let mut new = GenGraph::new(8.0, 8);
+        new.add_node("c", Box::new(UGConst::new(3.0)));
+        g.hot_swap(new, 16);
+        assert_eq!(g.get_execution_names(), vec!["c"]);
+
+        g.process();
+        let out = g.get_output_by_label("c.out");
+        assert_eq!(out[0], 1.125);
+        assert_eq!(out[7], 2.0);
+        g.process();
+        assert_eq!(g.get_output_by_label("c.out")[7], 3.0);
+        // the retiring node is dropped once the crossfade ends
+        assert_eq!(g.nodes.len(), 1);
+        g.process();
+        assert_eq!(g.get_output_by_label("c.out"), vec![3.0; 8]);
+    }
+
+    #[test]
+    fn test_hot_swap_rewired_input_crossfades() {
+        let mut g = GenGraph::new(8.0, 8);
+        register_many![g,
+            "x" => 1,
+            "z" => 0,
+            "s" => UGSum::new(2),
+        ];
+        g.connect("x.out", "s.in1");
+        g.connect("z.out", "s.in2");
+        g.process();
+
+        let mut new = GenGraph::new(8.0, 8);
+        register_many![new,
+            "y" => 5,
+            "z" => 0,
+            "s" => UGSum::new(2),
+        ];
+        new.connect("y.out", "s.in1");
+        new.connect("z.out", "s.in2");
+        g.hot_swap(new, 8);
+
+        // x was removed but keeps running (unnamed) for the crossfade
+        assert_eq!(g.get_execution_names().len(), 3);
+        assert_eq!(
+            g.try_get_output("x.out"),
+            Err(GraphError::UnknownNode("x".to_string()))
+        );
+        g.process();
+        assert_eq!(
+            g.get_output_by_label("s.out"),
+            vec![1.5, 2.0, 2.5, 3.0, 3.5, 4.0, 4.5, 5.0]
+        );
+        g.process();
+        assert_eq!(g.get_output_by_label("s.out"), vec![5.0; 8]);
+        assert_eq!(g.nodes.len(), 3);
+    }
+
+    #[test]
+    fn test_hot_swap_without_fade() {
+        let mut g = GenGraph::new(8.0, 8);
+        register_many![g,
+            "a" => 1,
+            "b" => 2,
+        ];
+        let mut new = GenGraph::new(8.0, 8);
+        new.add_node("b", Box::new(UGConst::new(4.0)));
+        g.hot_swap(new, 0);
+        assert_eq!(g.nodes.len(), 1);
+        g.process();
+        assert_eq!(g.get_output_by_label("b.out"), vec![4.0; 8]);
+    }
+
+    //--------------------------------------------------------------------------
+    fn voices(threads: usize) -> GenGraph {
+        let mut g = GenGraph::new(800.0, 16);
+        g.set_threads(threads);
+        g.add_node("clock", Box::new(UGClock::new(7.0, UnitRate::Samples)));
+        g.add_node("mix", Box::new(UGSum::new(4)));
+        for i in 0..4 {
+            let osc = format!("osc{i}");
+            let lpf = format!("lpf{i}");
+            g.add_node(osc.clone(), Box::new(UGSine::new()));
+            g.add_node(lpf.clone(), Box::new(UGLowPass::new(12.0)));
+            g.add_node(
+                format!("f{i}"),
+                Box::new(UGConst::new(55.0 * (i + 1) as f32)),
+            );
+            g.connect(&format!("f{i}.out"), &format!("{osc}.freq"));
+            g.connect(&format!("{osc}.wave"), &format!("{lpf}.in"));
+            g.connect(&format!("{lpf}.out"), &format!("mix.in{}", i + 1));
+        }
+        g.add_node("kick", Box::new(UGBassDrum::new()));
+        g.connect("clock.out", "kick.gate");
+        g.add_node("acc", Box::new(UGSum::new(2)));
+        g.connect("kick.out", "acc.in1");
+        g.connect_feedback("acc.out", "acc.in2");
+        g.schedule("lpf2.cutoff", 20, GraphEvent::Override(300.0));
+        g
+    }
+
+    #[test]
+    fn test_execution_levels_a() {
+        let mut g = voices(1);
+        g.update_execution_node_ids();
+        let sizes: Vec<usize> = g.execution_levels.iter().map(|l| l.len()).collect();
+        // consts and clock; oscs and kick; lpfs and acc; mix
+        assert_eq!(sizes, vec![5, 5, 5, 1]);
+        assert_eq!(g.node_levels[g.node_id("mix").unwrap().0], 3);
+    }
+
+    #[test]
+    fn test_parallel_matches_serial_a() {
+        let mut serial = voices(1);
+        let mut parallel = voices(3);
+        let labels = serial.get_node_output_names();
+        for _ in 0..6 {
+            serial.process();
+            parallel.process();
+            for label in &labels {
+                assert_eq!(
+                    serial.get_output_by_label(label),
+                    parallel.get_output_by_label(label),
+                    "{label}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_parallel_hot_swap_matches_serial() {
+        let mut serial = voices(1);
+        let mut parallel = voices(4);
+        serial.process();
+        parallel.process();
+        for g in [&mut serial, &mut parallel] {
+            let mut new = voices(1);
+            new.add_node("lfo", Box::new(UGSine::new()));
+            new.connect("lfo.wave", "osc0.phase");
+            g.hot_swap(new, 24);
+        }
+        for _ in 0..3 {
+            serial.process();
+            parallel.process();
+            assert_eq!(
+                serial.get_output_by_label("mix.out"),
+                parallel.get_output_by_label("mix.out")
+            );
+        }
+    }
 }
@@ -1,8 +1,10 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use crate::GenGraph;
+use crate::GraphError;
 use crate::ModeRound;
 use crate::Recorder;
+use crate::chain::quote_value;
 use crate::ugen_core::UGen;
 use crate::ugen_core::{
     LfoWave, UGAsHz, UGCeil, UGClock, UGConst, UGFade, UGFloor, UGLfo, UGMixLinear,
@@ -14,17 +16,27 @@ use crate::ugen_filter::{
     UGHighPass, UGHighPassConst, UGHighPassQ, UGLowPass, UGLowPassConst, UGLowPassQ,
     UGParametric, UGParametricConst,
 };
+use crate::ugen_math::{
+    ModeScale, UGAbs, UGAnd, UGClamp, UGDiv, UGEqual, UGExp, UGGreater, UGLess, UGLog,
+    UGMax, UGMin, UGMod, UGNeg, UGNot, UGOr, UGPow, UGScale, UGSign, UGSub, UGXor,
+};
+use crate::ugen_osc::{OscWave, UGOsc};
+use crate::ugen_oversample::UGOversample;
+use crate::ugen_poly::{ModeSteal, UGPoly};
 use crate::ugen_reverb::UGReverb;
 use crate::ugen_rhythm::UGPulseSelect;
+use crate::ugen_sample::{ModeInterp, UGSample};
 use crate::ugen_select::{ModeSelect, UGSelect};
 use crate::ugen_string::UGString;
 use crate::util::Sample;
 use crate::util::UnitRate;
+use crate::util::split_name;
+use serde_json::{Map, Value, json};
 use std::collections::HashMap;
 use std::path::Path;
 
 // The UGFacade provides enum-based deserialization of JSON encodings of UGen definition and intialization parameters.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, strum::VariantNames)]
 #[serde(deny_unknown_fields, tag = "0", content = "1")]
 pub enum UGFacade {
     AsHz {
@@ -39,6 +51,7 @@ pub enum UGFacade {
     Const {
         value: Sample,
     },
+    Div {},
     EnvBreakPoint {
         duration_values: Vec<Sample>,
         duration_mode: ModeSelect,
@@ -110,10 +123,24 @@ pub enum UGFacade {
         #[serde(default = "UGFacade::default_pan")]
         pan: Sample,
     },
+    Mod {},
     Mult {
         #[serde(default = "UGFacade::default_inputs")]
         inputs: usize,
     },
+    Neg {},
+    Oversample {
+        #[serde(default = "UGFacade::default_oversample_factor")]
+        factor: usize,
+        ugen: Box<UGFacade>,
+    },
+    Poly {
+        #[serde(default = "UGFacade::default_poly_voices")]
+        voices: usize,
+        #[serde(default = "UGFacade::default_mode_steal")]
+        steal: ModeSteal,
+        ugen: Box<UGFacade>,
+    },
     MixLinear {
         #[serde(default = "UGFacade::default_mix_input_count")]
         inputs: usize,
@@ -137,8 +164,16 @@ pub enum UGFacade {
         mode: ModeSelect,
         seed: Option<u64>,
     },
+    Sample {
+        path: String,
+        #[serde(default = "UGFacade::default_mode_interp")]
+        interp: ModeInterp,
+    },
     SampleHold {},
     Sine {},
+    Osc {
+        wave: OscWave,
+    },
     BassDrum {},
     HighHat {
         seed: Option<u64>,
@@ -153,6 +188,7 @@ pub enum UGFacade {
         damping: f32,
         seed: Option<u64>,
     },
+    Sub {},
     Sum {
         #[serde(default = "UGFacade::default_inputs")]
         inputs: usize,
@@ -161,12 +197,38 @@ pub enum UGFacade {
     White {
         seed: Option<u64>,
     },
+    Abs {},
+    Min {},
+    Max {},
+    Clamp {},
+    Pow {},
+    Exp {},
+    Log {},
+    Sign {},
+    Scale {
+        #[serde(default = "UGFacade::default_mode_scale")]
+        mode: ModeScale,
+    },
+    Greater {},
+    Less {},
+    Equal {},
+    And {},
+    Or {},
+    Xor {},
+    Not {},
 }
 
 #[allow(unused)]
 impl UGFacade {
+    /// Construct the UGen. Panics if it cannot be constructed; see `try_to_ugen`.
     pub fn to_ugen(&self) -> Box<dyn UGen> {
-        match self {
+        self.try_to_ugen().unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Construct the UGen, returning an error if it cannot be constructed, such as a
+    /// `Sample` whose file cannot be read.
+    pub fn try_to_ugen(&self) -> Result<Box<dyn UGen>, String> {
+        Ok(match self {
             UGFacade::Const { value } => Box::new(UGConst::new(*value)),
             UGFacade::Clock { rate, mode } => Box::new(UGClock::new(*rate, *mode)),
             UGFacade::Select { values, mode, seed } => {
@@ -174,17 +236,39 @@ impl UGFacade {
             }
             UGFacade::Round { places, mode } => Box::new(UGRound::new(*places, *mode)),
             UGFacade::Reverb {} => Box::new(UGReverb::new()),
-            UGFacade::Sum { inputs } => Box::new(UGSum::new(*inputs)),
+            UGFacade::Sum { inputs } => Box::new(UGSum::try_new(*inputs)?),
             UGFacade::White { seed } => Box::new(UGWhite::new(*seed)),
             UGFacade::AsHz { mode } => Box::new(UGAsHz::new(*mode)),
             UGFacade::Floor {} => Box::new(UGFloor::new()),
             UGFacade::Ceil {} => Box::new(UGCeil::new()),
-            UGFacade::Mult { inputs } => Box::new(UGMult::new(*inputs)),
+            UGFacade::Mult { inputs } => Box::new(UGMult::try_new(*inputs)?),
+            UGFacade::Sub {} => Box::new(UGSub::new()),
+            UGFacade::Div {} => Box::new(UGDiv::new()),
+            UGFacade::Mod {} => Box::new(UGMod::new()),
+            UGFacade::Neg {} => Box::new(UGNeg::new()),
             UGFacade::MixLinear { inputs, outputs } => {
-                Box::new(UGMixLinear::new(*inputs, *outputs))
+                Box::new(UGMixLinear::try_new(*inputs, *outputs)?)
             }
+            UGFacade::Sample { path, interp } => Box::new(UGSample::new(path, *interp)?),
+            UGFacade::Abs {} => Box::new(UGAbs::new()),
+            UGFacade::Min {} => Box::new(UGMin::new()),
+            UGFacade::Max {} => Box::new(UGMax::new()),
+            UGFacade::Clamp {} => Box::new(UGClamp::new()),
+            UGFacade::Pow {} => Box::new(UGPow::new()),
+            UGFacade::Exp {} => Box::new(UGExp::new()),
+            UGFacade::Log {} => Box::new(UGLog::new()),
+            UGFacade::Sign {} => Box::new(UGSign::new()),
+            UGFacade::Scale { mode } => Box::new(UGScale::new(*mode)),
+            UGFacade::Greater {} => Box::new(UGGreater::new()),
+            UGFacade::Less {} => Box::new(UGLess::new()),
+            UGFacade::Equal {} => Box::new(UGEqual::new()),
+            UGFacade::And {} => Box::new(UGAnd::new()),
+            UGFacade::Or {} => Box::new(UGOr::new()),
+            UGFacade::Xor {} => Box::new(UGXor::new()),
+            UGFacade::Not {} => Box::new(UGNot::new()),
             UGFacade::SampleHold {} => Box::new(UGSampleHold::new()),
             UGFacade::Sine {} => Box::new(UGSine::new()),
+            UGFacade::Osc { wave } => Box::new(UGOsc::new(*wave)),
             UGFacade::Lfo {
                 wave,
                 rate,
@@ -211,12 +295,12 @@ impl UGFacade {
                 cutoff,
                 resonance,
                 channels,
-            } => Box::new(UGHighPassConst::new(
+            } => Box::new(UGHighPassConst::try_new(
                 *roll_off_db,
                 *cutoff,
                 *resonance,
                 *channels,
-            )),
+            )?),
             UGFacade::LowPass { roll_off_db } => Box::new(UGLowPass::new(*roll_off_db)),
             UGFacade::LowPassQ { roll_off_db } => Box::new(UGLowPassQ::new(*roll_off_db)),
             UGFacade::LowPassConst {
@@ -224,18 +308,18 @@ impl UGFacade {
                 cutoff,
                 resonance,
                 channels,
-            } => Box::new(UGLowPassConst::new(
+            } => Box::new(UGLowPassConst::try_new(
                 *roll_off_db,
                 *cutoff,
                 *resonance,
                 *channels,
-            )),
+            )?),
             UGFacade::Parametric {} => Box::new(UGParametric::new()),
             UGFacade::ParametricConst { gain, bw, freq } => {
                 Box::new(UGParametricConst::new(*gain, *bw, *freq))
             }
             UGFacade::Pan { outputs, pan } => {
-                Box::new(UGPan::new(outputs.unwrap_or(2), *pan))
+                Box::new(UGPan::try_new(outputs.unwrap_or(2), *pan)?)
             }
             UGFacade::EnvBreakPoint {
                 duration_values,
@@ -251,8 +335,21 @@ impl UGFacade {
                 *seed,
             )),
             UGFacade::EnvAR {} => Box::new(UGEnvAR::new()),
+            UGFacade::Oversample { factor, ugen } => {
+                Box::new(UGOversample::new(ugen.try_to_ugen()?, *factor)?)
+            }
+            UGFacade::Poly {
+                voices,
+                steal,
+                ugen,
+            } => Box::new(UGPoly::new(
+                (0..*voices)
+                    .map(|_| ugen.try_to_ugen())
+                    .collect::<Result<_, _>>()?,
+                *steal,
+            )?),
             UGFacade::Fade { channels, level } => {
-                Box::new(UGFade::new(*channels, *level as f32))
+                Box::new(UGFade::try_new(*channels, *level as f32)?)
             }
             UGFacade::PulseSelect {
                 duration_values,
@@ -263,7 +360,7 @@ impl UGFacade {
                 *duration_mode,
                 *seed,
             )),
-        }
+        })
     }
 
     /// Return `true` if `name` is a recognized UGFacade variant name.
@@ -339,6 +436,26 @@ impl UGFacade {
         1.0
     }
 
+    fn default_oversample_factor() -> usize {
+        2
+    }
+
+    fn default_poly_voices() -> usize {
+        4
+    }
+
+    fn default_mode_steal() -> ModeSteal {
+        ModeSteal::Oldest
+    }
+
+    fn default_mode_interp() -> ModeInterp {
+        ModeInterp::Linear
+    }
+
+    fn default_mode_scale() -> ModeScale {
+        ModeScale::Linear
+    }
+
     fn default_pan() -> Sample {
         0.5
     }
@@ -354,20 +471,72 @@ impl UGFacade {
 
 //------------------------------------------------------------------------------
 
-#[derive(Deserialize, Debug)]
-#[serde(untagged)]
+#[derive(Debug, Clone)]
 #[allow(unused)]
 pub enum Facade {
-    Short(f32),     // concise numeric constant: "step": 1
-    Full(UGFacade), // ["Clock", { ... }] or ["Round", { ... }]
+    Short(f32), // concise numeric constant: "step": 1
+    // ["Clock", { ... }] or ["Sine", { "@freq": 220 }], with any `@input` defaults
+    Full(UGFacade, Vec<(String, Sample)>),
+}
+
+impl<'de> Deserialize<'de> for Facade {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        Facade::from_value(Value::deserialize(deserializer)?)
+            .map_err(serde::de::Error::custom)
+    }
 }
 
 #[allow(unused)]
 impl Facade {
+    /// Read a number or `["Name", {args}]`.
+    ///
+    /// Arguments named `@input` are not UGen arguments but input defaults, applied with
+    /// `GenGraph::set_input_default`.
+    pub(crate) fn from_value(mut value: Value) -> Result<Self, String> {
+        if let Some(n) = value.as_f64() {
+            return Ok(Facade::Short(n as f32));
+        }
+        let mut input_defaults = Vec::new();
+        if let Some(args) = value.get_mut(1).and_then(Value::as_object_mut) {
+            let keys: Vec<String> = args
+                .keys()
+                .filter(|k| k.starts_with('@'))
+                .cloned()
+                .collect();
+            for key in keys {
+                let v = args.remove(&key).expect("key is present");
+                let v = v.as_f64().ok_or_else(|| {
+                    format!("Input default '{key}' should be a number, got {v}")
+                })?;
+                input_defaults.push((key[1..].to_string(), v as Sample));
+            }
+        }
+        input_defaults.sort_by(|a, b| a.0.cmp(&b.0));
+        let facade = serde_json::from_value(value).map_err(|e| e.to_string())?;
+        Ok(Facade::Full(facade, input_defaults))
+    }
+
     pub fn to_ugen(&self) -> Box<dyn UGen> {
         match self {
             Facade::Short(f) => Box::new(UGConst::new(*f)),
-            Facade::Full(facade) => facade.to_ugen(),
+            Facade::Full(facade, _) => facade.to_ugen(),
+        }
+    }
+
+    pub fn try_to_ugen(&self) -> Result<Box<dyn UGen>, String> {
+        match self {
+            Facade::Short(f) => Ok(Box::new(UGConst::new(*f))),
+            Facade::Full(facade, _) => facade.try_to_ugen(),
+        }
+    }
+
+    /// Input defaults given as `@input` arguments.
+    pub fn input_defaults(&self) -> &[(String, Sample)] {
+        match self {
+            Facade::Short(_) => &[],
+            Facade::Full(_, input_defaults) => input_defaults,
         }
     }
 }
@@ -409,6 +578,11 @@ pub(crate) struct GraphFacade {
     register: HashMap<String, Facade>,
     #[serde(default)]
     connect: Vec<(String, String)>,
+    #[serde(default)]
+    feedback: Vec<(String, String)>,
+    // `def` macro sources available to `chain`
+    #[serde(default)]
+    defs: Vec<String>,
 }
 
 #[allow(unused)]
@@ -427,25 +601,32 @@ impl GraphFacade {
         let mut facade: Self = serde_json::from_str(json)
             .map_err(|e| format!("Failed to parse JSON: {e}"))?;
         if let Some(ref chain) = facade.chain {
-            if !facade.register.is_empty() || !facade.connect.is_empty() {
+            if !facade.register.is_empty()
+                || !facade.connect.is_empty()
+                || !facade.feedback.is_empty()
+            {
                 return Err(
-                    "Cannot specify both 'chain' and 'register'/'connect'".to_string()
+                    "Cannot specify both 'chain' and 'register'/'connect'/'feedback'"
+                        .to_string(),
                 );
             }
-            let (register, connect) = crate::chain::parse_chain(chain)?;
-            facade.register = register;
-            facade.connect = connect;
+            let parsed = crate::chain::parse_chain_with_defs(&facade.defs, chain)?;
+            facade.register = parsed.register;
+            facade.connect = parsed.connect;
+            facade.feedback = parsed.feedback;
+        } else if !facade.defs.is_empty() {
+            return Err("'defs' can only be used with 'chain'".to_string());
         }
         Ok(facade)
     }
 
     /// Construct a `GraphFacade` by parsing a Chain DSL string.
     ///
-    /// The resulting `register` and `connect` containers are equivalent to
+    /// The resulting `register`, `connect` and `feedback` containers are equivalent to
     /// those you would get from the JSON form and can be used with
     /// [`register_and_connect`] to build a [`GenGraph`].
     pub fn from_chain(chain: &str) -> Result<Self, String> {
-        let (register, connect) = crate::chain::parse_chain(chain)?;
+        let parsed = crate::chain::parse_chain(chain)?;
         Ok(Self {
             title: None,
             label: None,
@@ -453,28 +634,60 @@ impl GraphFacade {
             sample_rate: Self::default_sample_rate(),
             buffer_size: Self::default_buffer_size(),
             total_samples: Self::default_total_samples(),
-            register,
-            connect,
+            register: parsed.register,
+            connect: parsed.connect,
+            feedback: parsed.feedback,
+            defs: Vec::new(),
         })
     }
 
-    pub fn register_and_connect(&self, graph: &mut GenGraph) -> Result<(), String> {
+    /// Register and connect all nodes on `graph`, returning the first `GraphError`
+    /// encountered.
+    pub fn register_and_connect(&self, graph: &mut GenGraph) -> Result<(), GraphError> {
         // Register all nodes in sorted order for deterministic output
         let mut keys: Vec<_> = self.register.keys().collect();
         keys.sort();
         for name in keys {
             let facade = &self.register[name];
             eprintln!("register: {:?}", name);
-            graph.add_node(name, facade.to_ugen());
+            let ugen =
+                facade
+                    .try_to_ugen()
+                    .map_err(|reason| GraphError::InvalidUGen {
+                        node: name.clone(),
+                        reason,
+                    })?;
+            graph.try_add_node(name, ugen)?;
+            for (input, value) in facade.input_defaults() {
+                graph.try_set_input_default(&format!("{name}.{input}"), *value)?;
+            }
         }
         // Connect nodes
         for (src, dst) in &self.connect {
             eprintln!("connect: {:?} -> {:?}", src, dst);
-            graph.connect(src, dst);
+            graph.try_connect(src, dst)?;
+        }
+        // Connect feedback (one-block delay) edges
+        for (src, dst) in &self.feedback {
+            eprintln!("feedback: {:?} -> {:?}", src, dst);
+            graph.try_connect_feedback(src, dst)?;
         }
         Ok(())
     }
 
+    /// Update the running `graph` in place to match this facade, keeping the state of
+    /// unchanged nodes; see [`GenGraph::hot_swap`].
+    pub fn patch(
+        &self,
+        graph: &mut GenGraph,
+        fade_samples: usize,
+    ) -> Result<(), GraphError> {
+        let mut new = GenGraph::new(graph.sample_rate, graph.buffer_size);
+        self.register_and_connect(&mut new)?;
+        graph.hot_swap(new, fade_samples);
+        Ok(())
+    }
+
     /// Based on this GraphFacade, create a Graph and render both a graph figure and a time-domain plot figure.
     fn to_rendered_figures(&self, dir: &Path) -> Result<(String, String), String> {
         let mut g = GenGraph::new(self.sample_rate, self.buffer_size);
@@ -575,9 +788,15 @@ fn chain_ugen_reference_markdown() -> String {
     let mode_select = enum_md::<ModeSelect>();
     let mode_round = enum_md::<ModeRound>();
     let lfo_wave = enum_md::<LfoWave>();
+    let osc_wave = enum_md::<OscWave>();
+    let mode_steal = enum_md::<ModeSteal>();
+    let mode_interp = enum_md::<ModeInterp>();
+    let mode_scale = enum_md::<ModeScale>();
 
     // (facade_name, construction_args, representative_ugen_instance)
     let variants: Vec<(&str, Vec<FacadeArgDoc>, Box<dyn UGen>)> = vec![
+        ("Abs", vec![], Box::new(UGAbs::new())),
+        ("And", vec![], Box::new(UGAnd::new())),
         (
             "AsHz",
             vec![FacadeArgDoc::optional("mode", &unit_rate, "Hz")],
@@ -585,6 +804,7 @@ fn chain_ugen_reference_markdown() -> String {
         ),
         ("BassDrum", vec![], Box::new(UGBassDrum::new())),
         ("Ceil", vec![], Box::new(UGCeil::new())),
+        ("Clamp", vec![], Box::new(UGClamp::new())),
         (
             "Clock",
             vec![
@@ -598,6 +818,7 @@ fn chain_ugen_reference_markdown() -> String {
             vec![FacadeArgDoc::required("value", "number")],
             Box::new(UGConst::new(0.0)),
         ),
+        ("Div", vec![], Box::new(UGDiv::new())),
         ("EnvAR", vec![], Box::new(UGEnvAR::new())),
         (
             "EnvBreakPoint",
@@ -616,6 +837,8 @@ fn chain_ugen_reference_markdown() -> String {
                 None,
             )),
         ),
+        ("Equal", vec![], Box::new(UGEqual::new())),
+        ("Exp", vec![], Box::new(UGExp::new())),
         (
             "Fade",
             vec![
@@ -625,6 +848,7 @@ fn chain_ugen_reference_markdown() -> String {
             Box::new(UGFade::new(1, 1.0)),
         ),
         ("Floor", vec![], Box::new(UGFloor::new())),
+        ("Greater", vec![], Box::new(UGGreater::new())),
         (
             "HighHat",
             vec![FacadeArgDoc::optional("seed", "integer", "none")],
@@ -650,6 +874,7 @@ fn chain_ugen_reference_markdown() -> String {
             ],
             Box::new(UGHighPassConst::new(6.0, 1000.0, 0.0, 1)),
         ),
+        ("Less", vec![], Box::new(UGLess::new())),
         (
             "Lfo",
             vec![
@@ -662,6 +887,7 @@ fn chain_ugen_reference_markdown() -> String {
             ],
             Box::new(UGLfo::new(LfoWave::Sine, 1.0, UnitRate::Hz, 0.5, 0.0, 1.0)),
         ),
+        ("Log", vec![], Box::new(UGLog::new())),
         (
             "LowPass",
             vec![FacadeArgDoc::optional("roll_off_db", "number", "6.0")],
@@ -682,6 +908,8 @@ fn chain_ugen_reference_markdown() -> String {
             ],
             Box::new(UGLowPassConst::new(6.0, 1000.0, 0.0, 1)),
         ),
+        ("Max", vec![], Box::new(UGMax::new())),
+        ("Min", vec![], Box::new(UGMin::new())),
         (
             "MixLinear",
             vec![
@@ -690,11 +918,28 @@ fn chain_ugen_reference_markdown() -> String {
             ],
             Box::new(UGMixLinear::new(2, 2)),
         ),
+        ("Mod", vec![], Box::new(UGMod::new())),
         (
             "Mult",
             vec![FacadeArgDoc::optional("inputs", "integer", "2")],
             Box::new(UGMult::new(2)),
         ),
+        ("Neg", vec![], Box::new(UGNeg::new())),
+        ("Not", vec![], Box::new(UGNot::new())),
+        ("Or", vec![], Box::new(UGOr::new())),
+        (
+            "Osc",
+            vec![FacadeArgDoc::required("wave", &osc_wave)],
+            Box::new(UGOsc::new(OscWave::Saw)),
+        ),
+        (
+            "Oversample",
+            vec![
+                FacadeArgDoc::optional("factor", "`2` \\| `4` \\| `8`", "2"),
+                FacadeArgDoc::required("ugen", "UGen, in `{ }` after the args"),
+            ],
+            Box::new(UGOversample::new(Box::new(UGFloor::new()), 2).unwrap()),
+        ),
         (
             "Pan",
             vec![
@@ -713,6 +958,22 @@ fn chain_ugen_reference_markdown() -> String {
             ],
             Box::new(UGParametricConst::new(0.0, 0.333, 1000.0)),
         ),
+        (
+            "Poly",
+            vec![
+                FacadeArgDoc::optional("voices", "integer", "4"),
+                FacadeArgDoc::optional("steal", &mode_steal, "Oldest"),
+                FacadeArgDoc::required("ugen", "UGen, in `{ }` after the args"),
+            ],
+            Box::new(
+                UGPoly::new(
+                    vec![Box::new(UGString::new(440.0, 0.996, None))],
+                    ModeSteal::Oldest,
+                )
+                .unwrap(),
+            ),
+        ),
+        ("Pow", vec![], Box::new(UGPow::new())),
         (
             "PulseSelect",
             vec![
@@ -731,7 +992,24 @@ fn chain_ugen_reference_markdown() -> String {
             ],
             Box::new(UGRound::new(0, ModeRound::Round)),
         ),
+        (
+            "Sample",
+            vec![
+                FacadeArgDoc::required("path", "WAV file path"),
+                FacadeArgDoc::optional("interp", &mode_interp, "Linear"),
+            ],
+            Box::new(UGSample::from_channels(
+                vec![vec![0.0]],
+                44100.0,
+                ModeInterp::Linear,
+            )),
+        ),
         ("SampleHold", vec![], Box::new(UGSampleHold::new())),
+        (
+            "Scale",
+            vec![FacadeArgDoc::optional("mode", &mode_scale, "Linear")],
+            Box::new(UGScale::new(ModeScale::Linear)),
+        ),
         (
             "Select",
             vec![
@@ -741,6 +1019,7 @@ fn chain_ugen_reference_markdown() -> String {
             ],
             Box::new(UGSelect::new(vec![0.0], ModeSelect::Cycle, None)),
         ),
+        ("Sign", vec![], Box::new(UGSign::new())),
         ("Sine", vec![], Box::new(UGSine::new())),
         (
             "SnareDrum",
@@ -756,6 +1035,7 @@ fn chain_ugen_reference_markdown() -> String {
             ],
             Box::new(UGString::new(440.0, 0.996, None)),
         ),
+        ("Sub", vec![], Box::new(UGSub::new())),
         (
             "Sum",
             vec![FacadeArgDoc::optional("inputs", "integer", "2")],
@@ -767,6 +1047,7 @@ fn chain_ugen_reference_markdown() -> String {
             vec![FacadeArgDoc::optional("seed", "integer", "none")],
             Box::new(UGWhite::new(None)),
         ),
+        ("Xor", vec![], Box::new(UGXor::new())),
     ];
 
     let mut md: Vec<String> = vec![
@@ -801,6 +1082,13 @@ fn chain_ugen_reference_markdown() -> String {
             md.push("".to_string());
         }
 
+        // a wrapper has the ports of the UGen it wraps
+        if matches!(ugen.type_name(), "UGOversample" | "UGPoly") {
+            md.push("**Inputs and outputs:** those of the wrapped UGen.".to_string());
+            md.push("".to_string());
+            continue;
+        }
+
         let inputs = ugen.input_names();
         if !inputs.is_empty() {
             md.push("**Inputs:**".to_string());
@@ -937,6 +1225,237 @@ pub fn graph_from_json_definition(
     Ok(graph)
 }
 
+/// Update a running [`GenGraph`] in place from a Chain DSL expression, preserving the
+/// state of nodes that are unchanged and crossfading the rest over `fade_samples`. Nodes
+/// named with `=>` are matched reliably between edits.
+pub fn patch_graph_from_chain_expression(
+    graph: &mut GenGraph,
+    chain: &str,
+    fade_samples: usize,
+) -> Result<(), String> {
+    let facade = GraphFacade::from_chain(chain)?;
+    facade.patch(graph, fade_samples)?;
+    Ok(())
+}
+
+/// Update a running [`GenGraph`] in place from GraphFacade JSON content; see
+/// [`patch_graph_from_chain_expression`].
+pub fn patch_graph_from_json_definition(
+    graph: &mut GenGraph,
+    json: &str,
+    fade_samples: usize,
+) -> Result<(), String> {
+    let facade = GraphFacade::from_json(json)?;
+    facade.patch(graph, fade_samples)?;
+    Ok(())
+}
+
+//------------------------------------------------------------------------------
+// Serializing a live GenGraph
+
+/// Drop unset (`null`) fields and reduce floats to `f32` precision, so that `0.1` is not
+/// written as `0.10000000149011612`.
+fn compact_value(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (k, compact_value(v)))
+                .collect(),
+        ),
+        Value::Array(items) => {
+            Value::Array(items.into_iter().map(compact_value).collect())
+        }
+        Value::Number(n) if n.is_f64() => compact_f32(n.as_f64().unwrap_or_default()),
+        v => v,
+    }
+}
+
+fn compact_f32(f: f64) -> Value {
+    (f as f32)
+        .to_string()
+        .parse::<f64>()
+        .ok()
+        .and_then(serde_json::Number::from_f64)
+        .map_or(Value::Null, Value::Number)
+}
+
+/// Format a JSON argument value as a Chain DSL argument value.
+fn chain_value(value: &Value) -> String {
+    match value {
+        Value::Number(n) if n.is_f64() => {
+            (n.as_f64().unwrap_or_default() as f32).to_string()
+        }
+        Value::String(s) => quote_value(s),
+        Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(chain_value).collect();
+            format!("[{}]", items.join(", "))
+        }
+        v => v.to_string(),
+    }
+}
+
+impl UGFacade {
+    /// Split into the variant name and its arguments.
+    fn name_and_args(&self) -> (String, Map<String, Value>) {
+        let mut tagged = match serde_json::to_value(self) {
+            Ok(Value::Object(map)) => map,
+            _ => unreachable!("UGFacade serializes as an adjacently tagged map"),
+        };
+        let name = match tagged.remove("0") {
+            Some(Value::String(name)) => name,
+            _ => unreachable!("UGFacade tag is a string"),
+        };
+        let args = match tagged.remove("1").map(compact_value) {
+            Some(Value::Object(args)) => args,
+            _ => Map::new(),
+        };
+        (name, args)
+    }
+
+    /// The UGen wrapped by a wrapper such as `Oversample` or `Poly`.
+    fn wrapped(&self) -> Option<&UGFacade> {
+        match self {
+            UGFacade::Oversample { ugen, .. } | UGFacade::Poly { ugen, .. } => Some(ugen),
+            _ => None,
+        }
+    }
+
+    /// Encode as `["Name", {args}]`, the form read by `Facade`.
+    pub fn to_value(&self) -> Value {
+        let (name, mut args) = self.name_and_args();
+        if let Some(ugen) = self.wrapped() {
+            args.insert("ugen".to_string(), ugen.to_value());
+        }
+        json!([name, args])
+    }
+
+    /// Encode as a Chain DSL UGen call, e.g. `Clock(mode=Bpm, rate=120)`.
+    pub fn to_chain(&self) -> String {
+        self.to_chain_with_defaults(&[])
+    }
+
+    /// Like `to_chain`, followed by an `@input=value` argument for each input default.
+    fn to_chain_with_defaults(&self, input_defaults: &[(String, Sample)]) -> String {
+        let defaults = input_defaults.iter().map(|(k, v)| format!("@{k}={v}"));
+        let (name, mut args) = self.name_and_args();
+        args.remove("ugen");
+        let args: Vec<String> = args
+            .iter()
+            .map(|(k, v)| format!("{k}={}", chain_value(v)))
+            .chain(defaults)
+            .collect();
+        match self.wrapped() {
+            Some(ugen) => {
+                format!("{name}({}) {{ {} }}", args.join(", "), ugen.to_chain())
+            }
+            None => format!("{name}({})", args.join(", ")),
+        }
+    }
+}
+
+// A node name, its facade, and its input defaults
+type NodeFacade = (String, UGFacade, Vec<(String, Sample)>);
+
+/// The facade and input defaults of each live node of `graph`, sorted by name so that
+/// output is stable across builds (ties in execution order depend on registration order).
+fn graph_facades(graph: &mut GenGraph) -> Result<Vec<NodeFacade>, GraphError> {
+    let mut nodes = graph.live_nodes();
+    nodes.sort_by_key(|(name, _, _)| *name);
+    nodes
+        .into_iter()
+        .map(|(name, ugen, input_defaults)| match ugen.to_facade() {
+            Some(facade) => Ok((
+                name.to_string(),
+                facade,
+                input_defaults
+                    .into_iter()
+                    .map(|(input, v)| (input.to_string(), v))
+                    .collect(),
+            )),
+            None => Err(GraphError::Unserializable {
+                node: name.to_string(),
+                reason: format!("{} does not implement to_facade", ugen.type_name()),
+            }),
+        })
+        .collect()
+}
+
+/// Live connections sorted by destination label, then source label.
+fn sorted_connections(graph: &mut GenGraph) -> Vec<(String, String, bool)> {
+    let mut connections = graph.live_connections();
+    connections.sort_by(|a, b| (&a.1, &a.0).cmp(&(&b.1, &b.0)));
+    connections
+}
+
+pub(crate) fn graph_to_facade_json(graph: &mut GenGraph) -> Result<String, GraphError> {
+    let mut register = Map::new();
+    for (name, facade, input_defaults) in graph_facades(graph)? {
+        let mut value = match facade {
+            UGFacade::Const { value } => compact_f32(value as f64),
+            facade => facade.to_value(),
+        };
+        if let Some(args) = value.get_mut(1).and_then(Value::as_object_mut) {
+            for (input, v) in input_defaults {
+                args.insert(format!("@{input}"), compact_f32(v as f64));
+            }
+        }
+        register.insert(name, value);
+    }
+    let (feedback, connect): (Vec<_>, Vec<_>) = sorted_connections(graph)
+        .into_iter()
+        .partition(|(_, _, feedback)| *feedback);
+    let pairs = |edges: Vec<(String, String, bool)>| -> Vec<Value> {
+        edges
+            .into_iter()
+            .map(|(src, dst, _)| json!([src, dst]))
+            .collect()
+    };
+    let mut definition = json!({
+        "sample_rate": graph.sample_rate,
+        "buffer_size": graph.buffer_size,
+        "register": register,
+        "connect": pairs(connect),
+    });
+    if !feedback.is_empty() {
+        definition["feedback"] = Value::Array(pairs(feedback));
+    }
+    Ok(serde_json::to_string_pretty(&definition).expect("JSON values serialize"))
+}
+
+pub(crate) fn graph_to_chain(graph: &mut GenGraph) -> Result<String, GraphError> {
+    let mut segments = Vec::new();
+    for (name, facade, input_defaults) in graph_facades(graph)? {
+        if !is_chain_name(&name) {
+            return Err(GraphError::Unserializable {
+                node: name,
+                reason: "the name is not a Chain DSL identifier".to_string(),
+            });
+        }
+        let atom = match facade {
+            UGFacade::Const { value } => value.to_string(),
+            facade => facade.to_chain_with_defaults(&input_defaults),
+        };
+        segments.push(format!("{atom} => {name}"));
+    }
+    for (src, dst, feedback) in sorted_connections(graph) {
+        // labels were built from valid node and port names
+        let (src_node, output) = split_name(&src).unwrap();
+        let (dst_node, input) = split_name(&dst).unwrap();
+        let arrow = if feedback { "~>" } else { "->" };
+        segments.push(format!("{src_node} {arrow}{output}:{input} {dst_node}"));
+    }
+    Ok(segments.join("\n| "))
+}
+
+/// True if `name` can be written as a node name in the Chain DSL.
+fn is_chain_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '.')
+        && !UGFacade::is_variant_name(name)
+}
+
 //------------------------------------------------------------------------------
 #[cfg(test)]
 mod tests {
@@ -1071,6 +1590,270 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_ug_facade_feedback() {
+        let json = r#"{
+            "register": {
+                "one": 1,
+                "acc": ["Sum", {}]
+            },
+            "connect": [["one.out", "acc.in1"]],
+            "feedback": [["acc.out", "acc.in2"]]
+        }"#;
+        let mut g = GenGraph::new(8.0, 8);
+        let gf = GraphFacade::from_json(json).unwrap();
+        gf.register_and_connect(&mut g).unwrap();
+        g.process();
+        g.process();
+        assert_eq!(g.get_output_by_label("acc.out"), vec![2.0; 8]);
+    }
+
+    #[test]
+    fn test_register_and_connect_error() {
+        let json = r#"{
+            "register": {"a": 1, "b": ["Sum", {}]},
+            "connect": [["a.out", "b.in1"], ["a.out", "b.nope"]]
+        }"#;
+        let mut g = GenGraph::new(8.0, 8);
+        let gf = GraphFacade::from_json(json).unwrap();
+        assert_eq!(
+            gf.register_and_connect(&mut g),
+            Err(GraphError::UnknownPort {
+                node: "b".to_string(),
+                port: "nope".to_string()
+            })
+        );
+        let err = graph_from_chain_expression("Sine() => a | a ->:nope a", 8.0, 8)
+            .err()
+            .unwrap();
+        assert_eq!(err, "For a, invalid port name: nope");
+    }
+
+    #[test]
+    fn test_patch_graph_from_chain_expression() {
+        let mut g =
+            graph_from_chain_expression("Sine() => osc | 2 ->:freq osc", 100.0, 8)
+                .unwrap();
+        let mut reference =
+            graph_from_chain_expression("Sine() => osc | 2 ->:freq osc", 100.0, 8)
+                .unwrap();
+        g.process();
+        reference.process();
+
+        // osc is kept, so its phase continues without a reset
+        patch_graph_from_chain_expression(
+            &mut g,
+            "Sine() => osc | 2 ->:freq osc | osc * 0.5",
+            8,
+        )
+        .unwrap();
+        g.process();
+        reference.process();
+        assert_eq!(
+            g.get_output_by_label("osc.wave"),
+            reference.get_output_by_label("osc.wave")
+        );
+        assert_eq!(g.len(), 4);
+
+        assert!(patch_graph_from_chain_expression(&mut g, "Sine() -> ", 8).is_err());
+    }
+
+    #[test]
+    fn test_graph_facade_defs() {
+        let json = r#"{
+            "defs": ["def Level(v) { Sum() => s | v ->:in1 s | 1 ->:in2 s | out out = s }"],
+            "chain": "Level(v=2) => a | Level(v=3) => b | (a * b) => m"
+        }"#;
+        let mut g = graph_from_json_definition(json, 8.0, 8).unwrap();
+        g.process();
+        // (2 + 1) * (3 + 1)
+        assert_eq!(g.get_output_by_label("m.out"), vec![12.0; 8]);
+        assert_eq!(g.get_output_by_label("a.s.out"), vec![3.0; 8]);
+
+        let json = r#"{"defs": ["def A() { 1 }"], "register": {"a": 1}}"#;
+        assert!(GraphFacade::from_json(json).is_err());
+    }
+
+    //--------------------------------------------------------------------------
+    fn serializable_graph() -> GenGraph {
+        let mut g = GenGraph::new(100.0, 8);
+        g.add_node("clk", Box::new(UGClock::new(10.0, UnitRate::Samples)));
+        g.add_node(
+            "sel",
+            Box::new(UGSelect::new(vec![0.1, 0.5], ModeSelect::Cycle, Some(3))),
+        );
+        g.add_node("noise", Box::new(UGWhite::new(Some(42))));
+        g.add_node("lpf", Box::new(UGLowPass::new(12.0)));
+        g.add_node("acc", Box::new(UGSum::new(2)));
+        g.add_node("gain", Box::new(UGConst::new(-400.5)));
+        g.connect("clk.out", "sel.trigger");
+        g.connect("noise.out", "lpf.in");
+        g.connect("sel.out", "acc.in1");
+        g.connect("gain.out", "lpf.cutoff");
+        g.connect_feedback("acc.out", "acc.in2");
+        g
+    }
+
+    #[test]
+    fn test_to_facade_json_round_trip() {
+        let mut g1 = serializable_graph();
+        let json = g1.to_facade_json().unwrap();
+        let v: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(v["register"]["gain"], json!(-400.5));
+        assert_eq!(
+            v["register"]["sel"],
+            json!(["Select", {"values": [0.1, 0.5], "mode": "Cycle", "seed": 3}])
+        );
+        assert_eq!(
+            v["register"]["lpf"],
+            json!(["LowPass", {"roll_off_db": 12.0}])
+        );
+        assert_eq!(v["feedback"], json!([["acc.out", "acc.in2"]]));
+
+        let mut g2 = graph_from_json_definition(&json, 100.0, 8).unwrap();
+        assert_eq!(g2.to_facade_json().unwrap(), json);
+        for _ in 0..4 {
+            g1.process();
+            g2.process();
+            for label in ["lpf.out", "acc.out", "sel.out"] {
+                assert_eq!(g1.get_output_by_label(label), g2.get_output_by_label(label));
+            }
+        }
+    }
+
+    #[test]
+    fn test_to_chain_round_trip() {
+        let mut g1 = serializable_graph();
+        let chain = g1.to_chain().unwrap();
+        assert!(chain.contains("Select(mode=Cycle, seed=3, values=[0.1, 0.5]) => sel"));
+        assert!(chain.contains("-400.5 => gain"));
+        assert!(chain.contains("clk ->out:trigger sel"));
+        assert!(chain.contains("acc ~>out:in2 acc"));
+
+        let mut g2 = graph_from_chain_expression(&chain, 100.0, 8).unwrap();
+        assert_eq!(g2.to_chain().unwrap(), chain);
+        assert_eq!(g2.to_facade_json().unwrap(), g1.to_facade_json().unwrap());
+    }
+
+    #[test]
+    fn test_to_chain_macro_instance_names() {
+        let mut g1 = graph_from_chain_expression(
+            "def V(f) { Sine() => osc | f ->:freq osc | out out = osc } | V(f=220) => v",
+            100.0,
+            8,
+        )
+        .unwrap();
+        let chain = g1.to_chain().unwrap();
+        assert!(chain.contains("Sine() => v.osc"));
+        let mut g2 = graph_from_chain_expression(&chain, 100.0, 8).unwrap();
+        assert_eq!(g2.to_chain().unwrap(), chain);
+    }
+
+    #[test]
+    fn test_serialize_examples_round_trip() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("doc/example");
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            let content = std::fs::read_to_string(&path).unwrap();
+            let mut g1 = graph_from_json_definition(&content, 100.0, 8).unwrap();
+
+            let json = g1.to_facade_json().unwrap();
+            let mut g2 = graph_from_json_definition(&json, 100.0, 8).unwrap();
+            assert_eq!(g2.to_facade_json().unwrap(), json, "{}", path.display());
+
+            let chain = g1.to_chain().unwrap();
+            let mut g3 = graph_from_chain_expression(&chain, 100.0, 8).unwrap();
+            assert_eq!(g3.to_chain().unwrap(), chain, "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn test_serialize_errors() {
+        struct Opaque;
+        impl UGen for Opaque {
+            fn process(
+                &mut self,
+                _: &[crate::Input],
+                _: &mut [&mut [Sample]],
+                _: f32,
+                _: usize,
+            ) {
+            }
+            fn type_name(&self) -> &'static str {
+                "Opaque"
+            }
+            fn input_names(&self) -> &[String] {
+                &[]
+            }
+            fn output_names(&self) -> &[String] {
+                &[]
+            }
+        }
+        let mut g = GenGraph::new(100.0, 8);
+        g.add_node("x", Box::new(Opaque));
+        assert!(matches!(
+            g.to_facade_json(),
+            Err(GraphError::Unserializable { node, .. }) if node == "x"
+        ));
+
+        let mut g = GenGraph::new(100.0, 8);
+        g.add_node("a-b", Box::new(UGConst::new(1.0)));
+        assert!(g.to_facade_json().is_ok());
+        assert!(matches!(
+            g.to_chain(),
+            Err(GraphError::Unserializable { .. })
+        ));
+    }
+
+    #[test]
+    fn test_ug_facade_oversample() {
+        let json = r#"{
+            "register": {
+                "c": 0.27,
+                "os": ["Oversample", {"factor": 4, "ugen": ["Round", {"places": 1}]}]
+            },
+            "connect": [["c.out", "os.in"]]
+        }"#;
+        let mut g = graph_from_json_definition(json, 100.0, 8).unwrap();
+        for _ in 0..8 {
+            g.process();
+        }
+        let out = g.get_output_by_label("os.out");
+        assert!(out.iter().all(|v| (v - 0.3).abs() < 1e-3), "{out:?}");
+
+        let chain = g.to_chain().unwrap();
+        assert!(
+            chain.contains("Oversample(factor=4) { Round(mode=Round, places=1) } => os")
+        );
+        let mut g2 = graph_from_chain_expression(&chain, 100.0, 8).unwrap();
+        assert_eq!(g2.to_facade_json().unwrap(), g.to_facade_json().unwrap());
+    }
+
+    #[test]
+    fn test_ug_facade_poly() {
+        let chain = "Clock(rate=20, mode=Hz) -> Poly(voices=3, steal=Quietest) { String(seed=2) } => p";
+        let mut g = graph_from_chain_expression(chain, 8000.0, 64).unwrap();
+        let mut peak: Sample = 0.0;
+        for _ in 0..16 {
+            g.process();
+            peak = g
+                .get_output_by_label("p.out")
+                .iter()
+                .fold(peak, |p, v| p.max(v.abs()));
+        }
+        assert!(peak > 0.1, "{peak}");
+
+        let chain = g.to_chain().unwrap();
+        assert!(
+            chain.contains(
+                "Poly(steal=Quietest, voices=3) { String(damping=0.996, freq=440, seed=2) } => p"
+            ),
+            "{chain}"
+        );
+        let mut g2 = graph_from_chain_expression(&chain, 8000.0, 64).unwrap();
+        assert_eq!(g2.to_facade_json().unwrap(), g.to_facade_json().unwrap());
+    }
+
     #[test]
     fn test_ug_facade_floor() {
         let json = r#"{
@@ -1209,6 +1992,58 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_ug_facade_osc() {
+        let json = r#"{
+            "register": {
+                "freq": ["Const", {"value": 1.0}],
+                "osc": ["Osc", {"wave": "Triangle"}]
+            },
+            "connect": [["freq.out", "osc.freq"]]
+        }"#;
+        let mut g = graph_from_json_definition(json, 8.0, 8).unwrap();
+        g.process();
+        // corners at the peak and the trough are rounded off
+        let expected = [-0.5, 0.0, 0.5, 0.6667, 0.5, 0.0, -0.5, -0.6667];
+        for (v, e) in g.get_output_by_label("osc.wave").iter().zip(expected) {
+            assert!((v - e).abs() < 1e-3, "{v} != {e}");
+        }
+        let chain = g.to_chain().unwrap();
+        assert!(chain.contains("Osc(wave=Triangle) => osc"));
+    }
+
+    #[test]
+    fn test_ug_facade_input_defaults() {
+        let json = r#"{
+            "register": {
+                "osc": ["Sine", {"@freq": 2, "@min": 0}],
+                "r": ["Round", {"places": 1}]
+            },
+            "connect": [["osc.wave", "r.in"]]
+        }"#;
+        let mut g = graph_from_json_definition(json, 8.0, 8).unwrap();
+        g.process();
+        assert_eq!(
+            g.get_output_by_label("r.out"),
+            vec![1.0, 0.5, 0.0, 0.5, 1.0, 0.5, 0.0, 0.5]
+        );
+
+        let chain = g.to_chain().unwrap();
+        assert!(chain.contains("Sine(@freq=2, @min=0) => osc"), "{chain}");
+        let mut g2 = graph_from_chain_expression(&chain, 8.0, 8).unwrap();
+        assert_eq!(g2.to_facade_json().unwrap(), g.to_facade_json().unwrap());
+        assert!(g.to_facade_json().unwrap().contains(r#""@freq": 2.0"#));
+
+        let Err(err) = graph_from_json_definition(
+            r#"{"register": {"osc": ["Sine", {"@nope": 1}]}}"#,
+            8.0,
+            8,
+        ) else {
+            panic!("expected an error");
+        };
+        assert!(err.contains("invalid port name: nope"), "{err}");
+    }
+
     #[test]
     fn test_ug_facade_trigger() {
         let json = r#"{
@@ -1692,38 +2527,59 @@ mod tests {
 
         // Every UGFacade variant name appears as a subsection heading.
         for name in [
+            "Abs",
+            "And",
             "AsHz",
             "BassDrum",
             "Ceil",
+            "Clamp",
             "Clock",
             "Const",
+            "Div",
             "EnvAR",
             "EnvBreakPoint",
+            "Equal",
+            "Exp",
             "Fade",
             "Floor",
+            "Greater",
             "HighHat",
             "HighPass",
             "HighPassConst",
             "HighPassQ",
+            "Less",
             "Lfo",
+            "Log",
             "LowPass",
             "LowPassConst",
             "LowPassQ",
+            "Max",
+            "Min",
             "MixLinear",
+            "Mod",
             "Mult",
+            "Neg",
+            "Not",
+            "Or",
             "Pan",
             "Parametric",
             "ParametricConst",
+            "Pow",
             "PulseSelect",
             "Reverb",
             "Round",
+            "Sample",
+            "Scale",
             "Select",
+            "Sign",
             "Sine",
             "SnareDrum",
             "String",
+            "Sub",
             "Sum",
             "Trigger",
             "White",
+            "Xor",
         ] {
             assert!(
                 md.contains(&format!("### {name}")),
@@ -2,22 +2,31 @@ mod chain;
 mod graph;
 mod graph_facade;
 mod recorder;
+mod schedule;
+mod sink;
 mod ugen_core;
 mod ugen_drum;
 mod ugen_env;
 mod ugen_filter;
+mod ugen_math;
+mod ugen_osc;
+mod ugen_oversample;
+mod ugen_poly;
 mod ugen_reverb;
 mod ugen_rhythm;
+mod ugen_sample;
 mod ugen_select;
 mod ugen_string;
 mod util;
 
 pub use recorder::{Recorder, WavFormat};
 
+pub use sink::{AudioSink, NullSink, RawSink, WavPipeSink, WavSink, stream_to_sink};
+
 pub use ugen_core::{
-    LfoWave, ModeRound, UGAsHz, UGCeil, UGClock, UGConst, UGFade, UGFloor, UGLfo,
-    UGMixLinear, UGMult, UGPan, UGRound, UGSampleHold, UGSine, UGSum, UGTrigger, UGWhite,
-    UGen,
+    Input, LfoWave, ModeRound, Rate, UGAsHz, UGCeil, UGClock, UGConst, UGFade, UGFloor,
+    UGLfo, UGMixLinear, UGMult, UGPan, UGRound, UGSampleHold, UGSine, UGSum, UGTrigger,
+    UGWhite, UGen,
 };
 
 pub use ugen_select::{ModeSelect, UGSelect};
@@ -29,8 +38,17 @@ pub use ugen_filter::{
 
 pub use ugen_env::{UGEnvAR, UGEnvBreakPoint};
 
+pub use ugen_math::{
+    ModeScale, UGAbs, UGAnd, UGClamp, UGDiv, UGEqual, UGExp, UGGreater, UGLess, UGLog,
+    UGMax, UGMin, UGMod, UGNeg, UGNot, UGOr, UGPow, UGScale, UGSign, UGSub, UGXor,
+};
+
+pub use ugen_osc::{OscWave, UGOsc};
+pub use ugen_oversample::UGOversample;
+pub use ugen_poly::{ModeSteal, UGPoly};
 pub use ugen_reverb::UGReverb;
 pub use ugen_rhythm::UGPulseSelect;
+pub use ugen_sample::{ModeInterp, UGSample};
 
 pub use ugen_drum::{UGBassDrum, UGHighHat, UGSnareDrum};
 
@@ -38,8 +56,13 @@ pub use ugen_string::UGString;
 
 pub use util::{Sample, UnitRate};
 
-pub use graph::GenGraph;
+pub use graph::{
+    GenGraph, GraphError, GraphIssue, GraphSnapshot, NodeSnapshot, Severity,
+};
+pub use schedule::GraphEvent;
 
 pub use graph_facade::{
-    build_markdown_index, graph_from_chain_expression, graph_from_json_definition,
+    UGFacade, build_markdown_index, graph_from_chain_expression,
+    graph_from_json_definition, patch_graph_from_chain_expression,
+    patch_graph_from_json_definition,
 };
@@ -21,7 +21,7 @@ pub enum WavFormat {
 }
 
 impl WavFormat {
-    fn bits_per_sample(&self) -> u16 {
+    pub(crate) fn bits_per_sample(&self) -> u16 {
         match self {
             WavFormat::Float32 => 32,
             WavFormat::Int16 => 16,
@@ -38,7 +38,11 @@ impl WavFormat {
     }
 
     /// Encode a single f32 sample and write it to `w`.
-    fn write_sample<W: Write>(&self, w: &mut W, s: f32) -> std::io::Result<()> {
+    pub(crate) fn write_sample<W: Write>(
+        &self,
+        w: &mut W,
+        s: f32,
+    ) -> std::io::Result<()> {
         match self {
             WavFormat::Float32 => w.write_all(&s.to_le_bytes()),
             WavFormat::Int16 => {
@@ -66,6 +70,38 @@ impl TryFrom<u16> for WavFormat {
     }
 }
 
+/// Write a 44-byte RIFF/WAVE header for `data_size` bytes of interleaved samples.
+pub(crate) fn write_wav_header<W: Write>(
+    w: &mut W,
+    format: WavFormat,
+    channels: u16,
+    sample_rate: u32,
+    data_size: u32,
+) -> std::io::Result<()> {
+    let bits_per_sample = format.bits_per_sample();
+    let bytes_per_sample = (bits_per_sample / 8) as u32;
+    let block_align = channels as u32 * bytes_per_sample;
+    let byte_rate = sample_rate * block_align;
+    let riff_size = 36u32.saturating_add(data_size);
+
+    // RIFF header
+    w.write_all(b"RIFF")?;
+    w.write_all(&riff_size.to_le_bytes())?;
+    w.write_all(b"WAVE")?;
+    // fmt chunk
+    w.write_all(b"fmt ")?;
+    w.write_all(&16u32.to_le_bytes())?;
+    w.write_all(&format.audio_format_tag().to_le_bytes())?;
+    w.write_all(&channels.to_le_bytes())?;
+    w.write_all(&sample_rate.to_le_bytes())?;
+    w.write_all(&byte_rate.to_le_bytes())?;
+    w.write_all(&(block_align as u16).to_le_bytes())?;
+    w.write_all(&bits_per_sample.to_le_bytes())?;
+    // data chunk
+    w.write_all(b"data")?;
+    w.write_all(&data_size.to_le_bytes())
+}
+
 pub struct Recorder {
     sample_rate: f32,
     recorded: HashMap<String, Vec<Sample>>,
@@ -85,6 +121,8 @@ impl Recorder {
 
         match output_labels {
             Some(ref labels) => {
+                // only the requested outputs are rendered
+                graph.compile(labels).unwrap_or_else(|e| panic!("{e}"));
                 for label in labels {
                     recorded.insert(label.clone(), Vec::with_capacity(total_samples));
                     collected_labels.insert(label.clone());
@@ -185,33 +223,10 @@ impl Recorder {
         format: WavFormat,
     ) -> std::io::Result<()> {
         let channels = self.output_names.len() as u16;
-        let bits_per_sample = format.bits_per_sample();
-        let audio_format = format.audio_format_tag();
-        let sample_rate = self.sample_rate as u32;
-        let bytes_per_sample = (bits_per_sample / 8) as u32;
-        let block_align = channels as u32 * bytes_per_sample;
-        let byte_rate = sample_rate * block_align;
+        let bytes_per_sample = (format.bits_per_sample() / 8) as u32;
         let (_, length) = self.get_shape();
         let data_size = length as u32 * channels as u32 * bytes_per_sample;
-        let riff_size = 36u32 + data_size;
-
-        // RIFF header
-        w.write_all(b"RIFF")?;
-        w.write_all(&riff_size.to_le_bytes())?;
-        w.write_all(b"WAVE")?;
-        // fmt chunk
-        w.write_all(b"fmt ")?;
-        w.write_all(&16u32.to_le_bytes())?;
-        w.write_all(&audio_format.to_le_bytes())?;
-        w.write_all(&channels.to_le_bytes())?;
-        w.write_all(&sample_rate.to_le_bytes())?;
-        w.write_all(&byte_rate.to_le_bytes())?;
-        w.write_all(&(block_align as u16).to_le_bytes())?;
-        w.write_all(&bits_per_sample.to_le_bytes())?;
-        // data chunk
-        w.write_all(b"data")?;
-        w.write_all(&data_size.to_le_bytes())?;
-
+        write_wav_header(&mut w, format, channels, self.sample_rate as u32, data_size)?;
         self.write_samples(&mut w, format)
     }
 
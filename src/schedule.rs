@@ -0,0 +1,230 @@
+use std::collections::VecDeque;
+
+use crate::graph::NodeId;
+use crate::ugen_core::Input;
+use crate::util::Sample;
+
+//------------------------------------------------------------------------------
+
+/// A timestamped change applied to a node port by `GenGraph::schedule`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GraphEvent {
+    /// Replace the port's signal with `value` from the event sample onward, until a
+    /// `Release`. A connected input ignores its source meanwhile.
+    Override(Sample),
+    /// Emit 1.0 for the event sample only, then return to the override, or to the port's
+    /// own signal if there is none. Suitable for drum `gate` inputs.
+    Trigger,
+    /// Move linearly from the current value to `value`, arriving on the last of `samples`
+    /// samples, then hold `value` until a `Release`.
+    Ramp { value: Sample, samples: usize },
+    /// End any override or ramp, so the port carries its own signal again from the event
+    /// sample.
+    Release,
+}
+
+/// The port an `Automation` lane drives: an input (before the node is processed) or an
+/// output (after).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AutomationPort {
+    Input(usize),
+    Output(usize),
+}
+
+struct Ramp {
+    from: Sample,
+    to: Sample,
+    len: usize,
+    pos: usize,
+}
+
+/// Scheduled events for one port, rendered to a per-sample buffer each block.
+///
+/// Rather than splitting `process()` at event boundaries (which would hand UGens
+/// sub-blocks that are not a multiple of the SIMD lane width), the lane renders a full
+/// block of values in which each event takes effect on its exact sample. Until the first
+/// event, and after a `Release`, the lane passes through the port's own signal: the
+/// connected input, the input default, or the node's output.
+pub(crate) struct Automation {
+    pub(crate) node: NodeId,
+    pub(crate) port: AutomationPort,
+    // Pending events sorted by time; events at the same time keep insertion order
+    events: VecDeque<(usize, GraphEvent)>,
+    current: Option<Sample>,
+    ramp: Option<Ramp>,
+    pub(crate) buffer: Vec<Sample>,
+    // True if `buffer` should replace the port's signal for the current block
+    pub(crate) active: bool,
+}
+
+impl Automation {
+    pub(crate) fn new(node: NodeId, port: AutomationPort, buffer_size: usize) -> Self {
+        Self {
+            node,
+            port,
+            events: VecDeque::new(),
+            current: None,
+            ramp: None,
+            buffer: vec![0.0; buffer_size],
+            active: false,
+        }
+    }
+
+    pub(crate) fn push(&mut self, time_sample: usize, event: GraphEvent) {
+        let pos = self.events.partition_point(|(t, _)| *t <= time_sample);
+        self.events.insert(pos, (time_sample, event));
+    }
+
+    /// Render the block starting at `time_sample`.
+    ///
+    /// `base` is the port's own signal, used (or `default` if unconnected) where the lane
+    /// holds no value. Events scheduled before `time_sample` take effect on the first
+    /// sample.
+    pub(crate) fn render(&mut self, time_sample: usize, base: Input, default: Sample) {
+        let len = self.buffer.len();
+        self.active = self.current.is_some()
+            || self.ramp.is_some()
+            || self
+                .events
+                .front()
+                .is_some_and(|(t, _)| *t < time_sample + len);
+        if !self.active {
+            return;
+        }
+        for i in 0..len {
+            let t = time_sample + i;
+            let base_v = base.value_or(i, default);
+            let mut pulse = false;
+
+            while self.events.front().is_some_and(|(et, _)| *et <= t) {
+                let (_, event) = self.events.pop_front().unwrap();
+                match event {
+                    GraphEvent::Override(v) => {
+                        self.current = Some(v);
+                        self.ramp = None;
+                    }
+                    GraphEvent::Trigger => pulse = true,
+                    GraphEvent::Ramp { value, samples: 0 } => {
+                        self.current = Some(value);
+                        self.ramp = None;
+                    }
+                    GraphEvent::Ramp { value, samples } => {
+                        self.ramp = Some(Ramp {
+                            from: self.current.unwrap_or(base_v),
+                            to: value,
+                            len: samples,
+                            pos: 0,
+                        });
+                    }
+                    GraphEvent::Release => {
+                        self.current = None;
+                        self.ramp = None;
+                    }
+                }
+            }
+            if let Some(r) = &mut self.ramp {
+                r.pos += 1;
+                self.current =
+                    Some(r.from + (r.to - r.from) * r.pos as f32 / r.len as f32);
+                if r.pos == r.len {
+                    self.ramp = None;
+                }
+            }
+            self.buffer[i] = if pulse {
+                1.0
+            } else {
+                self.current.unwrap_or(base_v)
+            };
+        }
+    }
+}
+
+//------------------------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lane() -> Automation {
+        Automation::new(NodeId(0), AutomationPort::Input(0), 8)
+    }
+
+    #[test]
+    fn test_automation_inactive_a() {
+        let mut a = lane();
+        a.push(20, GraphEvent::Override(1.0));
+        a.render(0, Input::Unconnected, 0.0);
+        assert!(!a.active);
+        a.render(8, Input::Unconnected, 0.0);
+        assert!(!a.active);
+        a.render(16, Input::Unconnected, 0.5);
+        assert!(a.active);
+        assert_eq!(a.buffer, vec![0.5, 0.5, 0.5, 0.5, 1.0, 1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_automation_ramp_a() {
+        let mut a = lane();
+        a.push(
+            2,
+            GraphEvent::Ramp {
+                value: 4.0,
+                samples: 4,
+            },
+        );
+        a.render(0, Input::Audio(&[0.0; 8]), 0.0);
+        assert_eq!(a.buffer, vec![0.0, 0.0, 1.0, 2.0, 3.0, 4.0, 4.0, 4.0]);
+    }
+
+    #[test]
+    fn test_automation_ramp_across_blocks_a() {
+        let mut a = lane();
+        a.push(0, GraphEvent::Override(8.0));
+        a.push(
+            4,
+            GraphEvent::Ramp {
+                value: 0.0,
+                samples: 8,
+            },
+        );
+        a.render(0, Input::Unconnected, 0.0);
+        assert_eq!(a.buffer, vec![8.0, 8.0, 8.0, 8.0, 7.0, 6.0, 5.0, 4.0]);
+        a.render(8, Input::Unconnected, 0.0);
+        assert_eq!(a.buffer, vec![3.0, 2.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_automation_trigger_a() {
+        let mut a = lane();
+        a.push(3, GraphEvent::Trigger);
+        a.push(5, GraphEvent::Override(0.25));
+        a.push(6, GraphEvent::Trigger);
+        a.render(0, Input::Unconnected, 0.0);
+        assert_eq!(a.buffer, vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.25, 1.0, 0.25]);
+    }
+
+    #[test]
+    fn test_automation_past_event_a() {
+        let mut a = lane();
+        a.push(3, GraphEvent::Override(2.0));
+        a.render(16, Input::Unconnected, 0.0);
+        assert_eq!(a.buffer, vec![2.0; 8]);
+    }
+
+    #[test]
+    fn test_automation_release_a() {
+        let mut a = lane();
+        a.push(2, GraphEvent::Override(5.0));
+        a.push(
+            4,
+            GraphEvent::Ramp {
+                value: 1.0,
+                samples: 8,
+            },
+        );
+        a.push(6, GraphEvent::Release);
+        a.render(0, Input::Audio(&[0.5; 8]), 0.0);
+        assert_eq!(a.buffer, vec![0.5, 0.5, 5.0, 5.0, 4.5, 4.0, 0.5, 0.5]);
+        a.render(8, Input::Audio(&[0.5; 8]), 0.0);
+        assert!(!a.active);
+    }
+}
@@ -0,0 +1,364 @@
+use std::io::{self, Seek, SeekFrom, Write};
+
+use crate::GenGraph;
+use crate::recorder::{WavFormat, write_wav_header};
+use crate::util::Sample;
+use crate::util::recycle;
+
+/// A destination for interleaved audio frames, fed block by block by `stream_to_sink`.
+pub trait AudioSink {
+    /// Called once before any frames are written.
+    fn begin(&mut self, channels: usize, sample_rate: f32) -> io::Result<()>;
+    /// Write interleaved frames; `samples.len()` is a multiple of the channel count.
+    fn write_frames(&mut self, samples: &[Sample]) -> io::Result<()>;
+    /// Called once after the last frames are written.
+    fn finish(&mut self) -> io::Result<()>;
+}
+
+/// Pull blocks from `graph` and push the outputs named by `labels` to `sink` as
+/// interleaved frames, one channel per label. Renders `total_samples` frames, or, if
+/// `None`, runs until the sink returns an error. Returns the number of frames written.
+pub fn stream_to_sink<S: AudioSink + ?Sized>(
+    graph: &mut GenGraph,
+    labels: &[String],
+    sink: &mut S,
+    total_samples: Option<usize>,
+) -> io::Result<usize> {
+    for label in labels {
+        graph
+            .try_get_output(label)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    }
+    let channels = labels.len();
+    let buffer_size = graph.buffer_size;
+    let mut interleaved: Vec<Sample> = Vec::with_capacity(buffer_size * channels);
+    // Empty table of the labelled output blocks, kept to reuse its allocation
+    let mut output_scratch: Vec<&'static [Sample]> = Vec::with_capacity(channels);
+
+    sink.begin(channels, graph.sample_rate)?;
+    let mut written = 0;
+    while total_samples.is_none_or(|total| written < total) {
+        graph.process();
+        let frames = match total_samples {
+            Some(total) => buffer_size.min(total - written),
+            None => buffer_size,
+        };
+        let mut outputs: Vec<&[Sample]> = recycle(output_scratch);
+        outputs.extend(labels.iter().map(|label| graph.get_output_by_label(label)));
+        interleaved.clear();
+        for i in 0..frames {
+            interleaved.extend(outputs.iter().map(|out| out[i]));
+        }
+        output_scratch = recycle(outputs);
+        sink.write_frames(&interleaved)?;
+        written += frames;
+    }
+    sink.finish()?;
+    Ok(written)
+}
+
+//------------------------------------------------------------------------------
+
+/// Streams a WAV file to a seekable writer.
+///
+/// The header is written with zero sizes and patched with the real sizes at least once
+/// per second of audio and on `finish`, so a file interrupted mid-stream remains
+/// readable.
+pub struct WavSink<W: Write + Seek> {
+    w: W,
+    format: WavFormat,
+    channels: usize,
+    sample_rate: u32,
+    frames: usize,
+    frames_since_patch: usize,
+}
+
+impl<W: Write + Seek> WavSink<W> {
+    pub fn new(w: W, format: WavFormat) -> Self {
+        Self {
+            w,
+            format,
+            channels: 0,
+            sample_rate: 0,
+            frames: 0,
+            frames_since_patch: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.w
+    }
+
+    fn data_size(&self) -> u32 {
+        let bytes =
+            self.frames * self.channels * (self.format.bits_per_sample() / 8) as usize;
+        u32::try_from(bytes).unwrap_or(u32::MAX - 36)
+    }
+
+    fn patch_header(&mut self) -> io::Result<()> {
+        let data_size = self.data_size();
+        let end = self.w.stream_position()?;
+        self.w.seek(SeekFrom::Start(0))?;
+        write_wav_header(
+            &mut self.w,
+            self.format,
+            self.channels as u16,
+            self.sample_rate,
+            data_size,
+        )?;
+        self.w.seek(SeekFrom::Start(end))?;
+        self.frames_since_patch = 0;
+        Ok(())
+    }
+}
+
+impl<W: Write + Seek> AudioSink for WavSink<W> {
+    fn begin(&mut self, channels: usize, sample_rate: f32) -> io::Result<()> {
+        self.channels = channels;
+        self.sample_rate = sample_rate as u32;
+        write_wav_header(
+            &mut self.w,
+            self.format,
+            channels as u16,
+            self.sample_rate,
+            0,
+        )
+    }
+
+    fn write_frames(&mut self, samples: &[Sample]) -> io::Result<()> {
+        for &s in samples {
+            self.format.write_sample(&mut self.w, s)?;
+        }
+        let frames = samples.len() / self.channels.max(1);
+        self.frames += frames;
+        self.frames_since_patch += frames;
+        if self.frames_since_patch >= self.sample_rate as usize {
+            self.patch_header()?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.patch_header()?;
+        self.w.flush()
+    }
+}
+
+//------------------------------------------------------------------------------
+
+/// Streams a WAV file to a writer that cannot seek, such as stdout.
+///
+/// If the total frame count is known the header carries the exact sizes; otherwise it
+/// uses the maximum sizes, which streaming readers such as `aplay` and `sox` treat as
+/// unbounded.
+pub struct WavPipeSink<W: Write> {
+    w: W,
+    format: WavFormat,
+    total_frames: Option<usize>,
+}
+
+impl<W: Write> WavPipeSink<W> {
+    pub fn new(w: W, format: WavFormat, total_frames: Option<usize>) -> Self {
+        Self {
+            w,
+            format,
+            total_frames,
+        }
+    }
+}
+
+impl<W: Write> AudioSink for WavPipeSink<W> {
+    fn begin(&mut self, channels: usize, sample_rate: f32) -> io::Result<()> {
+        let bytes_per_frame = channels * (self.format.bits_per_sample() / 8) as usize;
+        let data_size = self
+            .total_frames
+            .and_then(|frames| u32::try_from(frames * bytes_per_frame).ok())
+            .unwrap_or(u32::MAX - 36);
+        write_wav_header(
+            &mut self.w,
+            self.format,
+            channels as u16,
+            sample_rate as u32,
+            data_size,
+        )
+    }
+
+    fn write_frames(&mut self, samples: &[Sample]) -> io::Result<()> {
+        for &s in samples {
+            self.format.write_sample(&mut self.w, s)?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.w.flush()
+    }
+}
+
+//------------------------------------------------------------------------------
+
+/// Streams headerless interleaved PCM, encoded as `format` (e.g. `Float32` for f32 or
+/// `Int16` for s16le), for piping into tools such as `aplay` or `sox`.
+pub struct RawSink<W: Write> {
+    w: W,
+    format: WavFormat,
+}
+
+impl<W: Write> RawSink<W> {
+    pub fn new(w: W, format: WavFormat) -> Self {
+        Self { w, format }
+    }
+}
+
+impl<W: Write> AudioSink for RawSink<W> {
+    fn begin(&mut self, _channels: usize, _sample_rate: f32) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn write_frames(&mut self, samples: &[Sample]) -> io::Result<()> {
+        for &s in samples {
+            self.format.write_sample(&mut self.w, s)?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.w.flush()
+    }
+}
+
+//------------------------------------------------------------------------------
+
+/// Discards all frames, counting the samples; useful for benchmarking graph rendering.
+#[derive(Default)]
+pub struct NullSink {
+    pub samples: usize,
+}
+
+impl AudioSink for NullSink {
+    fn begin(&mut self, _channels: usize, _sample_rate: f32) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn write_frames(&mut self, samples: &[Sample]) -> io::Result<()> {
+        self.samples += samples.len();
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+//------------------------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::register_many;
+    use crate::{UGClock, UnitRate};
+    use std::io::Cursor;
+
+    fn graph() -> GenGraph {
+        let mut g = GenGraph::new(8.0, 8);
+        register_many![g,
+            "a" => 0.5,
+            "c" => UGClock::new(4.0, UnitRate::Samples),
+        ];
+        g
+    }
+
+    fn labels() -> Vec<String> {
+        vec!["a.out".to_string(), "c.out".to_string()]
+    }
+
+    #[test]
+    fn test_stream_wav_sink_a() {
+        let mut g = graph();
+        let mut sink = WavSink::new(Cursor::new(Vec::new()), WavFormat::Float32);
+        let frames = stream_to_sink(&mut g, &labels(), &mut sink, Some(20)).unwrap();
+        assert_eq!(frames, 20);
+
+        let bytes = sink.into_inner().into_inner();
+        let mut reader = hound::WavReader::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(reader.spec().channels, 2);
+        assert_eq!(reader.spec().sample_rate, 8);
+        assert_eq!(reader.duration(), 20);
+        let samples: Vec<f32> = reader.samples::<f32>().map(|s| s.unwrap()).collect();
+        assert_eq!(&samples[..8], &[0.5, 1.0, 0.5, 0.0, 0.5, 0.0, 0.5, 0.0]);
+    }
+
+    #[test]
+    fn test_wav_sink_patches_header_while_streaming() {
+        let mut sink = WavSink::new(Cursor::new(Vec::new()), WavFormat::Int16);
+        sink.begin(2, 8.0).unwrap();
+        let block = vec![0.0; 16];
+        sink.write_frames(&block).unwrap();
+        // one second (8 frames) has been written, so the header is already patched
+        let bytes = sink.into_inner().into_inner();
+        assert_eq!(u32::from_le_bytes(bytes[40..44].try_into().unwrap()), 32);
+        assert_eq!(bytes.len(), 44 + 32);
+    }
+
+    #[test]
+    fn test_stream_wav_pipe_sink_a() {
+        let mut g = graph();
+        let mut out = Vec::new();
+        let mut sink = WavPipeSink::new(&mut out, WavFormat::Int16, Some(12));
+        stream_to_sink(&mut g, &labels(), &mut sink, Some(12)).unwrap();
+        let reader = hound::WavReader::new(Cursor::new(out)).unwrap();
+        assert_eq!(reader.duration(), 12);
+    }
+
+    #[test]
+    fn test_stream_raw_sink_a() {
+        let mut g = graph();
+        let mut out = Vec::new();
+        let mut sink = RawSink::new(&mut out, WavFormat::Int16);
+        stream_to_sink(&mut g, &labels(), &mut sink, Some(10)).unwrap();
+        assert_eq!(out.len(), 10 * 2 * 2);
+        assert_eq!(i16::from_le_bytes([out[0], out[1]]), 16384);
+        assert_eq!(i16::from_le_bytes([out[2], out[3]]), 32767);
+    }
+
+    #[test]
+    fn test_stream_null_sink_a() {
+        let mut g = graph();
+        let mut sink = NullSink::default();
+        let frames = stream_to_sink(&mut g, &labels(), &mut sink, Some(100)).unwrap();
+        assert_eq!(frames, 100);
+        assert_eq!(sink.samples, 200);
+    }
+
+    #[test]
+    fn test_stream_unknown_label() {
+        let mut g = graph();
+        let mut sink = NullSink::default();
+        let err = stream_to_sink(&mut g, &["x.out".to_string()], &mut sink, Some(8))
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    /// With no total, streaming runs until the sink fails.
+    #[test]
+    fn test_stream_indefinite_until_error() {
+        struct Limited(usize);
+        impl AudioSink for Limited {
+            fn begin(&mut self, _: usize, _: f32) -> io::Result<()> {
+                Ok(())
+            }
+            fn write_frames(&mut self, samples: &[Sample]) -> io::Result<()> {
+                self.0 += samples.len();
+                if self.0 > 64 {
+                    return Err(io::Error::from(io::ErrorKind::BrokenPipe));
+                }
+                Ok(())
+            }
+            fn finish(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+        let mut g = graph();
+        let err = stream_to_sink(&mut g, &labels(), &mut Limited(0), None).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+    }
+}
@@ -1,8 +1,11 @@
-use rand::{Rng, SeedableRng, rngs::StdRng};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
 use serde::Deserialize;
 use serde::Serialize;
+use serde_json::Value;
 use wide::{CmpNe, f32x8};
 
+use crate::graph_facade::UGFacade;
 use crate::util::Sample;
 use crate::util::UnitRate;
 use crate::util::unit_rate_to_hz;
@@ -16,16 +19,128 @@ fn simd_load(slice: &[f32], offset: usize) -> f32x8 {
     f32x8::from(<[f32; 8]>::try_from(&slice[offset..offset + 8]).unwrap())
 }
 
+/// Load 8 values of `input` starting at `offset`: samples of an audio-rate input, or the
+/// value of a control-rate input in every lane.
+#[inline(always)]
+pub(crate) fn simd_input(input: &Input, offset: usize) -> f32x8 {
+    match input {
+        Input::Audio(samples) => simd_load(samples, offset),
+        Input::Control(value) => f32x8::splat(*value),
+        Input::Unconnected => f32x8::splat(0.0),
+    }
+}
+
+/// Write `f(i)` to each 8-sample chunk of `out` starting at `i`.
+///
+/// A control-rate output, a single sample, takes the first lane of `f(0)`.
+#[inline(always)]
+pub(crate) fn simd_fill(out: &mut [Sample], f: impl Fn(usize) -> f32x8) {
+    if out.len() < 8 {
+        let len = out.len();
+        out.copy_from_slice(&f(0).to_array()[..len]);
+        return;
+    }
+    for c in 0..out.len() / 8 {
+        let i = c * 8;
+        out[i..i + 8].copy_from_slice(&f(i).to_array());
+    }
+}
+
 //------------------------------------------------------------------------------
 
-pub trait UGen {
+/// The rate of a signal: one value per sample, or one value per block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rate {
+    Audio,
+    Control,
+}
+
+/// One block of a UGen input, as passed to `UGen::process`.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum Input<'a> {
+    /// One value per sample, from an audio-rate output.
+    Audio(&'a [Sample]),
+    /// One value for the whole block, from a control-rate output or an input default.
+    Control(Sample),
+    /// Not connected; the UGen applies its own default.
+    #[default]
+    Unconnected,
+}
+
+impl<'a> Input<'a> {
+    pub fn is_connected(&self) -> bool {
+        !matches!(self, Input::Unconnected)
+    }
+
+    /// The value at sample `i`, or `None` if unconnected.
+    #[inline(always)]
+    pub fn get(&self, i: usize) -> Option<Sample> {
+        match self {
+            Input::Audio(samples) => samples.get(i).copied(),
+            Input::Control(value) => Some(*value),
+            Input::Unconnected => None,
+        }
+    }
+
+    /// The value at sample `i`, or `default` if unconnected.
+    #[inline(always)]
+    pub fn value_or(&self, i: usize, default: Sample) -> Sample {
+        self.get(i).unwrap_or(default)
+    }
+
+    /// The samples of an audio-rate input.
+    pub fn audio(&self) -> Option<&'a [Sample]> {
+        match self {
+            Input::Audio(samples) => Some(samples),
+            _ => None,
+        }
+    }
+
+    /// The rate of a connected input.
+    pub fn rate(&self) -> Option<Rate> {
+        match self {
+            Input::Audio(_) => Some(Rate::Audio),
+            Input::Control(_) => Some(Rate::Control),
+            Input::Unconnected => None,
+        }
+    }
+}
+
+/// `Rate::Control` if no input is audio-rate, else `Rate::Audio`; the output rate of a
+/// stateless UGen.
+pub fn rate_of(inputs: &[Input]) -> Rate {
+    if inputs.iter().any(|i| i.rate() == Some(Rate::Audio)) {
+        Rate::Audio
+    } else {
+        Rate::Control
+    }
+}
+
+pub trait UGen: Send + Sync {
+    /// Render one block.
+    ///
+    /// Each output slice has one sample per sample of the block, or a single sample if
+    /// `output_rate` returned `Rate::Control`; every sample must be written.
     fn process(
         &mut self,
-        inputs: &[&[Sample]],
+        inputs: &[Input],
         outputs: &mut [&mut [Sample]],
         sample_rate: f32,
         time_sample: usize,
     );
+    /// The rate of output `output` for a block with these `inputs`.
+    ///
+    /// Control-rate outputs are rendered as one value per block and passed to connected
+    /// inputs as `Input::Control`. Defaults to `Rate::Audio`.
+    fn output_rate(&self, _output: usize, _inputs: &[Input]) -> Rate {
+        Rate::Audio
+    }
+    /// True if the outputs depend only on the current inputs (no state, time, or
+    /// randomness), so that `GenGraph::compile` may replace a node whose inputs are all
+    /// constant with its output values. Defaults to `false`.
+    fn is_pure(&self) -> bool {
+        false
+    }
     fn type_name(&self) -> &'static str;
     fn input_names(&self) -> &[String];
     fn output_names(&self) -> &[String];
@@ -35,6 +150,12 @@ pub trait UGen {
     fn describe_config(&self) -> Option<String> {
         None
     }
+    /// The construction arguments of this UGen, used to serialize a graph with
+    /// `GenGraph::to_facade_json` and `GenGraph::to_chain`. Returns `None` if the UGen
+    /// cannot be serialized.
+    fn to_facade(&self) -> Option<UGFacade> {
+        None
+    }
     fn first_input(&self) -> Option<&str> {
         self.input_names().first().map(|s| s.as_str())
     }
@@ -53,8 +174,38 @@ pub trait UGen {
             None
         }
     }
+    /// The running state of this UGen (phases, filter memory, delay lines, RNG state),
+    /// used by `GenGraph::snapshot`. Returns `None` if the UGen has no state.
+    fn save_state(&self) -> Option<Value> {
+        None
+    }
+    /// Restore a state returned by `save_state` on a UGen of the same type and
+    /// configuration.
+    fn load_state(&mut self, _state: &Value) -> Result<(), String> {
+        Ok(())
+    }
+    /// Reseed the random number generator of a UGen constructed without a seed, as
+    /// `GenGraph::set_seed` does with a seed derived from the master seed and the node
+    /// name. UGens constructed with a seed, and UGens without randomness, ignore it.
+    fn set_graph_seed(&mut self, _seed: u64) {}
 }
 
+/// Implement `UGen::save_state` and `UGen::load_state` for a UGen that derives
+/// `Serialize` and `Deserialize`, saving all of its fields.
+macro_rules! impl_serde_state {
+    () => {
+        fn save_state(&self) -> Option<serde_json::Value> {
+            serde_json::to_value(self).ok()
+        }
+
+        fn load_state(&mut self, state: &serde_json::Value) -> Result<(), String> {
+            *self = serde_json::from_value(state.clone()).map_err(|e| e.to_string())?;
+            Ok(())
+        }
+    };
+}
+pub(crate) use impl_serde_state;
+
 //------------------------------------------------------------------------------
 
 pub struct UGConst {
@@ -72,6 +223,10 @@ impl UGen for UGConst {
         "UGConst"
     }
 
+    fn to_facade(&self) -> Option<UGFacade> {
+        Some(UGFacade::Const { value: self.value })
+    }
+
     fn describe_config(&self) -> Option<String> {
         Some(format!("value = {:.3}", self.value))
     }
@@ -85,20 +240,22 @@ impl UGen for UGConst {
         NAMES.get_or_init(|| vec!["out".to_string()])
     }
 
+    fn is_pure(&self) -> bool {
+        true
+    }
+
+    fn output_rate(&self, _output: usize, _inputs: &[Input]) -> Rate {
+        Rate::Control
+    }
+
     fn process(
         &mut self,
-        _inputs: &[&[Sample]],
+        _inputs: &[Input],
         outputs: &mut [&mut [Sample]],
         _sample_rate: f32,
         _time_sample: usize,
     ) {
-        let out = &mut outputs[0];
-        let chunks = out.len() / 8;
-        let v = f32x8::splat(self.value);
-        for c in 0..chunks {
-            let i = c * 8;
-            out[i..i + 8].copy_from_slice(&v.to_array());
-        }
+        outputs[0].fill(self.value);
     }
 }
 
@@ -119,6 +276,10 @@ impl UGen for UGAsHz {
         "UGAsHz"
     }
 
+    fn to_facade(&self) -> Option<UGFacade> {
+        Some(UGFacade::AsHz { mode: self.mode })
+    }
+
     fn describe_config(&self) -> Option<String> {
         Some(format!("mode = {:?}", self.mode).to_lowercase())
     }
@@ -133,64 +294,54 @@ impl UGen for UGAsHz {
         NAMES.get_or_init(|| vec!["out".to_string()])
     }
 
+    fn is_pure(&self) -> bool {
+        true
+    }
+
+    fn output_rate(&self, _output: usize, inputs: &[Input]) -> Rate {
+        rate_of(inputs)
+    }
+
     fn process(
         &mut self,
-        inputs: &[&[Sample]],
+        inputs: &[Input],
         outputs: &mut [&mut [Sample]],
         sample_rate: f32,
         _time_sample: usize,
     ) {
-        let input = inputs.first().copied().unwrap_or(&[]);
+        let input = inputs.first().copied().unwrap_or_default();
         let out = &mut outputs[0];
-        let chunks = out.len() / 8;
         let zero = f32x8::splat(0.0);
 
         //  zero-safe divide for Seconds and Samples works by bitwise-ANDing the computed reciprocal with the simd_ne mask — positions where the input is zero have all-zero mask bits, which zero out the Inf result cleanly.
 
         // NOTE: util::unit_rate_to_hz provides an element-wise implementation.
         match self.mode {
-            UnitRate::Hz => {
-                for c in 0..chunks {
-                    let i = c * 8;
-                    out[i..i + 8].copy_from_slice(&simd_load(input, i).to_array());
-                }
-            }
-            UnitRate::Seconds => {
-                for c in 0..chunks {
-                    let i = c * 8;
-                    let x = simd_load(input, i);
-                    let result = x.simd_ne(zero) & (f32x8::splat(1.0) / x);
-                    out[i..i + 8].copy_from_slice(&result.to_array());
-                }
-            }
+            UnitRate::Hz => simd_fill(out, |i| simd_input(&input, i)),
+            UnitRate::Seconds => simd_fill(out, |i| {
+                let x = simd_input(&input, i);
+                x.simd_ne(zero) & (f32x8::splat(1.0) / x)
+            }),
             UnitRate::Samples => {
                 let sr = f32x8::splat(sample_rate);
-                for c in 0..chunks {
-                    let i = c * 8;
-                    let x = simd_load(input, i);
-                    let result = x.simd_ne(zero) & (sr / x);
-                    out[i..i + 8].copy_from_slice(&result.to_array());
-                }
+                simd_fill(out, |i| {
+                    let x = simd_input(&input, i);
+                    x.simd_ne(zero) & (sr / x)
+                })
             }
             UnitRate::Midi => {
                 let base = f32x8::splat(2.0);
                 let a = f32x8::splat(440.0);
                 let offset = f32x8::splat(69.0);
                 let inv12 = f32x8::splat(1.0 / 12.0);
-                for c in 0..chunks {
-                    let i = c * 8;
-                    let x = simd_load(input, i);
-                    let result = a * base.pow_f32x8((x - offset) * inv12);
-                    out[i..i + 8].copy_from_slice(&result.to_array());
-                }
+                simd_fill(out, |i| {
+                    let x = simd_input(&input, i);
+                    a * base.pow_f32x8((x - offset) * inv12)
+                })
             }
             UnitRate::Bpm => {
                 let inv60 = f32x8::splat(1.0 / 60.0);
-                for c in 0..chunks {
-                    let i = c * 8;
-                    let x = simd_load(input, i);
-                    out[i..i + 8].copy_from_slice(&(x * inv60).to_array());
-                }
+                simd_fill(out, |i| simd_input(&input, i) * inv60)
             }
         }
     }
@@ -227,6 +378,13 @@ impl UGen for UGRound {
         "UGRound"
     }
 
+    fn to_facade(&self) -> Option<UGFacade> {
+        Some(UGFacade::Round {
+            places: self.places,
+            mode: self.mode,
+        })
+    }
+
     fn describe_config(&self) -> Option<String> {
         Some(format!("places = {}, mode = {:?}", self.places, self.mode))
     }
@@ -241,38 +399,33 @@ impl UGen for UGRound {
         NAMES.get_or_init(|| vec!["out".to_string()])
     }
 
+    fn is_pure(&self) -> bool {
+        true
+    }
+
+    fn output_rate(&self, _output: usize, inputs: &[Input]) -> Rate {
+        rate_of(inputs)
+    }
+
     fn process(
         &mut self,
-        inputs: &[&[Sample]],
+        inputs: &[Input],
         outputs: &mut [&mut [Sample]],
         _sample_rate: f32,
         _time_sample: usize,
     ) {
-        let input = inputs[0];
+        let input = &inputs[0];
         let out = &mut outputs[0];
-        let chunks = out.len() / 8;
         let factor_v = f32x8::splat(self.factor);
         match self.mode {
-            ModeRound::Round => {
-                for c in 0..chunks {
-                    let i = c * 8;
-                    let result = (simd_load(input, i) * factor_v).round() / factor_v;
-                    out[i..i + 8].copy_from_slice(&result.to_array());
-                }
-            }
-            ModeRound::Floor => {
-                for c in 0..chunks {
-                    let i = c * 8;
-                    let result = (simd_load(input, i) * factor_v).floor() / factor_v;
-                    out[i..i + 8].copy_from_slice(&result.to_array());
-                }
-            }
+            ModeRound::Round => simd_fill(out, |i| {
+                (simd_input(input, i) * factor_v).round() / factor_v
+            }),
+            ModeRound::Floor => simd_fill(out, |i| {
+                (simd_input(input, i) * factor_v).floor() / factor_v
+            }),
             ModeRound::Ceil => {
-                for c in 0..chunks {
-                    let i = c * 8;
-                    let result = (simd_load(input, i) * factor_v).ceil() / factor_v;
-                    out[i..i + 8].copy_from_slice(&result.to_array());
-                }
+                simd_fill(out, |i| (simd_input(input, i) * factor_v).ceil() / factor_v)
             }
         }
     }
@@ -299,6 +452,10 @@ impl UGen for UGFloor {
         "UGFloor"
     }
 
+    fn to_facade(&self) -> Option<UGFacade> {
+        Some(UGFacade::Floor {})
+    }
+
     fn input_names(&self) -> &[String] {
         static NAMES: std::sync::OnceLock<Vec<String>> = std::sync::OnceLock::new();
         NAMES.get_or_init(|| vec!["in".to_string()])
@@ -309,21 +466,23 @@ impl UGen for UGFloor {
         NAMES.get_or_init(|| vec!["out".to_string()])
     }
 
+    fn is_pure(&self) -> bool {
+        true
+    }
+
+    fn output_rate(&self, _output: usize, inputs: &[Input]) -> Rate {
+        rate_of(inputs)
+    }
+
     fn process(
         &mut self,
-        inputs: &[&[Sample]],
+        inputs: &[Input],
         outputs: &mut [&mut [Sample]],
         _sample_rate: f32,
         _time_sample: usize,
     ) {
-        let input = inputs[0];
-        let out = &mut outputs[0];
-        let chunks = out.len() / 8;
-        for c in 0..chunks {
-            let i = c * 8;
-            let result = simd_load(input, i).floor();
-            out[i..i + 8].copy_from_slice(&result.to_array());
-        }
+        let input = &inputs[0];
+        simd_fill(outputs[0], |i| simd_input(input, i).floor());
     }
 }
 
@@ -348,6 +507,10 @@ impl UGen for UGCeil {
         "UGCeil"
     }
 
+    fn to_facade(&self) -> Option<UGFacade> {
+        Some(UGFacade::Ceil {})
+    }
+
     fn input_names(&self) -> &[String] {
         static NAMES: std::sync::OnceLock<Vec<String>> = std::sync::OnceLock::new();
         NAMES.get_or_init(|| vec!["in".to_string()])
@@ -358,21 +521,23 @@ impl UGen for UGCeil {
         NAMES.get_or_init(|| vec!["out".to_string()])
     }
 
+    fn is_pure(&self) -> bool {
+        true
+    }
+
+    fn output_rate(&self, _output: usize, inputs: &[Input]) -> Rate {
+        rate_of(inputs)
+    }
+
     fn process(
         &mut self,
-        inputs: &[&[Sample]],
+        inputs: &[Input],
         outputs: &mut [&mut [Sample]],
         _sample_rate: f32,
         _time_sample: usize,
     ) {
-        let input = inputs[0];
-        let out = &mut outputs[0];
-        let chunks = out.len() / 8;
-        for c in 0..chunks {
-            let i = c * 8;
-            let result = simd_load(input, i).ceil();
-            out[i..i + 8].copy_from_slice(&result.to_array());
-        }
+        let input = &inputs[0];
+        simd_fill(outputs[0], |i| simd_input(input, i).ceil());
     }
 }
 
@@ -383,13 +548,21 @@ pub struct UGSum {
 }
 
 impl UGSum {
+    /// Panics if `inputs` is less than 2; see `try_new`.
     pub fn new(inputs: usize) -> Self {
+        Self::try_new(inputs).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Like `new`, but returns an error instead of panicking.
+    pub fn try_new(inputs: usize) -> Result<Self, String> {
         if inputs <= 1 {
-            panic!("Input count should be greater than 1");
+            return Err(format!(
+                "Input count should be greater than 1, got {inputs}"
+            ));
         }
         let input_refs: Vec<String> = (1..inputs + 1).map(|i| format!("in{i}")).collect();
 
-        Self { input_refs }
+        Ok(Self { input_refs })
     }
 }
 
@@ -398,6 +571,12 @@ impl UGen for UGSum {
         "UGSum"
     }
 
+    fn to_facade(&self) -> Option<UGFacade> {
+        Some(UGFacade::Sum {
+            inputs: self.input_refs.len(),
+        })
+    }
+
     fn input_names(&self) -> &[String] {
         &self.input_refs
     }
@@ -407,37 +586,34 @@ impl UGen for UGSum {
         NAMES.get_or_init(|| vec!["out".to_string()])
     }
 
+    fn is_pure(&self) -> bool {
+        true
+    }
+
+    fn output_rate(&self, _output: usize, inputs: &[Input]) -> Rate {
+        rate_of(inputs)
+    }
+
     fn process(
         &mut self,
-        inputs: &[&[Sample]],
+        inputs: &[Input],
         outputs: &mut [&mut [Sample]],
         _sample_rate: f32,
         _time_sample: usize,
     ) {
         let out = &mut outputs[0];
-        let len = out.len();
-        let chunks = len / 8;
-
         match inputs.len() {
             2 => {
-                let a = inputs[0];
-                let b = inputs[1];
-                for c in 0..chunks {
-                    let i = c * 8;
-                    let result = simd_load(a, i) + simd_load(b, i);
-                    out[i..i + 8].copy_from_slice(&result.to_array());
-                }
+                let (a, b) = (&inputs[0], &inputs[1]);
+                simd_fill(out, |i| simd_input(a, i) + simd_input(b, i));
             }
-            _ => {
-                for c in 0..chunks {
-                    let i = c * 8;
-                    let mut acc = f32x8::splat(0.0_f32);
-                    for input in inputs {
-                        acc += simd_load(input, i);
-                    }
-                    out[i..i + 8].copy_from_slice(&acc.to_array());
+            _ => simd_fill(out, |i| {
+                let mut acc = f32x8::splat(0.0_f32);
+                for input in inputs {
+                    acc += simd_input(input, i);
                 }
-            }
+                acc
+            }),
         }
     }
 }
@@ -449,13 +625,21 @@ pub struct UGMult {
 }
 
 impl UGMult {
+    /// Panics if `inputs` is less than 2; see `try_new`.
     pub fn new(inputs: usize) -> Self {
+        Self::try_new(inputs).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Like `new`, but returns an error instead of panicking.
+    pub fn try_new(inputs: usize) -> Result<Self, String> {
         if inputs <= 1 {
-            panic!("Input count should be greater than 1");
+            return Err(format!(
+                "Input count should be greater than 1, got {inputs}"
+            ));
         }
         let input_refs: Vec<String> = (1..inputs + 1).map(|i| format!("in{i}")).collect();
 
-        Self { input_refs }
+        Ok(Self { input_refs })
     }
 }
 
@@ -464,6 +648,12 @@ impl UGen for UGMult {
         "UGMult"
     }
 
+    fn to_facade(&self) -> Option<UGFacade> {
+        Some(UGFacade::Mult {
+            inputs: self.input_refs.len(),
+        })
+    }
+
     fn input_names(&self) -> &[String] {
         &self.input_refs
     }
@@ -473,37 +663,34 @@ impl UGen for UGMult {
         NAMES.get_or_init(|| vec!["out".to_string()])
     }
 
+    fn is_pure(&self) -> bool {
+        true
+    }
+
+    fn output_rate(&self, _output: usize, inputs: &[Input]) -> Rate {
+        rate_of(inputs)
+    }
+
     fn process(
         &mut self,
-        inputs: &[&[Sample]],
+        inputs: &[Input],
         outputs: &mut [&mut [Sample]],
         _sample_rate: f32,
         _time_sample: usize,
     ) {
         let out = &mut outputs[0];
-        let len = out.len();
-        let chunks = len / 8;
-
         match inputs.len() {
             2 => {
-                let a = inputs[0];
-                let b = inputs[1];
-                for c in 0..chunks {
-                    let i = c * 8;
-                    let result = simd_load(a, i) * simd_load(b, i);
-                    out[i..i + 8].copy_from_slice(&result.to_array());
-                }
+                let (a, b) = (&inputs[0], &inputs[1]);
+                simd_fill(out, |i| simd_input(a, i) * simd_input(b, i));
             }
-            _ => {
-                for c in 0..chunks {
-                    let i = c * 8;
-                    let mut acc = f32x8::splat(1.0_f32);
-                    for input in inputs {
-                        acc *= simd_load(input, i);
-                    }
-                    out[i..i + 8].copy_from_slice(&acc.to_array());
+            _ => simd_fill(out, |i| {
+                let mut acc = f32x8::splat(1.0_f32);
+                for input in inputs {
+                    acc *= simd_input(input, i);
                 }
-            }
+                acc
+            }),
         }
     }
 }
@@ -569,17 +756,25 @@ pub struct UGPan {
 }
 
 impl UGPan {
+    /// Panics if `outputs` is less than 2; see `try_new`.
     pub fn new(outputs: usize, pan: Sample) -> Self {
+        Self::try_new(outputs, pan).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Like `new`, but returns an error instead of panicking.
+    pub fn try_new(outputs: usize, pan: Sample) -> Result<Self, String> {
         if outputs < 2 {
-            panic!("Output count should be greater than 1");
+            return Err(format!(
+                "Output count should be greater than 1, got {outputs}"
+            ));
         }
         let output_refs: Vec<String> =
             (1..outputs + 1).map(|i| format!("out{i}")).collect();
 
-        Self {
+        Ok(Self {
             output_refs,
             default_pan: pan,
-        }
+        })
     }
 }
 
@@ -594,6 +789,13 @@ impl UGen for UGPan {
         "UGPan"
     }
 
+    fn to_facade(&self) -> Option<UGFacade> {
+        Some(UGFacade::Pan {
+            outputs: Some(self.output_refs.len()),
+            pan: self.default_pan,
+        })
+    }
+
     fn input_names(&self) -> &[String] {
         static NAMES: std::sync::OnceLock<Vec<String>> = std::sync::OnceLock::new();
         NAMES.get_or_init(|| vec!["in".to_string(), "pan".to_string()])
@@ -612,13 +814,13 @@ impl UGen for UGPan {
 
     fn process(
         &mut self,
-        inputs: &[&[Sample]],
+        inputs: &[Input],
         outputs: &mut [&mut [Sample]],
         _sample_rate: f32,
         _time_sample: usize,
     ) {
-        let input = inputs.first().copied().unwrap_or(&[]);
-        let pan = inputs.get(1).copied().unwrap_or(&[]);
+        let input = inputs.first().copied().unwrap_or_default();
+        let pan = inputs.get(1).copied().unwrap_or_default();
         let output_count = outputs.len();
         if output_count == 0 {
             return;
@@ -629,8 +831,8 @@ impl UGen for UGPan {
         let n = outputs[0].len();
 
         for i in 0..n {
-            let x = input.get(i).copied().unwrap_or(0.0);
-            let pair_pos = pan.get(i).copied().unwrap_or(self.default_pan);
+            let x = input.get(i).unwrap_or(0.0);
+            let pair_pos = pan.get(i).unwrap_or(self.default_pan);
             pan_linear_accumulate(x, pair_pos, outputs, i);
         }
     }
@@ -646,12 +848,18 @@ pub struct UGMixLinear {
 }
 
 impl UGMixLinear {
+    /// Panics if `inputs` is less than 1 or `outputs` is less than 2; see `try_new`.
     pub fn new(inputs: usize, outputs: usize) -> Self {
+        Self::try_new(inputs, outputs).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Like `new`, but returns an error instead of panicking.
+    pub fn try_new(inputs: usize, outputs: usize) -> Result<Self, String> {
         if inputs < 1 {
-            panic!("Input count should be at least 1");
+            return Err(format!("Input count should be at least 1, got {inputs}"));
         }
         if outputs < 2 {
-            panic!("Output count should be at least 2");
+            return Err(format!("Output count should be at least 2, got {outputs}"));
         }
         let mut input_labels: Vec<String> = Vec::with_capacity(inputs * 3);
         for i in 1..=inputs {
@@ -663,12 +871,12 @@ impl UGMixLinear {
 
         let output_refs: Vec<String> = (1..=outputs).map(|i| format!("out{i}")).collect();
 
-        Self {
+        Ok(Self {
             input_count: inputs,
             output_count: outputs,
             input_refs,
             output_refs,
-        }
+        })
     }
 }
 
@@ -683,6 +891,13 @@ impl UGen for UGMixLinear {
         "UGMixLinear"
     }
 
+    fn to_facade(&self) -> Option<UGFacade> {
+        Some(UGFacade::MixLinear {
+            inputs: self.input_count,
+            outputs: self.output_count,
+        })
+    }
+
     fn input_names(&self) -> &[String] {
         &self.input_refs
     }
@@ -703,7 +918,7 @@ impl UGen for UGMixLinear {
 
     fn process(
         &mut self,
-        inputs: &[&[Sample]],
+        inputs: &[Input],
         outputs: &mut [&mut [Sample]],
         _sample_rate: f32,
         _time_sample: usize,
@@ -720,14 +935,14 @@ impl UGen for UGMixLinear {
 
         for ch in 0..self.input_count {
             let base = ch * 3;
-            let in_sig = inputs.get(base).copied().unwrap_or(&[]);
-            let in_pan = inputs.get(base + 1).copied().unwrap_or(&[]);
-            let in_level = inputs.get(base + 2).copied().unwrap_or(&[]);
+            let in_sig = inputs.get(base).copied().unwrap_or_default();
+            let in_pan = inputs.get(base + 1).copied().unwrap_or_default();
+            let in_level = inputs.get(base + 2).copied().unwrap_or_default();
 
             for i in 0..n {
-                let x = in_sig.get(i).copied().unwrap_or(0.0);
-                let pan = in_pan.get(i).copied().unwrap_or(default_pan);
-                let level = in_level.get(i).copied().unwrap_or(1.0);
+                let x = in_sig.get(i).unwrap_or(0.0);
+                let pan = in_pan.get(i).unwrap_or(default_pan);
+                let level = in_level.get(i).unwrap_or(1.0);
                 let gain = amplitude_to_gain(level);
                 pan_linear_accumulate(x * gain, pan, outputs, i);
             }
@@ -753,9 +968,17 @@ pub struct UGFade {
 }
 
 impl UGFade {
+    /// Panics if `channels` is less than 1; see `try_new`.
     pub fn new(channels: usize, level: Sample) -> Self {
+        Self::try_new(channels, level).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Like `new`, but returns an error instead of panicking.
+    pub fn try_new(channels: usize, level: Sample) -> Result<Self, String> {
         if channels < 1 {
-            panic!("Channel count should be at least 1");
+            return Err(format!(
+                "Channel count should be at least 1, got {channels}"
+            ));
         }
         let mut input_labels: Vec<String> = Vec::with_capacity(channels + 1);
         for i in 1..=channels {
@@ -767,12 +990,12 @@ impl UGFade {
         let output_refs: Vec<String> =
             (1..=channels).map(|i| format!("out{i}")).collect();
 
-        Self {
+        Ok(Self {
             channels,
             level,
             input_refs,
             output_refs,
-        }
+        })
     }
 }
 
@@ -787,6 +1010,13 @@ impl UGen for UGFade {
         "UGFade"
     }
 
+    fn to_facade(&self) -> Option<UGFacade> {
+        Some(UGFacade::Fade {
+            channels: self.channels,
+            level: self.level as f64,
+        })
+    }
+
     fn input_names(&self) -> &[String] {
         &self.input_refs
     }
@@ -805,7 +1035,7 @@ impl UGen for UGFade {
 
     fn process(
         &mut self,
-        inputs: &[&[Sample]],
+        inputs: &[Input],
         outputs: &mut [&mut [Sample]],
         _sample_rate: f32,
         _time_sample: usize,
@@ -816,45 +1046,38 @@ impl UGen for UGFade {
         };
         // Input layout: in1…inN occupy indices 0..channels, level is at channels.
         let level_input_index = self.channels;
-        let in_level = inputs.get(level_input_index).copied().unwrap_or(&[]);
+        let in_level = inputs.get(level_input_index).copied().unwrap_or_default();
 
         // Fast paths for the most common configurations avoid outer-loop overhead.
         // In all paths gain is computed once per sample, then applied across channels.
         if self.channels == 1 {
-            let in_sig = inputs.first().copied().unwrap_or(&[]);
+            let in_sig = inputs.first().copied().unwrap_or_default();
             let out = &mut outputs[0];
             for i in 0..n {
-                let level = in_level.get(i).copied().unwrap_or(self.level);
+                let level = in_level.get(i).unwrap_or(self.level);
                 let gain = amplitude_to_gain(level);
-                out[i] = in_sig.get(i).copied().unwrap_or(0.0) * gain;
+                out[i] = in_sig.get(i).unwrap_or(0.0) * gain;
             }
             return;
         }
 
         if self.channels == 2 {
             let (out01, _) = outputs.split_at_mut(2);
-            let in0 = inputs.first().copied().unwrap_or(&[]);
-            let in1 = inputs.get(1).copied().unwrap_or(&[]);
+            let in0 = inputs.first().copied().unwrap_or_default();
+            let in1 = inputs.get(1).copied().unwrap_or_default();
             #[allow(clippy::needless_range_loop)]
             for i in 0..n {
-                let gain =
-                    amplitude_to_gain(in_level.get(i).copied().unwrap_or(self.level));
-                out01[0][i] = in0.get(i).copied().unwrap_or(0.0) * gain;
-                out01[1][i] = in1.get(i).copied().unwrap_or(0.0) * gain;
+                let gain = amplitude_to_gain(in_level.get(i).unwrap_or(self.level));
+                out01[0][i] = in0.get(i).unwrap_or(0.0) * gain;
+                out01[1][i] = in1.get(i).unwrap_or(0.0) * gain;
             }
             return;
         }
 
         for i in 0..n {
-            let gain = amplitude_to_gain(in_level.get(i).copied().unwrap_or(self.level));
+            let gain = amplitude_to_gain(in_level.get(i).unwrap_or(self.level));
             for (ch, out) in outputs.iter_mut().enumerate().take(self.channels) {
-                let x = inputs
-                    .get(ch)
-                    .copied()
-                    .unwrap_or(&[])
-                    .get(i)
-                    .copied()
-                    .unwrap_or(0.0);
+                let x = inputs.get(ch).and_then(|input| input.get(i)).unwrap_or(0.0);
                 out[i] = x * gain;
             }
         }
@@ -863,10 +1086,11 @@ impl UGen for UGFade {
 
 //------------------------------------------------------------------------------
 
+#[derive(Serialize, Deserialize)]
 pub struct UGWhite {
     default_min: Sample,
     default_max: Sample,
-    rng: StdRng,
+    rng: ChaCha12Rng,
     seed: Option<u64>,
 }
 
@@ -877,7 +1101,7 @@ impl UGWhite {
         Self {
             default_min: -1.0,
             default_max: 1.0,
-            rng: StdRng::seed_from_u64(actual_seed),
+            rng: ChaCha12Rng::seed_from_u64(actual_seed),
             seed, // original user-provided seed
         }
     }
@@ -888,6 +1112,18 @@ impl UGen for UGWhite {
         "UGWhite"
     }
 
+    fn to_facade(&self) -> Option<UGFacade> {
+        Some(UGFacade::White { seed: self.seed })
+    }
+
+    impl_serde_state!();
+
+    fn set_graph_seed(&mut self, seed: u64) {
+        if self.seed.is_none() {
+            self.rng = ChaCha12Rng::seed_from_u64(seed);
+        }
+    }
+
     fn input_names(&self) -> &[String] {
         static NAMES: std::sync::OnceLock<Vec<String>> = std::sync::OnceLock::new();
         NAMES.get_or_init(|| vec!["min".to_string(), "max".to_string()])
@@ -912,17 +1148,17 @@ impl UGen for UGWhite {
 
     fn process(
         &mut self,
-        inputs: &[&[Sample]],
+        inputs: &[Input],
         outputs: &mut [&mut [Sample]],
         _sample_rate: f32,
         _time_sample: usize,
     ) {
-        let min_in = inputs.first().copied().unwrap_or(&[]);
-        let max_in = inputs.get(1).copied().unwrap_or(&[]);
+        let min_in = inputs.first().copied().unwrap_or_default();
+        let max_in = inputs.get(1).copied().unwrap_or_default();
         let out = &mut outputs[0];
         let n = out.len();
 
-        match (min_in.len() >= n, max_in.len() >= n) {
+        match (min_in.is_connected(), max_in.is_connected()) {
             // most comon case
             (false, false) => {
                 let (min, max) = (self.default_min, self.default_max);
@@ -931,20 +1167,23 @@ impl UGen for UGWhite {
                 }
             }
             (true, false) => {
-                let max = self.default_max;
+                let (min, max) = (self.default_min, self.default_max);
                 for i in 0..n {
-                    out[i] = self.rng.random_range(min_in[i]..=max);
+                    out[i] = self.rng.random_range(min_in.value_or(i, min)..=max);
                 }
             }
             (false, true) => {
-                let min = self.default_min;
+                let (min, max) = (self.default_min, self.default_max);
                 for i in 0..n {
-                    out[i] = self.rng.random_range(min..=max_in[i]);
+                    out[i] = self.rng.random_range(min..=max_in.value_or(i, max));
                 }
             }
             (true, true) => {
+                let (min, max) = (self.default_min, self.default_max);
                 for i in 0..n {
-                    out[i] = self.rng.random_range(min_in[i]..=max_in[i]);
+                    out[i] = self
+                        .rng
+                        .random_range(min_in.value_or(i, min)..=max_in.value_or(i, max));
                 }
             }
         }
@@ -953,6 +1192,7 @@ impl UGen for UGWhite {
 
 //------------------------------------------------------------------------------
 
+#[derive(Serialize, Deserialize)]
 pub struct UGSine {
     phase: Sample,
     default_freq: Sample,
@@ -984,6 +1224,12 @@ impl UGen for UGSine {
         "UGSine"
     }
 
+    fn to_facade(&self) -> Option<UGFacade> {
+        Some(UGFacade::Sine {})
+    }
+
+    impl_serde_state!();
+
     fn input_names(&self) -> &[String] {
         static NAMES: std::sync::OnceLock<Vec<String>> = std::sync::OnceLock::new();
         NAMES.get_or_init(|| {
@@ -1013,15 +1259,15 @@ impl UGen for UGSine {
 
     fn process(
         &mut self,
-        inputs: &[&[Sample]],
+        inputs: &[Input],
         outputs: &mut [&mut [Sample]],
         sample_rate: f32,
         _time_sample: usize,
     ) {
-        let freq_in = inputs.first().copied().unwrap_or(&[]);
-        let phase_in = inputs.get(1).copied().unwrap_or(&[]);
-        let min_in = inputs.get(2).copied().unwrap_or(&[]);
-        let max_in = inputs.get(3).copied().unwrap_or(&[]);
+        let freq_in = inputs.first().copied().unwrap_or_default();
+        let phase_in = inputs.get(1).copied().unwrap_or_default();
+        let min_in = inputs.get(2).copied().unwrap_or_default();
+        let max_in = inputs.get(3).copied().unwrap_or_default();
 
         let (wave_out, rest) = outputs.split_at_mut(1);
         let wave_out = &mut wave_out[0];
@@ -1030,31 +1276,31 @@ impl UGen for UGSine {
         let dt = 1.0 / sample_rate;
         let n = wave_out.len();
 
-        let phase_connected = phase_in.len() >= n;
-        let min_connected = min_in.len() >= n;
-        let max_connected = max_in.len() >= n;
-        let freq_connected = freq_in.len() >= n;
-
-        if !phase_connected && !min_connected && !max_connected {
-            // common case: phase/min/max at defaults
-            let phase_offset = self.default_phase_offset;
-            let (min, max) = (self.default_min, self.default_max);
-            if freq_connected {
-                for i in 0..n {
-                    self.phase += freq_in[i] * dt;
+        let audio = |input: &Input| input.rate() == Some(Rate::Audio);
+        if !audio(&phase_in) && !audio(&min_in) && !audio(&max_in) {
+            // common case: phase/min/max are control-rate or defaults, constant for the
+            // block
+            let phase_offset = phase_in.value_or(0, self.default_phase_offset);
+            let min = min_in.value_or(0, self.default_min);
+            let max = max_in.value_or(0, self.default_max);
+            // zipped rather than indexed, so the loops carry no bounds checks
+            let outs = wave_out.iter_mut().zip(trig_out.iter_mut());
+            if let Input::Audio(freq_in) = freq_in {
+                for ((wave, trig), freq) in outs.zip(&freq_in[..n]) {
+                    self.phase += freq * dt;
                     let crossed = self.phase >= 1.0;
                     if crossed {
                         self.phase -= 1.0;
                     }
                     let norm =
                         ((self.phase + phase_offset) * std::f32::consts::TAU).sin();
-                    wave_out[i] = min + (norm + 1.0) * 0.5 * (max - min);
-                    trig_out[i] = if crossed { 1.0 } else { 0.0 };
+                    *wave = min + (norm + 1.0) * 0.5 * (max - min);
+                    *trig = if crossed { 1.0 } else { 0.0 };
                 }
             } else {
                 // freq also constant: precompute phase increment
-                let phase_inc = self.default_freq * dt;
-                for i in 0..n {
+                let phase_inc = freq_in.value_or(0, self.default_freq) * dt;
+                for (wave, trig) in outs {
                     self.phase += phase_inc;
                     let crossed = self.phase >= 1.0;
                     if crossed {
@@ -1062,33 +1308,17 @@ impl UGen for UGSine {
                     }
                     let norm =
                         ((self.phase + phase_offset) * std::f32::consts::TAU).sin();
-                    wave_out[i] = min + (norm + 1.0) * 0.5 * (max - min);
-                    trig_out[i] = if crossed { 1.0 } else { 0.0 };
+                    *wave = min + (norm + 1.0) * 0.5 * (max - min);
+                    *trig = if crossed { 1.0 } else { 0.0 };
                 }
             }
         } else {
-            // general case: at least one of phase/min/max is connected
+            // general case: at least one of phase/min/max is audio-rate
             for i in 0..n {
-                let freq = if freq_connected {
-                    freq_in[i]
-                } else {
-                    self.default_freq
-                };
-                let phase_offset = if phase_connected {
-                    phase_in[i]
-                } else {
-                    self.default_phase_offset
-                };
-                let min = if min_connected {
-                    min_in[i]
-                } else {
-                    self.default_min
-                };
-                let max = if max_connected {
-                    max_in[i]
-                } else {
-                    self.default_max
-                };
+                let freq = freq_in.value_or(i, self.default_freq);
+                let phase_offset = phase_in.value_or(i, self.default_phase_offset);
+                let min = min_in.value_or(i, self.default_min);
+                let max = max_in.value_or(i, self.default_max);
                 self.phase += freq * dt;
                 let crossed = self.phase >= 1.0;
                 if crossed {
@@ -1126,6 +1356,7 @@ pub enum LfoWave {
 ///
 /// All parameters (`freq`, `duty`, `min`, `max`) may be driven by signal
 /// inputs at audio or control rate.
+#[derive(Serialize, Deserialize)]
 pub struct UGLfo {
     wave: LfoWave,
     mode: UnitRate,
@@ -1162,6 +1393,19 @@ impl UGen for UGLfo {
         "UGLfo"
     }
 
+    fn to_facade(&self) -> Option<UGFacade> {
+        Some(UGFacade::Lfo {
+            wave: self.wave,
+            rate: self.default_rate,
+            mode: self.mode,
+            duty: self.default_duty,
+            min: self.default_min,
+            max: self.default_max,
+        })
+    }
+
+    impl_serde_state!();
+
     fn input_names(&self) -> &[String] {
         static NAMES: std::sync::OnceLock<Vec<String>> = std::sync::OnceLock::new();
         NAMES.get_or_init(|| {
@@ -1203,48 +1447,26 @@ impl UGen for UGLfo {
 
     fn process(
         &mut self,
-        inputs: &[&[Sample]],
+        inputs: &[Input],
         outputs: &mut [&mut [Sample]],
         sample_rate: f32,
         _time_sample: usize,
     ) {
-        let rate_in = inputs.first().copied().unwrap_or(&[]);
-        let duty_in = inputs.get(1).copied().unwrap_or(&[]);
-        let min_in = inputs.get(2).copied().unwrap_or(&[]);
-        let max_in = inputs.get(3).copied().unwrap_or(&[]);
+        let rate_in = inputs.first().copied().unwrap_or_default();
+        let duty_in = inputs.get(1).copied().unwrap_or_default();
+        let min_in = inputs.get(2).copied().unwrap_or_default();
+        let max_in = inputs.get(3).copied().unwrap_or_default();
 
         let wave_out = &mut outputs[0];
         let n = wave_out.len();
         let dt = 1.0 / sample_rate;
 
-        let rate_connected = rate_in.len() >= n;
-        let duty_connected = duty_in.len() >= n;
-        let min_connected = min_in.len() >= n;
-        let max_connected = max_in.len() >= n;
-
         for i in 0..n {
-            let rate = if rate_connected {
-                rate_in[i]
-            } else {
-                self.default_rate
-            };
+            let rate = rate_in.value_or(i, self.default_rate);
             let freq = unit_rate_to_hz(rate, self.mode, sample_rate);
-            let duty = if duty_connected {
-                duty_in[i]
-            } else {
-                self.default_duty
-            }
-            .clamp(0.0, 1.0);
-            let min = if min_connected {
-                min_in[i]
-            } else {
-                self.default_min
-            };
-            let max = if max_connected {
-                max_in[i]
-            } else {
-                self.default_max
-            };
+            let duty = duty_in.value_or(i, self.default_duty).clamp(0.0, 1.0);
+            let min = min_in.value_or(i, self.default_min);
+            let max = max_in.value_or(i, self.default_max);
 
             self.phase += freq * dt;
             if self.phase >= 1.0 {
@@ -1284,6 +1506,7 @@ impl UGen for UGLfo {
 //------------------------------------------------------------------------------
 
 /// Given a signal-controlled frequency, output an impulse.
+#[derive(Serialize, Deserialize)]
 pub struct UGTrigger {
     phase: f32,
 }
@@ -1304,6 +1527,12 @@ impl UGen for UGTrigger {
     fn type_name(&self) -> &'static str {
         "UGTrigger"
     }
+
+    fn to_facade(&self) -> Option<UGFacade> {
+        Some(UGFacade::Trigger {})
+    }
+
+    impl_serde_state!();
     fn input_names(&self) -> &[String] {
         static NAMES: std::sync::OnceLock<Vec<String>> = std::sync::OnceLock::new();
         NAMES.get_or_init(|| vec!["freq".to_string()])
@@ -1321,7 +1550,7 @@ impl UGen for UGTrigger {
     }
     fn process(
         &mut self,
-        inputs: &[&[Sample]],
+        inputs: &[Input],
         outputs: &mut [&mut [Sample]],
         sample_rate: f32,
         _time_sample: usize,
@@ -1332,7 +1561,7 @@ impl UGen for UGTrigger {
         out[0] = 1.0;
 
         for i in 1..out.len() {
-            let hz = rate[i].max(0.0); // clamp negative rates to 0
+            let hz = rate.value_or(i, 1.0).max(0.0); // clamp negative rates to 0
             let phase_inc = hz / sample_rate;
 
             self.phase += phase_inc;
@@ -1347,6 +1576,7 @@ impl UGen for UGTrigger {
 //------------------------------------------------------------------------------
 
 /// Given a constant rate determined by a `rate` value and a `UnitRate`, output impulses as long as the signal input is positive.
+#[derive(Serialize, Deserialize)]
 pub struct UGClock {
     rate: Sample,
     mode: UnitRate,
@@ -1368,6 +1598,15 @@ impl UGen for UGClock {
         "UGClock"
     }
 
+    fn to_facade(&self) -> Option<UGFacade> {
+        Some(UGFacade::Clock {
+            rate: self.rate,
+            mode: self.mode,
+        })
+    }
+
+    impl_serde_state!();
+
     fn input_names(&self) -> &[String] {
         static NAMES: std::sync::OnceLock<Vec<String>> = std::sync::OnceLock::new();
         NAMES.get_or_init(|| vec!["in".to_string()])
@@ -1391,18 +1630,18 @@ impl UGen for UGClock {
 
     fn process(
         &mut self,
-        inputs: &[&[Sample]],
+        inputs: &[Input],
         outputs: &mut [&mut [Sample]],
         sample_rate: f32,
         _time_sample: usize,
     ) {
-        let enabled = inputs.first().copied().unwrap_or(&[]);
+        let enabled = inputs.first().copied().unwrap_or_default();
         let out = &mut outputs[0];
         let hz = unit_rate_to_hz(self.rate, self.mode, sample_rate);
         let phase_inc = hz / sample_rate;
 
         for i in 0..out.len() {
-            let on = enabled.get(i).copied().unwrap_or(1.0) > 0.5;
+            let on = enabled.get(i).unwrap_or(1.0) > 0.5;
             if !on {
                 out[i] = 0.0;
                 continue;
@@ -1424,6 +1663,7 @@ impl UGen for UGClock {
 /// Sample and hold: latches the value of `in` whenever `trig` makes a
 /// positive-going transition above 0.5, and holds that value on `out` until
 /// the next trigger.  Before the first trigger, `out` is 0.0.
+#[derive(Serialize, Deserialize)]
 pub struct UGSampleHold {
     held: Sample,
     prev_trig: Sample,
@@ -1449,6 +1689,12 @@ impl UGen for UGSampleHold {
         "UGSampleHold"
     }
 
+    fn to_facade(&self) -> Option<UGFacade> {
+        Some(UGFacade::SampleHold {})
+    }
+
+    impl_serde_state!();
+
     fn input_names(&self) -> &[String] {
         static NAMES: std::sync::OnceLock<Vec<String>> = std::sync::OnceLock::new();
         NAMES.get_or_init(|| vec!["in".to_string(), "trig".to_string()])
@@ -1469,20 +1715,20 @@ impl UGen for UGSampleHold {
 
     fn process(
         &mut self,
-        inputs: &[&[Sample]],
+        inputs: &[Input],
         outputs: &mut [&mut [Sample]],
         _sample_rate: f32,
         _time_sample: usize,
     ) {
-        let sig = inputs.first().copied().unwrap_or(&[]);
-        let trig = inputs.get(1).copied().unwrap_or(&[]);
+        let sig = inputs.first().copied().unwrap_or_default();
+        let trig = inputs.get(1).copied().unwrap_or_default();
         let out = &mut outputs[0];
 
         for i in 0..out.len() {
-            let t = trig.get(i).copied().unwrap_or(0.0);
+            let t = trig.get(i).unwrap_or(0.0);
             // Positive-going edge across the 0.5 threshold latches a new value.
             if t > 0.5 && self.prev_trig <= 0.5 {
-                self.held = sig.get(i).copied().unwrap_or(0.0);
+                self.held = sig.get(i).unwrap_or(0.0);
             }
             self.prev_trig = t;
             out[i] = self.held;
@@ -1496,6 +1742,7 @@ mod tests {
     use super::*;
     use crate::GenGraph;
     use crate::connect_many;
+    use crate::graph_from_chain_expression;
     use crate::register_many;
     // use crate::Recorder;
 
@@ -1534,6 +1781,33 @@ mod tests {
         )
     }
 
+    //--------------------------------------------------------------------------
+    #[test]
+    fn test_sum_control_and_audio_a() {
+        let mut sum = UGSum::new(3);
+        let audio: Vec<Sample> = (0..8).map(|i| i as Sample).collect();
+        let inputs = [
+            Input::Control(2.0),
+            Input::Audio(&audio),
+            Input::Control(0.5),
+        ];
+        assert_eq!(sum.output_rate(0, &inputs), Rate::Audio);
+        let mut out = [0.0; 8];
+        sum.process(&inputs, &mut [&mut out], 8.0, 0);
+        assert_eq!(out, [2.5, 3.5, 4.5, 5.5, 6.5, 7.5, 8.5, 9.5]);
+
+        // all control-rate: one value for the block
+        let inputs = [
+            Input::Control(2.0),
+            Input::Control(1.0),
+            Input::Control(0.5),
+        ];
+        assert_eq!(sum.output_rate(0, &inputs), Rate::Control);
+        let mut out = [0.0; 1];
+        sum.process(&inputs, &mut [&mut out], 8.0, 0);
+        assert_eq!(out, [3.5]);
+    }
+
     //--------------------------------------------------------------------------
     #[test]
     fn test_mult_a() {
@@ -1831,6 +2105,18 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_clock_unconnected_a() {
+        // an unconnected freq reads the 1 Hz default
+        let mut g = GenGraph::new(4.0, 8);
+        g.add_node("clock1", Box::new(UGTrigger::new()));
+        g.process();
+        assert_eq!(
+            g.get_output_by_label("clock1.out"),
+            vec![1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0]
+        );
+    }
+
     //--------------------------------------------------------------------------
     #[test]
     fn test_lfo_sine_a() {
@@ -2208,4 +2494,76 @@ mod tests {
             vec![0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]
         );
     }
+
+    //--------------------------------------------------------------------------
+    #[test]
+    fn test_sum_invalid_inputs() {
+        let err = UGSum::try_new(1).err().unwrap();
+        assert_eq!(err, "Input count should be greater than 1, got 1");
+        let err = graph_from_chain_expression("Sum(inputs=1)", 8.0, 8)
+            .err()
+            .unwrap();
+        assert!(
+            err.contains("Input count should be greater than 1, got 1"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_mult_invalid_inputs() {
+        let err = UGMult::try_new(0).err().unwrap();
+        assert_eq!(err, "Input count should be greater than 1, got 0");
+        let err = graph_from_chain_expression("Mult(inputs=0)", 8.0, 8)
+            .err()
+            .unwrap();
+        assert!(
+            err.contains("Input count should be greater than 1, got 0"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_pan_invalid_outputs() {
+        let err = UGPan::try_new(1, 0.5).err().unwrap();
+        assert_eq!(err, "Output count should be greater than 1, got 1");
+        let err = graph_from_chain_expression("Pan(outputs=1)", 8.0, 8)
+            .err()
+            .unwrap();
+        assert!(
+            err.contains("Output count should be greater than 1, got 1"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_mix_linear_invalid_counts() {
+        let err = UGMixLinear::try_new(0, 2).err().unwrap();
+        assert_eq!(err, "Input count should be at least 1, got 0");
+        for (chain, expected) in [
+            (
+                "MixLinear(inputs=0, outputs=2)",
+                "Input count should be at least 1",
+            ),
+            (
+                "MixLinear(inputs=1, outputs=1)",
+                "Output count should be at least 2",
+            ),
+        ] {
+            let err = graph_from_chain_expression(chain, 8.0, 8).err().unwrap();
+            assert!(err.contains(expected), "{err}");
+        }
+    }
+
+    #[test]
+    fn test_fade_invalid_channels() {
+        let err = UGFade::try_new(0, 1.0).err().unwrap();
+        assert_eq!(err, "Channel count should be at least 1, got 0");
+        let err = graph_from_chain_expression("Fade(channels=0)", 8.0, 8)
+            .err()
+            .unwrap();
+        assert!(
+            err.contains("Channel count should be at least 1, got 0"),
+            "{err}"
+        );
+    }
 }
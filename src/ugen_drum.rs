@@ -1,7 +1,11 @@
-use rand::rngs::StdRng;
 use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
 
+use crate::Input;
 use crate::UGen;
+use crate::graph_facade::UGFacade;
+use crate::ugen_core::impl_serde_state;
 use crate::util::Sample;
 
 /// ln(1000) ≈ 6.9078; used so that `exp(-LN_1000 / decay_samples)` gives a
@@ -32,6 +36,7 @@ const LN_1000: f32 = 6.907_755;
 ///
 /// Outputs:
 ///   0 out - Mixed, soft-saturated snare output in approximately [-1..1].
+#[derive(Serialize, Deserialize)]
 pub struct UGSnareDrum {
     // Tonal oscillator internal phase (0..1)
     tone_phase: Sample,
@@ -46,7 +51,7 @@ pub struct UGSnareDrum {
     // Low-pass filter state used to derive the noise high-pass (input - lp = hp)
     noise_lp: Sample,
     // Random number generator for white noise
-    rng: StdRng,
+    rng: ChaCha12Rng,
     // Optional seed stored so describe_config can report it
     seed: Option<u64>,
     // Default parameter values
@@ -81,7 +86,7 @@ impl UGSnareDrum {
             pitch_env: 0.0,
             prev_gate: 0.0,
             noise_lp: 0.0,
-            rng: StdRng::seed_from_u64(actual_seed),
+            rng: ChaCha12Rng::seed_from_u64(actual_seed),
             seed,
             default_tune: 180.0,
             default_tone: 0.7,
@@ -105,6 +110,18 @@ impl UGen for UGSnareDrum {
         "UGSnareDrum"
     }
 
+    fn to_facade(&self) -> Option<UGFacade> {
+        Some(UGFacade::SnareDrum { seed: self.seed })
+    }
+
+    impl_serde_state!();
+
+    fn set_graph_seed(&mut self, seed: u64) {
+        if self.seed.is_none() {
+            self.rng = ChaCha12Rng::seed_from_u64(seed);
+        }
+    }
+
     fn input_names(&self) -> &[String] {
         static NAMES: std::sync::OnceLock<Vec<String>> = std::sync::OnceLock::new();
         NAMES.get_or_init(|| {
@@ -146,19 +163,19 @@ impl UGen for UGSnareDrum {
 
     fn process(
         &mut self,
-        inputs: &[&[Sample]],
+        inputs: &[Input],
         outputs: &mut [&mut [Sample]],
         sample_rate: f32,
         _time_sample: usize,
     ) {
-        let gate = inputs.first().copied().unwrap_or(&[]);
-        let tune = inputs.get(1).copied().unwrap_or(&[]);
-        let tone = inputs.get(2).copied().unwrap_or(&[]);
-        let snappy = inputs.get(3).copied().unwrap_or(&[]);
-        let tone_decay = inputs.get(4).copied().unwrap_or(&[]);
-        let snappy_decay = inputs.get(5).copied().unwrap_or(&[]);
-        let noise_filter = inputs.get(6).copied().unwrap_or(&[]);
-        let pitch_sweep = inputs.get(7).copied().unwrap_or(&[]);
+        let gate = inputs.first().copied().unwrap_or_default();
+        let tune = inputs.get(1).copied().unwrap_or_default();
+        let tone = inputs.get(2).copied().unwrap_or_default();
+        let snappy = inputs.get(3).copied().unwrap_or_default();
+        let tone_decay = inputs.get(4).copied().unwrap_or_default();
+        let snappy_decay = inputs.get(5).copied().unwrap_or_default();
+        let noise_filter = inputs.get(6).copied().unwrap_or_default();
+        let pitch_sweep = inputs.get(7).copied().unwrap_or_default();
 
         let out = &mut outputs[0];
         let n = out.len();
@@ -171,28 +188,24 @@ impl UGen for UGSnareDrum {
 
         for i in 0..n {
             // ── Read signal inputs, falling back to per-parameter defaults ────────
-            let gate_v = gate.get(i).copied().unwrap_or(0.0);
-            let tune_v = tune.get(i).copied().unwrap_or(self.default_tune);
-            let tone_v = tone.get(i).copied().unwrap_or(self.default_tone);
-            let snappy_v = snappy.get(i).copied().unwrap_or(self.default_snappy);
+            let gate_v = gate.get(i).unwrap_or(0.0);
+            let tune_v = tune.get(i).unwrap_or(self.default_tune);
+            let tone_v = tone.get(i).unwrap_or(self.default_tone);
+            let snappy_v = snappy.get(i).unwrap_or(self.default_snappy);
             let tone_decay_v = tone_decay
                 .get(i)
-                .copied()
                 .unwrap_or(self.default_tone_decay)
                 .max(1.0);
             let snappy_decay_v = snappy_decay
                 .get(i)
-                .copied()
                 .unwrap_or(self.default_snappy_decay)
                 .max(1.0);
             let noise_filter_v = noise_filter
                 .get(i)
-                .copied()
                 .unwrap_or(self.default_noise_filter)
                 .clamp(20.0, sample_rate * 0.45);
             let pitch_sweep_v = pitch_sweep
                 .get(i)
-                .copied()
                 .unwrap_or(self.default_pitch_sweep)
                 .max(1.0);
 
@@ -259,6 +272,7 @@ impl UGen for UGSnareDrum {
 ///
 /// Outputs:
 ///   0 out - Bass drum output in approximately [-1..1].
+#[derive(Serialize, Deserialize)]
 pub struct UGBassDrum {
     phase: Sample,
     click_phase: Sample,
@@ -306,6 +320,12 @@ impl UGen for UGBassDrum {
         "UGBassDrum"
     }
 
+    fn to_facade(&self) -> Option<UGFacade> {
+        Some(UGFacade::BassDrum {})
+    }
+
+    impl_serde_state!();
+
     fn input_names(&self) -> &[String] {
         static NAMES: std::sync::OnceLock<Vec<String>> = std::sync::OnceLock::new();
         NAMES.get_or_init(|| {
@@ -343,19 +363,19 @@ impl UGen for UGBassDrum {
 
     fn process(
         &mut self,
-        inputs: &[&[Sample]],
+        inputs: &[Input],
         outputs: &mut [&mut [Sample]],
         sample_rate: f32,
         _time_sample: usize,
     ) {
-        let gate = inputs.first().copied().unwrap_or(&[]);
-        let tune = inputs.get(1).copied().unwrap_or(&[]);
-        let decay = inputs.get(2).copied().unwrap_or(&[]);
-        let punch = inputs.get(3).copied().unwrap_or(&[]);
-        let sweep_decay = inputs.get(4).copied().unwrap_or(&[]);
-        let click = inputs.get(5).copied().unwrap_or(&[]);
-        let tone = inputs.get(6).copied().unwrap_or(&[]);
-        let drive = inputs.get(7).copied().unwrap_or(&[]);
+        let gate = inputs.first().copied().unwrap_or_default();
+        let tune = inputs.get(1).copied().unwrap_or_default();
+        let decay = inputs.get(2).copied().unwrap_or_default();
+        let punch = inputs.get(3).copied().unwrap_or_default();
+        let sweep_decay = inputs.get(4).copied().unwrap_or_default();
+        let click = inputs.get(5).copied().unwrap_or_default();
+        let tone = inputs.get(6).copied().unwrap_or_default();
+        let drive = inputs.get(7).copied().unwrap_or_default();
 
         let out = &mut outputs[0];
         let dt = 1.0 / sample_rate;
@@ -364,32 +384,22 @@ impl UGen for UGBassDrum {
         let click_decay_coeff = (-LN_1000 / (0.0015 * sample_rate).max(1.0)).exp();
 
         for (i, o) in out.iter_mut().enumerate() {
-            let gate_v = gate.get(i).copied().unwrap_or(0.0);
+            let gate_v = gate.get(i).unwrap_or(0.0);
             let tune_v = tune
                 .get(i)
-                .copied()
                 .unwrap_or(self.default_tune)
                 // Keep oscillator well below Nyquist (0.5*sr): 0.45 leaves headroom to
                 // reduce aliasing under modulation/saturation.
                 .clamp(20.0, sample_rate * 0.45);
-            let decay_v = decay.get(i).copied().unwrap_or(self.default_decay).max(1.0);
-            let punch_v = punch.get(i).copied().unwrap_or(self.default_punch).max(1.0);
+            let decay_v = decay.get(i).unwrap_or(self.default_decay).max(1.0);
+            let punch_v = punch.get(i).unwrap_or(self.default_punch).max(1.0);
             let sweep_decay_v = sweep_decay
                 .get(i)
-                .copied()
                 .unwrap_or(self.default_sweep_decay)
                 .max(1.0);
-            let click_v = click
-                .get(i)
-                .copied()
-                .unwrap_or(self.default_click)
-                .clamp(0.0, 1.0);
-            let tone_v = tone
-                .get(i)
-                .copied()
-                .unwrap_or(self.default_tone)
-                .clamp(0.0, 1.0);
-            let drive_v = drive.get(i).copied().unwrap_or(self.default_drive).max(0.0);
+            let click_v = click.get(i).unwrap_or(self.default_click).clamp(0.0, 1.0);
+            let tone_v = tone.get(i).unwrap_or(self.default_tone).clamp(0.0, 1.0);
+            let drive_v = drive.get(i).unwrap_or(self.default_drive).max(0.0);
 
             if gate_v > 0.5 && self.prev_gate <= 0.5 {
                 self.amp_env = 1.0;
@@ -461,6 +471,7 @@ const HAT_OSC_RATIOS: [f32; 6] = [1.0, 1.114, 1.330, 1.401, 1.568, 2.182];
 ///
 /// Outputs:
 ///   0 out - Hi-hat output in approximately [-1..1].
+#[derive(Serialize, Deserialize)]
 pub struct UGHighHat {
     // 6 inharmonic square-wave oscillators (phases in 0..1)
     osc_phases: [Sample; 6],
@@ -472,7 +483,7 @@ pub struct UGHighHat {
     // Previous gate value for rising-edge detection
     prev_gate: Sample,
     // Random number generator for the white-noise blend
-    rng: StdRng,
+    rng: ChaCha12Rng,
     // Optional seed stored so describe_config can report it
     seed: Option<u64>,
     // Default parameter values
@@ -495,7 +506,7 @@ impl UGHighHat {
             bp_low: 0.0,
             bp_band: 0.0,
             prev_gate: 0.0,
-            rng: StdRng::seed_from_u64(actual_seed),
+            rng: ChaCha12Rng::seed_from_u64(actual_seed),
             seed,
             default_tune: 3969.0,
             default_decay: 4000.0,
@@ -518,6 +529,18 @@ impl UGen for UGHighHat {
         "UGHighHat"
     }
 
+    fn to_facade(&self) -> Option<UGFacade> {
+        Some(UGFacade::HighHat { seed: self.seed })
+    }
+
+    impl_serde_state!();
+
+    fn set_graph_seed(&mut self, seed: u64) {
+        if self.seed.is_none() {
+            self.rng = ChaCha12Rng::seed_from_u64(seed);
+        }
+    }
+
     fn input_names(&self) -> &[String] {
         static NAMES: std::sync::OnceLock<Vec<String>> = std::sync::OnceLock::new();
         NAMES.get_or_init(|| {
@@ -557,18 +580,18 @@ impl UGen for UGHighHat {
 
     fn process(
         &mut self,
-        inputs: &[&[Sample]],
+        inputs: &[Input],
         outputs: &mut [&mut [Sample]],
         sample_rate: f32,
         _time_sample: usize,
     ) {
-        let gate = inputs.first().copied().unwrap_or(&[]);
-        let tune = inputs.get(1).copied().unwrap_or(&[]);
-        let decay = inputs.get(2).copied().unwrap_or(&[]);
-        let tone = inputs.get(3).copied().unwrap_or(&[]);
-        let accent = inputs.get(4).copied().unwrap_or(&[]);
-        let noise = inputs.get(5).copied().unwrap_or(&[]);
-        let drive = inputs.get(6).copied().unwrap_or(&[]);
+        let gate = inputs.first().copied().unwrap_or_default();
+        let tune = inputs.get(1).copied().unwrap_or_default();
+        let decay = inputs.get(2).copied().unwrap_or_default();
+        let tone = inputs.get(3).copied().unwrap_or_default();
+        let accent = inputs.get(4).copied().unwrap_or_default();
+        let noise = inputs.get(5).copied().unwrap_or_default();
+        let drive = inputs.get(6).copied().unwrap_or_default();
 
         let out = &mut outputs[0];
         let dt = 1.0 / sample_rate;
@@ -580,29 +603,19 @@ impl UGen for UGHighHat {
         let bp_r = 1.0 / (2.0 * bp_q);
 
         for (i, o) in out.iter_mut().enumerate() {
-            let gate_v = gate.get(i).copied().unwrap_or(0.0);
+            let gate_v = gate.get(i).unwrap_or(0.0);
             let tune_v = tune
                 .get(i)
-                .copied()
                 .unwrap_or(self.default_tune)
                 .clamp(20.0, (sample_rate * 0.45 / HAT_OSC_RATIOS[5]).max(20.0));
-            let decay_v = decay.get(i).copied().unwrap_or(self.default_decay).max(1.0);
+            let decay_v = decay.get(i).unwrap_or(self.default_decay).max(1.0);
             let tone_v = tone
                 .get(i)
-                .copied()
                 .unwrap_or(self.default_tone)
                 .clamp(20.0, sample_rate * 0.45);
-            let accent_v = accent
-                .get(i)
-                .copied()
-                .unwrap_or(self.default_accent)
-                .clamp(0.0, 1.0);
-            let noise_v = noise
-                .get(i)
-                .copied()
-                .unwrap_or(self.default_noise)
-                .clamp(0.0, 1.0);
-            let drive_v = drive.get(i).copied().unwrap_or(self.default_drive).max(0.0);
+            let accent_v = accent.get(i).unwrap_or(self.default_accent).clamp(0.0, 1.0);
+            let noise_v = noise.get(i).unwrap_or(self.default_noise).clamp(0.0, 1.0);
+            let drive_v = drive.get(i).unwrap_or(self.default_drive).max(0.0);
 
             // ── Rising-edge detection on gate ─────────────────────────────────────
             if gate_v > 0.5 && self.prev_gate <= 0.5 {
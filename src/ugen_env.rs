@@ -1,12 +1,16 @@
+use crate::Input;
 use crate::ModeSelect;
 use crate::UGSelect;
 use crate::UGen;
+use crate::graph_facade::UGFacade;
+use crate::ugen_core::impl_serde_state;
 use crate::util::Sample;
+use serde::{Deserialize, Serialize};
 
 //------------------------------------------------------------------------------
 // UGEnvBreakPoint
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct UGEnvBreakPoint {
     current: Sample,
     pulse_counter: usize,
@@ -38,6 +42,23 @@ impl UGen for UGEnvBreakPoint {
         "UGEnvBreakPoint"
     }
 
+    fn to_facade(&self) -> Option<UGFacade> {
+        Some(UGFacade::EnvBreakPoint {
+            duration_values: self.duration_select.values().to_vec(),
+            duration_mode: self.duration_select.mode(),
+            level_values: self.level_select.values().to_vec(),
+            level_mode: self.level_select.mode(),
+            seed: self.duration_select.seed(),
+        })
+    }
+
+    impl_serde_state!();
+
+    fn set_graph_seed(&mut self, seed: u64) {
+        self.duration_select.set_graph_seed(seed);
+        self.level_select.set_graph_seed(seed);
+    }
+
     fn input_names(&self) -> &[String] {
         static NAMES: std::sync::OnceLock<Vec<String>> = std::sync::OnceLock::new();
         NAMES.get_or_init(|| vec!["clock".to_string(), "step".to_string()])
@@ -62,20 +83,20 @@ impl UGen for UGEnvBreakPoint {
 
     fn process(
         &mut self,
-        inputs: &[&[Sample]],
+        inputs: &[Input],
         outputs: &mut [&mut [Sample]],
         sample_rate: f32,
         time_sample: usize,
     ) {
-        let clock = inputs.first().copied().unwrap_or(&[]);
-        let step = inputs.get(1).copied().unwrap_or(&[]);
+        let clock = inputs.first().copied().unwrap_or_default();
+        let step = inputs.get(1).copied().unwrap_or_default();
         let out = &mut outputs[0];
 
         for i in 0..out.len() {
-            let triggered = clock.get(i).copied().unwrap_or(0.0) > 0.5;
+            let triggered = clock.get(i).unwrap_or(0.0) > 0.5;
 
             if triggered {
-                let step_size = step.get(i).copied().unwrap_or(1.0).max(1.0).round();
+                let step_size = step.get(i).unwrap_or(1.0).max(1.0).round();
 
                 self.pulse_counter += 1;
 
@@ -103,7 +124,7 @@ impl UGen for UGEnvBreakPoint {
 
 //------------------------------------------------------------------------------
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 enum EnvPhase {
     Idle,
     Attack,
@@ -111,7 +132,7 @@ enum EnvPhase {
 }
 
 /// An attack-release envelope, with signal controllable attack and release times, as well as attack and release curves.
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct UGEnvAR {
     current: Sample,
     phase: EnvPhase,
@@ -147,6 +168,12 @@ impl UGen for UGEnvAR {
         "UGEnvAR"
     }
 
+    fn to_facade(&self) -> Option<UGFacade> {
+        Some(UGFacade::EnvAR {})
+    }
+
+    impl_serde_state!();
+
     fn input_names(&self) -> &[String] {
         static NAMES: std::sync::OnceLock<Vec<String>> = std::sync::OnceLock::new();
         NAMES.get_or_init(|| {
@@ -176,29 +203,28 @@ impl UGen for UGEnvAR {
 
     fn process(
         &mut self,
-        inputs: &[&[Sample]],
+        inputs: &[Input],
         outputs: &mut [&mut [Sample]],
         _sample_rate: f32,
         _time_sample: usize,
     ) {
-        let trigger = inputs.first().copied().unwrap_or(&[]);
-        let att_dur = inputs.get(1).copied().unwrap_or(&[]);
-        let rel_dur = inputs.get(2).copied().unwrap_or(&[]);
-        let att_curve = inputs.get(3).copied().unwrap_or(&[]);
-        let rel_curve = inputs.get(4).copied().unwrap_or(&[]);
+        let trigger = inputs.first().copied().unwrap_or_default();
+        let att_dur = inputs.get(1).copied().unwrap_or_default();
+        let rel_dur = inputs.get(2).copied().unwrap_or_default();
+        let att_curve = inputs.get(3).copied().unwrap_or_default();
+        let rel_curve = inputs.get(4).copied().unwrap_or_default();
         let out = &mut outputs[0];
 
         for i in 0..out.len() {
-            let trigger_now = trigger.get(i).copied().unwrap_or(0.0) > 0.5;
+            let trigger_now = trigger.get(i).unwrap_or(0.0) > 0.5;
 
             if trigger_now {
                 self.phase = EnvPhase::Attack;
                 self.start = self.current;
                 self.target = 1.0;
-                self.stage_total =
-                    att_dur.get(i).copied().unwrap_or(1.0).max(1.0).round() as i32;
+                self.stage_total = att_dur.get(i).unwrap_or(1.0).max(1.0).round() as i32;
                 self.stage_remain = self.stage_total;
-                self.curve = att_curve.get(i).copied().unwrap_or(1.0).max(0.001);
+                self.curve = att_curve.get(i).unwrap_or(1.0).max(0.001);
             }
 
             if self.stage_remain >= 0 {
@@ -223,11 +249,9 @@ impl UGen for UGEnvAR {
                             self.start = self.current;
                             self.target = 0.0;
                             self.stage_total =
-                                rel_dur.get(i).copied().unwrap_or(1.0).max(1.0).round()
-                                    as i32;
+                                rel_dur.get(i).unwrap_or(1.0).max(1.0).round() as i32;
                             self.stage_remain = self.stage_total;
-                            self.curve =
-                                rel_curve.get(i).copied().unwrap_or(1.0).max(0.001);
+                            self.curve = rel_curve.get(i).unwrap_or(1.0).max(0.001);
                         }
                         EnvPhase::Release => {
                             self.phase = EnvPhase::Idle;
@@ -1,10 +1,18 @@
+use crate::Input;
 use crate::Sample;
 use crate::UGen;
+use crate::graph_facade::UGFacade;
+use crate::ugen_core::impl_serde_state;
+use serde::{Deserialize, Serialize};
 
 fn db_per_octave_to_poles(db: f32) -> usize {
     ((db / 6.0).round()).clamp(1.0, 12.0) as usize
 }
 
+fn poles_to_db_per_octave(poles: usize) -> f32 {
+    poles as f32 * 6.0
+}
+
 /// Inner low-pass sample computation: state-variable cascade with resonance feedback.
 /// Updates `state` and `z1` in place and returns the filtered sample.
 #[inline]
@@ -44,6 +52,7 @@ fn high_pass_sample(
 }
 
 /// A low pass filter with variable cutoff frequency. Rolloff configurable at initialization.
+#[derive(Serialize, Deserialize)]
 pub struct UGLowPass {
     poles: usize,
     state: Vec<Sample>,
@@ -64,6 +73,14 @@ impl UGen for UGLowPass {
         "UGLowPass"
     }
 
+    fn to_facade(&self) -> Option<UGFacade> {
+        Some(UGFacade::LowPass {
+            roll_off_db: poles_to_db_per_octave(self.poles),
+        })
+    }
+
+    impl_serde_state!();
+
     fn input_names(&self) -> &[String] {
         static NAMES: std::sync::OnceLock<Vec<String>> = std::sync::OnceLock::new();
         NAMES.get_or_init(|| vec!["in".to_string(), "cutoff".to_string()])
@@ -74,22 +91,28 @@ impl UGen for UGLowPass {
         NAMES.get_or_init(|| vec!["out".to_string()])
     }
 
+    fn default_input(&self, input_name: &str) -> Option<Sample> {
+        match input_name {
+            "cutoff" => Some(1000.0),
+            _ => None,
+        }
+    }
+
     fn process(
         &mut self,
-        inputs: &[&[Sample]],
+        inputs: &[Input],
         outputs: &mut [&mut [Sample]],
         sample_rate: f32,
         _time_sample: usize,
     ) {
         let input = inputs[0];
-        let cutoff = inputs.get(1).copied().unwrap_or(&[]);
+        let cutoff = inputs.get(1).copied().unwrap_or_default();
         let out = &mut outputs[0];
 
         for i in 0..out.len() {
-            let x = input[i];
+            let x = input.value_or(i, 0.0);
             let fc = cutoff
                 .get(i)
-                .copied()
                 .unwrap_or(1000.0)
                 .clamp(1.0, sample_rate / 2.0);
             let g = (2.0 * std::f32::consts::PI * fc / sample_rate).clamp(0.0, 1.0);
@@ -107,6 +130,7 @@ impl UGen for UGLowPass {
 }
 
 /// A low pass filter with variable cutoff and resonance. Roll-off configuraable at initialization.
+#[derive(Serialize, Deserialize)]
 pub struct UGLowPassQ {
     state: Vec<Sample>,
     z1: Sample,
@@ -127,6 +151,14 @@ impl UGen for UGLowPassQ {
         "UGLowPassQ"
     }
 
+    fn to_facade(&self) -> Option<UGFacade> {
+        Some(UGFacade::LowPassQ {
+            roll_off_db: poles_to_db_per_octave(self.state.len()),
+        })
+    }
+
+    impl_serde_state!();
+
     fn input_names(&self) -> &[String] {
         static NAMES: std::sync::OnceLock<Vec<String>> = std::sync::OnceLock::new();
         NAMES.get_or_init(|| {
@@ -143,26 +175,33 @@ impl UGen for UGLowPassQ {
         NAMES.get_or_init(|| vec!["out".to_string()])
     }
 
+    fn default_input(&self, input_name: &str) -> Option<Sample> {
+        match input_name {
+            "cutoff" => Some(1000.0),
+            "resonance" => Some(0.0),
+            _ => None,
+        }
+    }
+
     fn process(
         &mut self,
-        inputs: &[&[Sample]],
+        inputs: &[Input],
         outputs: &mut [&mut [Sample]],
         sample_rate: f32,
         _time_sample: usize,
     ) {
         let input = inputs[0];
-        let cutoff = inputs.get(1).copied().unwrap_or(&[]);
-        let resonance = inputs.get(2).copied().unwrap_or(&[]);
+        let cutoff = inputs.get(1).copied().unwrap_or_default();
+        let resonance = inputs.get(2).copied().unwrap_or_default();
         let out = &mut outputs[0];
 
         for i in 0..out.len() {
-            let x = input[i];
+            let x = input.value_or(i, 0.0);
             let fc = cutoff
                 .get(i)
-                .copied()
                 .unwrap_or(1000.0)
                 .clamp(1.0, sample_rate / 2.0);
-            let res = resonance.get(i).copied().unwrap_or(0.0).clamp(0.0, 1.0);
+            let res = resonance.get(i).unwrap_or(0.0).clamp(0.0, 1.0);
 
             let g = (2.0 * std::f32::consts::PI * fc / sample_rate).clamp(0.0, 1.0);
             out[i] = low_pass_sample(x, g, res, &mut self.state, &mut self.z1);
@@ -171,6 +210,7 @@ impl UGen for UGLowPassQ {
 }
 
 /// A high pass filter with variable cutoff frequency. Rolloff configurable at initialization.
+#[derive(Serialize, Deserialize)]
 pub struct UGHighPass {
     poles: usize,
     state: Vec<Sample>,
@@ -191,6 +231,14 @@ impl UGen for UGHighPass {
         "UGHighPass"
     }
 
+    fn to_facade(&self) -> Option<UGFacade> {
+        Some(UGFacade::HighPass {
+            roll_off_db: poles_to_db_per_octave(self.poles),
+        })
+    }
+
+    impl_serde_state!();
+
     fn input_names(&self) -> &[String] {
         static NAMES: std::sync::OnceLock<Vec<String>> = std::sync::OnceLock::new();
         NAMES.get_or_init(|| vec!["in".to_string(), "cutoff".to_string()])
@@ -201,22 +249,28 @@ impl UGen for UGHighPass {
         NAMES.get_or_init(|| vec!["out".to_string()])
     }
 
+    fn default_input(&self, input_name: &str) -> Option<Sample> {
+        match input_name {
+            "cutoff" => Some(1000.0),
+            _ => None,
+        }
+    }
+
     fn process(
         &mut self,
-        inputs: &[&[Sample]],
+        inputs: &[Input],
         outputs: &mut [&mut [Sample]],
         sample_rate: f32,
         _time_sample: usize,
     ) {
         let input = inputs[0];
-        let cutoff = inputs.get(1).copied().unwrap_or(&[]);
+        let cutoff = inputs.get(1).copied().unwrap_or_default();
         let out = &mut outputs[0];
 
         for i in 0..out.len() {
-            let x = input[i];
+            let x = input.value_or(i, 0.0);
             let fc = cutoff
                 .get(i)
-                .copied()
                 .unwrap_or(1000.0)
                 .clamp(1.0, sample_rate / 2.0);
             let g = (2.0 * std::f32::consts::PI * fc / sample_rate).clamp(0.0, 1.0);
@@ -233,6 +287,7 @@ impl UGen for UGHighPass {
 }
 
 /// A high pass filter with variable cutoff and resonance. Roll-off configurable at initialization.
+#[derive(Serialize, Deserialize)]
 pub struct UGHighPassQ {
     state: Vec<Sample>,
     z1: Sample,
@@ -253,6 +308,14 @@ impl UGen for UGHighPassQ {
         "UGHighPassQ"
     }
 
+    fn to_facade(&self) -> Option<UGFacade> {
+        Some(UGFacade::HighPassQ {
+            roll_off_db: poles_to_db_per_octave(self.state.len()),
+        })
+    }
+
+    impl_serde_state!();
+
     fn input_names(&self) -> &[String] {
         static NAMES: std::sync::OnceLock<Vec<String>> = std::sync::OnceLock::new();
         NAMES.get_or_init(|| {
@@ -269,26 +332,33 @@ impl UGen for UGHighPassQ {
         NAMES.get_or_init(|| vec!["out".to_string()])
     }
 
+    fn default_input(&self, input_name: &str) -> Option<Sample> {
+        match input_name {
+            "cutoff" => Some(1000.0),
+            "resonance" => Some(0.0),
+            _ => None,
+        }
+    }
+
     fn process(
         &mut self,
-        inputs: &[&[Sample]],
+        inputs: &[Input],
         outputs: &mut [&mut [Sample]],
         sample_rate: f32,
         _time_sample: usize,
     ) {
         let input = inputs[0];
-        let cutoff = inputs.get(1).copied().unwrap_or(&[]);
-        let resonance = inputs.get(2).copied().unwrap_or(&[]);
+        let cutoff = inputs.get(1).copied().unwrap_or_default();
+        let resonance = inputs.get(2).copied().unwrap_or_default();
         let out = &mut outputs[0];
 
         for i in 0..out.len() {
-            let x = input[i];
+            let x = input.value_or(i, 0.0);
             let fc = cutoff
                 .get(i)
-                .copied()
                 .unwrap_or(1000.0)
                 .clamp(1.0, sample_rate / 2.0);
-            let res = resonance.get(i).copied().unwrap_or(0.0).clamp(0.0, 1.0);
+            let res = resonance.get(i).unwrap_or(0.0).clamp(0.0, 1.0);
 
             let g = (2.0 * std::f32::consts::PI * fc / sample_rate).clamp(0.0, 1.0);
             out[i] = high_pass_sample(x, g, res, &mut self.state, &mut self.z1);
@@ -301,6 +371,7 @@ impl UGen for UGHighPassQ {
 /// More efficient than `UGLowPassQ` when parameters do not vary at runtime.
 ///
 /// Inputs: `in1` … `inN`. Outputs: `out1` … `outN`.
+#[derive(Serialize, Deserialize)]
 pub struct UGLowPassConst {
     cutoff: f32,
     resonance: f32,
@@ -311,21 +382,35 @@ pub struct UGLowPassConst {
 }
 
 impl UGLowPassConst {
+    /// Panics if `channels` is less than 1; see `try_new`.
     pub fn new(roll_off_db: f32, cutoff: f32, resonance: f32, channels: usize) -> Self {
-        assert!(channels >= 1, "channels must be at least 1");
+        Self::try_new(roll_off_db, cutoff, resonance, channels)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Like `new`, but returns an error instead of panicking.
+    pub fn try_new(
+        roll_off_db: f32,
+        cutoff: f32,
+        resonance: f32,
+        channels: usize,
+    ) -> Result<Self, String> {
+        if channels < 1 {
+            return Err(format!("channels must be at least 1, got {channels}"));
+        }
         let poles = db_per_octave_to_poles(roll_off_db);
         let channel_state = (0..channels)
             .map(|_| (vec![0.0f32; poles], 0.0f32))
             .collect();
         let input_refs = (1..=channels).map(|i| format!("in{i}")).collect();
         let output_refs = (1..=channels).map(|i| format!("out{i}")).collect();
-        Self {
+        Ok(Self {
             cutoff: cutoff.clamp(1.0, f32::MAX),
             resonance: resonance.clamp(0.0, 1.0),
             channel_state,
             input_refs,
             output_refs,
-        }
+        })
     }
 }
 
@@ -334,6 +419,17 @@ impl UGen for UGLowPassConst {
         "UGLowPassConst"
     }
 
+    fn to_facade(&self) -> Option<UGFacade> {
+        Some(UGFacade::LowPassConst {
+            roll_off_db: poles_to_db_per_octave(self.channel_state[0].0.len()),
+            cutoff: self.cutoff,
+            resonance: self.resonance,
+            channels: self.channel_state.len(),
+        })
+    }
+
+    impl_serde_state!();
+
     fn input_names(&self) -> &[String] {
         &self.input_refs
     }
@@ -344,7 +440,7 @@ impl UGen for UGLowPassConst {
 
     fn process(
         &mut self,
-        inputs: &[&[Sample]],
+        inputs: &[Input],
         outputs: &mut [&mut [Sample]],
         sample_rate: f32,
         _time_sample: usize,
@@ -362,9 +458,9 @@ impl UGen for UGLowPassConst {
             .zip(self.channel_state.iter_mut())
             .enumerate()
         {
-            let input = inputs.get(ch).copied().unwrap_or(&[]);
+            let input = inputs.get(ch).copied().unwrap_or_default();
             for i in 0..n {
-                let x = input.get(i).copied().unwrap_or(0.0);
+                let x = input.get(i).unwrap_or(0.0);
                 out[i] = low_pass_sample(x, g, res, state, z1);
             }
         }
@@ -376,6 +472,7 @@ impl UGen for UGLowPassConst {
 /// More efficient than `UGHighPassQ` when parameters do not vary at runtime.
 ///
 /// Inputs: `in1` … `inN`. Outputs: `out1` … `outN`.
+#[derive(Serialize, Deserialize)]
 pub struct UGHighPassConst {
     cutoff: f32,
     resonance: f32,
@@ -386,21 +483,35 @@ pub struct UGHighPassConst {
 }
 
 impl UGHighPassConst {
+    /// Panics if `channels` is less than 1; see `try_new`.
     pub fn new(roll_off_db: f32, cutoff: f32, resonance: f32, channels: usize) -> Self {
-        assert!(channels >= 1, "channels must be at least 1");
+        Self::try_new(roll_off_db, cutoff, resonance, channels)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Like `new`, but returns an error instead of panicking.
+    pub fn try_new(
+        roll_off_db: f32,
+        cutoff: f32,
+        resonance: f32,
+        channels: usize,
+    ) -> Result<Self, String> {
+        if channels < 1 {
+            return Err(format!("channels must be at least 1, got {channels}"));
+        }
         let poles = db_per_octave_to_poles(roll_off_db);
         let channel_state = (0..channels)
             .map(|_| (vec![0.0f32; poles], 0.0f32))
             .collect();
         let input_refs = (1..=channels).map(|i| format!("in{i}")).collect();
         let output_refs = (1..=channels).map(|i| format!("out{i}")).collect();
-        Self {
+        Ok(Self {
             cutoff: cutoff.clamp(1.0, f32::MAX),
             resonance: resonance.clamp(0.0, 1.0),
             channel_state,
             input_refs,
             output_refs,
-        }
+        })
     }
 }
 
@@ -409,6 +520,17 @@ impl UGen for UGHighPassConst {
         "UGHighPassConst"
     }
 
+    fn to_facade(&self) -> Option<UGFacade> {
+        Some(UGFacade::HighPassConst {
+            roll_off_db: poles_to_db_per_octave(self.channel_state[0].0.len()),
+            cutoff: self.cutoff,
+            resonance: self.resonance,
+            channels: self.channel_state.len(),
+        })
+    }
+
+    impl_serde_state!();
+
     fn input_names(&self) -> &[String] {
         &self.input_refs
     }
@@ -419,7 +541,7 @@ impl UGen for UGHighPassConst {
 
     fn process(
         &mut self,
-        inputs: &[&[Sample]],
+        inputs: &[Input],
         outputs: &mut [&mut [Sample]],
         sample_rate: f32,
         _time_sample: usize,
@@ -437,9 +559,9 @@ impl UGen for UGHighPassConst {
             .zip(self.channel_state.iter_mut())
             .enumerate()
         {
-            let input = inputs.get(ch).copied().unwrap_or(&[]);
+            let input = inputs.get(ch).copied().unwrap_or_default();
             for i in 0..n {
-                let x = input.get(i).copied().unwrap_or(0.0);
+                let x = input.get(i).unwrap_or(0.0);
                 out[i] = high_pass_sample(x, g, res, state, z1);
             }
         }
@@ -473,6 +595,7 @@ fn peaking_eq_coeffs(
 /// A fully sweepable parametric equalizer with variable gain, bandwidth, and center frequency.
 /// Uses a biquad peaking EQ filter (Audio EQ Cookbook). No initialization arguments;
 /// all parameters are controlled via signal inputs.
+#[derive(Serialize, Deserialize)]
 pub struct UGParametric {
     x1: Sample,
     x2: Sample,
@@ -502,6 +625,12 @@ impl UGen for UGParametric {
         "UGParametric"
     }
 
+    fn to_facade(&self) -> Option<UGFacade> {
+        Some(UGFacade::Parametric {})
+    }
+
+    impl_serde_state!();
+
     fn input_names(&self) -> &[String] {
         static NAMES: std::sync::OnceLock<Vec<String>> = std::sync::OnceLock::new();
         NAMES.get_or_init(|| {
@@ -519,26 +648,34 @@ impl UGen for UGParametric {
         NAMES.get_or_init(|| vec!["out".to_string()])
     }
 
+    fn default_input(&self, input_name: &str) -> Option<Sample> {
+        match input_name {
+            "gain" => Some(0.0),
+            "bw" => Some(1.0 / 3.0),
+            "freq" => Some(1000.0),
+            _ => None,
+        }
+    }
+
     fn process(
         &mut self,
-        inputs: &[&[Sample]],
+        inputs: &[Input],
         outputs: &mut [&mut [Sample]],
         sample_rate: f32,
         _time_sample: usize,
     ) {
         let input = inputs[0];
-        let gain = inputs.get(1).copied().unwrap_or(&[]);
-        let bandwidth = inputs.get(2).copied().unwrap_or(&[]);
-        let freq = inputs.get(3).copied().unwrap_or(&[]);
+        let gain = inputs.get(1).copied().unwrap_or_default();
+        let bandwidth = inputs.get(2).copied().unwrap_or_default();
+        let freq = inputs.get(3).copied().unwrap_or_default();
         let out = &mut outputs[0];
 
         for i in 0..out.len() {
-            let x = input[i];
-            let db_gain = gain.get(i).copied().unwrap_or(0.0);
-            let bw = bandwidth.get(i).copied().unwrap_or(1.0 / 3.0).max(0.001);
+            let x = input.value_or(i, 0.0);
+            let db_gain = gain.get(i).unwrap_or(0.0);
+            let bw = bandwidth.get(i).unwrap_or(1.0 / 3.0).max(0.001);
             let fc = freq
                 .get(i)
-                .copied()
                 .unwrap_or(1000.0)
                 .clamp(1.0, sample_rate * 0.5 - 1.0);
 
@@ -559,6 +696,7 @@ impl UGen for UGParametric {
 /// A parametric equalizer with gain, bandwidth, and center frequency fixed at initialization.
 /// Uses the same biquad peaking EQ filter as `UGParametric`. Only the audio signal is a
 /// signal input; the EQ parameters are constant across the lifetime of the node.
+#[derive(Serialize, Deserialize)]
 pub struct UGParametricConst {
     db_gain: f32,
     bw: f32,
@@ -588,6 +726,16 @@ impl UGen for UGParametricConst {
         "UGParametricConst"
     }
 
+    fn to_facade(&self) -> Option<UGFacade> {
+        Some(UGFacade::ParametricConst {
+            gain: self.db_gain,
+            bw: self.bw,
+            freq: self.freq,
+        })
+    }
+
+    impl_serde_state!();
+
     fn input_names(&self) -> &[String] {
         static NAMES: std::sync::OnceLock<Vec<String>> = std::sync::OnceLock::new();
         NAMES.get_or_init(|| vec!["in".to_string()])
@@ -600,7 +748,7 @@ impl UGen for UGParametricConst {
 
     fn process(
         &mut self,
-        inputs: &[&[Sample]],
+        inputs: &[Input],
         outputs: &mut [&mut [Sample]],
         sample_rate: f32,
         _time_sample: usize,
@@ -613,7 +761,7 @@ impl UGen for UGParametricConst {
             peaking_eq_coeffs(self.db_gain, self.bw, fc, sample_rate);
 
         for i in 0..out.len() {
-            let x = input[i];
+            let x = input.value_or(i, 0.0);
             let y = b0 * x + b1 * self.x1 + b2 * self.x2 - a1 * self.y1 - a2 * self.y2;
             self.x2 = self.x1;
             self.x1 = x;
@@ -635,6 +783,7 @@ mod tests {
     use crate::UGSine;
     use crate::UnitRate;
     use crate::connect_many;
+    use crate::graph_from_chain_expression;
     use crate::register_many;
     // use crate::plot_graph_to_image;
 
@@ -1021,4 +1170,23 @@ mod tests {
         assert!(out1.iter().any(|&v| v != 0.0));
         assert!(out2.iter().any(|&v| v != 0.0));
     }
+
+    //--------------------------------------------------------------------------
+    #[test]
+    fn test_low_pass_const_invalid_channels() {
+        let err = UGLowPassConst::try_new(12.0, 60.0, 0.5, 0).err().unwrap();
+        assert_eq!(err, "channels must be at least 1, got 0");
+        let chain = "LowPassConst(roll_off_db=12, cutoff=60, resonance=0.5, channels=0)";
+        let err = graph_from_chain_expression(chain, 8.0, 8).err().unwrap();
+        assert!(err.contains("channels must be at least 1, got 0"), "{err}");
+    }
+
+    #[test]
+    fn test_high_pass_const_invalid_channels() {
+        let err = UGHighPassConst::try_new(12.0, 60.0, 0.5, 0).err().unwrap();
+        assert_eq!(err, "channels must be at least 1, got 0");
+        let chain = "HighPassConst(roll_off_db=12, cutoff=60, resonance=0.5, channels=0)";
+        let err = graph_from_chain_expression(chain, 8.0, 8).err().unwrap();
+        assert!(err.contains("channels must be at least 1, got 0"), "{err}");
+    }
 }
@@ -0,0 +1,634 @@
+use serde::{Deserialize, Serialize};
+use wide::{CmpEq, CmpGt, CmpLt, CmpNe, f32x8};
+
+use crate::graph_facade::UGFacade;
+use crate::ugen_core::{Input, Rate, UGen, rate_of, simd_fill, simd_input};
+use crate::util::Sample;
+
+/// Like `simd_input`, but an unconnected input reads `default`.
+#[inline(always)]
+fn simd_input_or(input: &Input, offset: usize, default: Sample) -> f32x8 {
+    match input {
+        Input::Unconnected => f32x8::splat(default),
+        input => simd_input(input, offset),
+    }
+}
+
+/// 1 where `mask` is set, else 0.
+#[inline(always)]
+fn simd_bool(mask: f32x8) -> f32x8 {
+    mask & f32x8::splat(1.0)
+}
+
+/// A gate is high above 0.5.
+#[inline(always)]
+fn simd_gate(x: f32x8) -> f32x8 {
+    x.simd_gt(f32x8::splat(0.5))
+}
+
+/// `x`, or 0 where `x` is infinite or NaN.
+#[inline(always)]
+fn simd_finite(x: f32x8) -> f32x8 {
+    x.is_finite() & x
+}
+
+/// `a` raised to the power `b`. A negative base has a real power only for an integer
+/// exponent, negative where the exponent is odd; elsewhere the result is NaN.
+#[inline(always)]
+fn simd_pow(a: f32x8, b: f32x8) -> f32x8 {
+    let magnitude = a.abs().pow_f32x8(b);
+    let half = b * f32x8::splat(0.5);
+    let integer = b.floor().simd_eq(b);
+    let odd = integer & half.floor().simd_ne(half);
+    let signed = integer.blend(odd.blend(-magnitude, magnitude), f32x8::splat(f32::NAN));
+    a.simd_lt(f32x8::splat(0.0)).blend(signed, magnitude)
+}
+
+/// Define a stateless UGen with one output, `out`, whose samples are a function
+/// of the input samples at the same position, computed eight at a time: the
+/// closure takes the named inputs as `f32x8` lanes, in order.
+macro_rules! elementwise_ugen {
+    (
+        $(#[$meta:meta])*
+        $name:ident, $facade:ident, [$($input:literal),+],
+        |[$($arg:ident),+]| $body:expr
+    ) => {
+        $(#[$meta])*
+        pub struct $name;
+
+        impl $name {
+            pub fn new() -> Self {
+                Self
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl UGen for $name {
+            fn type_name(&self) -> &'static str {
+                stringify!($name)
+            }
+
+            fn to_facade(&self) -> Option<UGFacade> {
+                Some(UGFacade::$facade {})
+            }
+
+            fn input_names(&self) -> &[String] {
+                static NAMES: std::sync::OnceLock<Vec<String>> = std::sync::OnceLock::new();
+                NAMES.get_or_init(|| vec![$($input.to_string()),+])
+            }
+
+            fn output_names(&self) -> &[String] {
+                static NAMES: std::sync::OnceLock<Vec<String>> = std::sync::OnceLock::new();
+                NAMES.get_or_init(|| vec!["out".to_string()])
+            }
+
+            fn is_pure(&self) -> bool {
+                true
+            }
+
+            fn output_rate(&self, _output: usize, inputs: &[Input]) -> Rate {
+                rate_of(inputs)
+            }
+
+            fn process(
+                &mut self,
+                inputs: &[Input],
+                outputs: &mut [&mut [Sample]],
+                _sample_rate: f32,
+                _time_sample: usize,
+            ) {
+                simd_fill(outputs[0], |i| {
+                    let [$($arg),+]: [f32x8; _] =
+                        std::array::from_fn(|k| simd_input(&inputs[k], i));
+                    $body
+                });
+            }
+        }
+    };
+}
+
+//------------------------------------------------------------------------------
+// Arithmetic
+
+elementwise_ugen!(
+    /// Subtracts `in2` from `in1`.
+    UGSub, Sub, ["in1", "in2"], |[a, b]| a - b
+);
+
+elementwise_ugen!(
+    /// Divides `in1` by `in2`. Where `in2` is zero the output is zero.
+    UGDiv, Div, ["in1", "in2"], |[a, b]| b.simd_ne(f32x8::splat(0.0)) & (a / b)
+);
+
+elementwise_ugen!(
+    /// The floored remainder of `in1` divided by `in2`, with the sign of `in2`:
+    /// `-0.25 % 1` is `0.75`. Where `in2` is zero the output is zero.
+    UGMod, Mod, ["in1", "in2"], |[a, b]| {
+        b.simd_ne(f32x8::splat(0.0)) & (a - b * (a / b).floor())
+    }
+);
+
+elementwise_ugen!(
+    /// The negation of `in`.
+    UGNeg, Neg, ["in"], |[x]| -x
+);
+
+elementwise_ugen!(
+    /// The absolute value of `in`.
+    UGAbs, Abs, ["in"], |[x]| x.abs()
+);
+
+elementwise_ugen!(
+    /// The smaller of `in1` and `in2`.
+    UGMin, Min, ["in1", "in2"], |[a, b]| a.min(b)
+);
+
+elementwise_ugen!(
+    /// The larger of `in1` and `in2`.
+    UGMax, Max, ["in1", "in2"], |[a, b]| a.max(b)
+);
+
+elementwise_ugen!(
+    /// `in1` raised to the power `in2`. A negative base keeps its sign for an odd
+    /// integer exponent, so -2 to the power 3 is -8. Where the result is not a finite
+    /// number, such as a negative base with a fractional exponent, the output is zero.
+    UGPow, Pow, ["in1", "in2"], |[a, b]| simd_finite(simd_pow(a, b))
+);
+
+elementwise_ugen!(
+    /// e raised to the power `in`; zero where the result overflows.
+    UGExp, Exp, ["in"], |[x]| simd_finite(x.exp())
+);
+
+elementwise_ugen!(
+    /// The natural logarithm of `in`; zero where `in` is not above zero.
+    UGLog, Log, ["in"], |[x]| x.simd_gt(f32x8::splat(0.0)) & simd_finite(x.ln())
+);
+
+elementwise_ugen!(
+    /// The sign of `in`: -1, 0 or 1.
+    UGSign, Sign, ["in"], |[x]| {
+        let zero = f32x8::splat(0.0);
+        simd_bool(x.simd_gt(zero)) - simd_bool(x.simd_lt(zero))
+    }
+);
+
+//------------------------------------------------------------------------------
+// Comparison
+
+elementwise_ugen!(
+    /// 1 where `in1` is greater than `in2`, else 0.
+    UGGreater, Greater, ["in1", "in2"], |[a, b]| simd_bool(a.simd_gt(b))
+);
+
+elementwise_ugen!(
+    /// 1 where `in1` is less than `in2`, else 0.
+    UGLess, Less, ["in1", "in2"], |[a, b]| simd_bool(a.simd_lt(b))
+);
+
+elementwise_ugen!(
+    /// 1 where `in1` equals `in2`, else 0.
+    UGEqual, Equal, ["in1", "in2"], |[a, b]| simd_bool(a.simd_eq(b))
+);
+
+//------------------------------------------------------------------------------
+// Logic: gates are high above 0.5, and outputs are 1 or 0
+
+elementwise_ugen!(
+    /// 1 where both `in1` and `in2` are high, else 0.
+    UGAnd, And, ["in1", "in2"], |[a, b]| simd_bool(simd_gate(a) & simd_gate(b))
+);
+
+elementwise_ugen!(
+    /// 1 where `in1` or `in2` (or both) is high, else 0.
+    UGOr, Or, ["in1", "in2"], |[a, b]| simd_bool(simd_gate(a) | simd_gate(b))
+);
+
+elementwise_ugen!(
+    /// 1 where exactly one of `in1` and `in2` is high, else 0.
+    UGXor, Xor, ["in1", "in2"], |[a, b]| simd_bool(simd_gate(a) ^ simd_gate(b))
+);
+
+elementwise_ugen!(
+    /// 1 where `in` is low, else 0.
+    UGNot, Not, ["in"], |[x]| simd_bool(!simd_gate(x))
+);
+
+//------------------------------------------------------------------------------
+// UGClamp
+
+/// `in` limited to the range from `min` to `max` (by default -1 to 1).
+pub struct UGClamp;
+
+impl UGClamp {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for UGClamp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UGen for UGClamp {
+    fn type_name(&self) -> &'static str {
+        "UGClamp"
+    }
+
+    fn to_facade(&self) -> Option<UGFacade> {
+        Some(UGFacade::Clamp {})
+    }
+
+    fn input_names(&self) -> &[String] {
+        static NAMES: std::sync::OnceLock<Vec<String>> = std::sync::OnceLock::new();
+        NAMES.get_or_init(|| vec!["in".to_string(), "min".to_string(), "max".to_string()])
+    }
+
+    fn output_names(&self) -> &[String] {
+        static NAMES: std::sync::OnceLock<Vec<String>> = std::sync::OnceLock::new();
+        NAMES.get_or_init(|| vec!["out".to_string()])
+    }
+
+    fn default_input(&self, input_name: &str) -> Option<Sample> {
+        match input_name {
+            "min" => Some(-1.0),
+            "max" => Some(1.0),
+            _ => None,
+        }
+    }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
+
+    fn output_rate(&self, _output: usize, inputs: &[Input]) -> Rate {
+        rate_of(inputs)
+    }
+
+    fn process(
+        &mut self,
+        inputs: &[Input],
+        outputs: &mut [&mut [Sample]],
+        _sample_rate: f32,
+        _time_sample: usize,
+    ) {
+        let (input, min, max) = (&inputs[0], &inputs[1], &inputs[2]);
+        simd_fill(outputs[0], |i| {
+            simd_input(input, i)
+                .max(simd_input_or(min, i, -1.0))
+                .min(simd_input_or(max, i, 1.0))
+        });
+    }
+}
+
+//------------------------------------------------------------------------------
+// UGScale
+
+/// How `UGScale` maps between ranges.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    strum::EnumIter,
+    strum::Display,
+)]
+pub enum ModeScale {
+    /// Equal steps in the input are equal differences in the output.
+    Linear,
+    /// Equal steps in the input are equal ratios in the output, as for frequencies.
+    Exponential,
+}
+
+/// Maps `in` from the range `in_min` to `in_max` onto the range `out_min` to `out_max`
+/// (all 0 to 1 by default); values outside the input range are extrapolated.
+///
+/// In `Exponential` mode, `out_min` and `out_max` must be non-zero with the same sign;
+/// otherwise the output is zero. Where `in_min` equals `in_max`, the output is `out_min`.
+pub struct UGScale {
+    mode: ModeScale,
+}
+
+impl UGScale {
+    pub fn new(mode: ModeScale) -> Self {
+        Self { mode }
+    }
+}
+
+impl UGen for UGScale {
+    fn type_name(&self) -> &'static str {
+        "UGScale"
+    }
+
+    fn to_facade(&self) -> Option<UGFacade> {
+        Some(UGFacade::Scale { mode: self.mode })
+    }
+
+    fn describe_config(&self) -> Option<String> {
+        Some(format!("mode = {}", self.mode))
+    }
+
+    fn input_names(&self) -> &[String] {
+        static NAMES: std::sync::OnceLock<Vec<String>> = std::sync::OnceLock::new();
+        NAMES.get_or_init(|| {
+            vec![
+                "in".to_string(),
+                "in_min".to_string(),
+                "in_max".to_string(),
+                "out_min".to_string(),
+                "out_max".to_string(),
+            ]
+        })
+    }
+
+    fn output_names(&self) -> &[String] {
+        static NAMES: std::sync::OnceLock<Vec<String>> = std::sync::OnceLock::new();
+        NAMES.get_or_init(|| vec!["out".to_string()])
+    }
+
+    fn default_input(&self, input_name: &str) -> Option<Sample> {
+        match input_name {
+            "in_min" | "out_min" => Some(0.0),
+            "in_max" | "out_max" => Some(1.0),
+            _ => None,
+        }
+    }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
+
+    fn output_rate(&self, _output: usize, inputs: &[Input]) -> Rate {
+        rate_of(inputs)
+    }
+
+    fn process(
+        &mut self,
+        inputs: &[Input],
+        outputs: &mut [&mut [Sample]],
+        _sample_rate: f32,
+        _time_sample: usize,
+    ) {
+        let zero = f32x8::splat(0.0);
+        let mode = self.mode;
+        simd_fill(outputs[0], |i| {
+            let x = simd_input(&inputs[0], i);
+            let in_min = simd_input_or(&inputs[1], i, 0.0);
+            let in_max = simd_input_or(&inputs[2], i, 1.0);
+            let out_min = simd_input_or(&inputs[3], i, 0.0);
+            let out_max = simd_input_or(&inputs[4], i, 1.0);
+            // position in the input range, 0 where the range is empty
+            let t = simd_finite((x - in_min) / (in_max - in_min));
+            match mode {
+                ModeScale::Linear => out_min + t * (out_max - out_min),
+                ModeScale::Exponential => {
+                    let ratio = out_max / out_min;
+                    ratio.simd_gt(zero) & simd_finite(out_min * ratio.pow_f32x8(t))
+                }
+            }
+        });
+    }
+}
+
+//------------------------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GenGraph;
+    use crate::connect_many;
+    use crate::register_many;
+
+    /// Render `ugen` with its inputs, in order, connected to constants.
+    fn render(ugen: impl UGen + 'static, values: &[Sample]) -> Sample {
+        let mut g = GenGraph::new(8.0, 8);
+        let inputs = ugen.input_names().to_vec();
+        g.add_node("u", Box::new(ugen));
+        for (input, value) in inputs.iter().zip(values) {
+            let name = format!("c_{input}");
+            g.add_node(&name, Box::new(crate::UGConst::new(*value)));
+            g.connect(&format!("{name}.out"), &format!("u.{input}"));
+        }
+        g.process();
+        let out = g.get_output_by_label("u.out");
+        assert!(
+            out.iter().all(|v| v.to_bits() == out[0].to_bits()),
+            "{out:?}"
+        );
+        out[0]
+    }
+
+    //--------------------------------------------------------------------------
+    #[test]
+    fn test_sub_a() {
+        let mut g = GenGraph::new(120.0, 8);
+        register_many![g,
+            "c1" => 3,
+            "c2" => 5,
+            "s1" => UGSub::new(),
+        ];
+        connect_many![g,
+        "c1.out" -> "s1.in1",
+        "c2.out" -> "s1.in2",
+        ];
+        g.process();
+        assert_eq!(g.get_output_by_label("s1.out"), vec![-2.0; 8])
+    }
+
+    //--------------------------------------------------------------------------
+    #[test]
+    fn test_div_a() {
+        let mut g = GenGraph::new(120.0, 8);
+        register_many![g,
+            "c1" => 3,
+            "c2" => 4,
+            "c3" => 0,
+            "d1" => UGDiv::new(),
+            "d2" => UGDiv::new(),
+        ];
+        connect_many![g,
+        "c1.out" -> "d1.in1",
+        "c2.out" -> "d1.in2",
+        "c1.out" -> "d2.in1",
+        "c3.out" -> "d2.in2",
+        ];
+        g.process();
+        assert_eq!(g.get_output_by_label("d1.out"), vec![0.75; 8]);
+        // division by zero is zero
+        assert_eq!(g.get_output_by_label("d2.out"), vec![0.0; 8]);
+    }
+
+    //--------------------------------------------------------------------------
+    #[test]
+    fn test_mod_a() {
+        let mut g = GenGraph::new(120.0, 8);
+        register_many![g,
+            "c1" => -0.25,
+            "c2" => 1,
+            "c3" => 5.5,
+            "c4" => -2,
+            "m1" => UGMod::new(),
+            "m2" => UGMod::new(),
+        ];
+        connect_many![g,
+        "c1.out" -> "m1.in1",
+        "c2.out" -> "m1.in2",
+        "c3.out" -> "m2.in1",
+        "c4.out" -> "m2.in2",
+        ];
+        g.process();
+        assert_eq!(g.get_output_by_label("m1.out"), vec![0.75; 8]);
+        assert_eq!(g.get_output_by_label("m2.out"), vec![-0.5; 8]);
+    }
+
+    //--------------------------------------------------------------------------
+    #[test]
+    fn test_neg_a() {
+        let mut g = GenGraph::new(120.0, 8);
+        register_many![g,
+            "c1" => 2.5,
+            "n1" => UGNeg::new(),
+        ];
+        connect_many![g,
+        "c1.out" -> "n1.in",
+        ];
+        g.process();
+        assert_eq!(g.get_output_by_label("n1.out"), vec![-2.5; 8]);
+    }
+
+    //--------------------------------------------------------------------------
+    #[test]
+    fn test_arithmetic_a() {
+        assert_eq!(render(UGAbs::new(), &[-2.5]), 2.5);
+        assert_eq!(render(UGMin::new(), &[3.0, -1.0]), -1.0);
+        assert_eq!(render(UGMax::new(), &[3.0, -1.0]), 3.0);
+        assert_eq!(render(UGPow::new(), &[2.0, 10.0]), 1024.0);
+        assert_eq!(render(UGPow::new(), &[-8.0, 0.5]), 0.0);
+        assert_eq!(render(UGPow::new(), &[0.0, -1.0]), 0.0);
+        // a negative base with an integer exponent is negative for odd exponents
+        assert_eq!(render(UGPow::new(), &[-2.0, 3.0]), -8.0);
+        assert_eq!(render(UGPow::new(), &[-2.0, 2.0]), 4.0);
+        assert_eq!(render(UGPow::new(), &[-2.0, -1.0]), -0.5);
+        assert_eq!(render(UGPow::new(), &[-2.0, 0.0]), 1.0);
+        // a negative base with a fractional exponent is not real
+        assert_eq!(render(UGPow::new(), &[-8.0, 1.5]), 0.0);
+        assert_eq!(render(UGPow::new(), &[-8.0, -0.5]), 0.0);
+        assert!((render(UGExp::new(), &[1.0]) - std::f32::consts::E).abs() < 1e-6);
+        assert!((render(UGLog::new(), &[std::f32::consts::E]) - 1.0).abs() < 1e-6);
+        assert_eq!(render(UGLog::new(), &[0.0]), 0.0);
+        assert_eq!(render(UGLog::new(), &[-1.0]), 0.0);
+        for (x, sign) in [(-0.1, -1.0), (0.0, 0.0), (7.0, 1.0)] {
+            assert_eq!(render(UGSign::new(), &[x]), sign);
+        }
+    }
+
+    //--------------------------------------------------------------------------
+    #[test]
+    fn test_compare_a() {
+        for (a, b, greater, less, equal) in [
+            (1.0, 2.0, 0.0, 1.0, 0.0),
+            (2.0, 1.0, 1.0, 0.0, 0.0),
+            (1.5, 1.5, 0.0, 0.0, 1.0),
+        ] {
+            assert_eq!(render(UGGreater::new(), &[a, b]), greater);
+            assert_eq!(render(UGLess::new(), &[a, b]), less);
+            assert_eq!(render(UGEqual::new(), &[a, b]), equal);
+        }
+    }
+
+    //--------------------------------------------------------------------------
+    #[test]
+    fn test_logic_a() {
+        for (a, b, and, or, xor) in [
+            (0.0, 0.0, 0.0, 0.0, 0.0),
+            (1.0, 0.0, 0.0, 1.0, 1.0),
+            (0.5, 0.8, 0.0, 1.0, 1.0),
+            (0.9, 1.0, 1.0, 1.0, 0.0),
+        ] {
+            assert_eq!(render(UGAnd::new(), &[a, b]), and);
+            assert_eq!(render(UGOr::new(), &[a, b]), or);
+            assert_eq!(render(UGXor::new(), &[a, b]), xor);
+        }
+        assert_eq!(render(UGNot::new(), &[0.5]), 1.0);
+        assert_eq!(render(UGNot::new(), &[1.0]), 0.0);
+    }
+
+    //--------------------------------------------------------------------------
+    #[test]
+    fn test_clamp_a() {
+        assert_eq!(render(UGClamp::new(), &[5.0, 0.0, 2.0]), 2.0);
+        assert_eq!(render(UGClamp::new(), &[-5.0, 0.0, 2.0]), 0.0);
+        assert_eq!(render(UGClamp::new(), &[1.5, 0.0, 2.0]), 1.5);
+        // unconnected bounds default to -1 and 1
+        assert_eq!(render(UGClamp::new(), &[-3.0]), -1.0);
+        assert_eq!(render(UGClamp::new(), &[3.0]), 1.0);
+    }
+
+    //--------------------------------------------------------------------------
+    #[test]
+    fn test_scale_a() {
+        let linear = || UGScale::new(ModeScale::Linear);
+        assert_eq!(render(linear(), &[0.25]), 0.25);
+        assert_eq!(render(linear(), &[0.0, -1.0, 1.0, 100.0, 200.0]), 150.0);
+        // extrapolated beyond the input range
+        assert_eq!(render(linear(), &[2.0, 0.0, 1.0, 10.0, 20.0]), 30.0);
+        // an empty input range gives out_min
+        assert_eq!(render(linear(), &[3.0, 1.0, 1.0, 10.0, 20.0]), 10.0);
+
+        let exponential = || UGScale::new(ModeScale::Exponential);
+        for (x, hz) in [(0.0, 20.0), (0.5, 200.0), (1.0, 2000.0)] {
+            let out = render(exponential(), &[x, 0.0, 1.0, 20.0, 2000.0]);
+            assert!((out - hz).abs() < hz * 1e-5, "{out}");
+        }
+        // the output range cannot cross zero
+        assert_eq!(render(exponential(), &[0.5, 0.0, 1.0, -1.0, 1.0]), 0.0);
+        assert_eq!(render(exponential(), &[0.5]), 0.0);
+    }
+
+    //--------------------------------------------------------------------------
+    #[test]
+    fn test_scale_audio_rate_a() {
+        let mut g = GenGraph::new(8.0, 8);
+        register_many![g,
+            "lfo" => crate::UGSine::new(),
+            "s1" => UGScale::new(ModeScale::Linear),
+        ];
+        connect_many![g,
+        "lfo.wave" -> "s1.in",
+        ];
+        g.set_input_default("s1.in_min", -1.0);
+        g.set_input_default("s1.out_min", 100.0);
+        g.set_input_default("s1.out_max", 300.0);
+        g.process();
+        let wave = g.get_output_by_label("lfo.wave");
+        let scaled = g.get_output_by_label("s1.out");
+        for (w, s) in wave.iter().zip(scaled) {
+            assert!((s - (200.0 + 100.0 * w)).abs() < 1e-3, "{w} {s}");
+        }
+    }
+
+    //--------------------------------------------------------------------------
+    #[test]
+    fn test_chain_math_a() {
+        let chain = "0.5 -> Scale(mode=Exponential, @out_min=20, @out_max=2000) => s \
+                     -> Clamp(@max=100) => c | (s - 200) -> Abs() -> Greater(@in2=1) => g";
+        let mut g = crate::graph_from_chain_expression(chain, 8.0, 8).unwrap();
+        g.process();
+        assert_eq!(g.get_output_by_label("c.out"), vec![100.0; 8]);
+        assert_eq!(g.get_output_by_label("g.out"), vec![0.0; 8]);
+        let chain = g.to_chain().unwrap();
+        assert!(
+            chain.contains("Scale(mode=Exponential, @out_min=20, @out_max=2000) => s"),
+            "{chain}"
+        );
+    }
+}
@@ -0,0 +1,503 @@
+use crate::graph_facade::UGFacade;
+use crate::ugen_core::{Input, UGen};
+use crate::util::Sample;
+use crate::util::derive_seed;
+use crate::util::recycle;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+//------------------------------------------------------------------------------
+// UGPoly
+
+/// How `UGPoly` chooses the voice for a new trigger.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    strum::EnumIter,
+    strum::Display,
+)]
+pub enum ModeSteal {
+    /// A free voice if there is one, else the voice triggered longest ago.
+    Oldest,
+    /// The voice with the lowest output level in the last block.
+    Quietest,
+    /// Each voice in turn, whether or not it is still sounding.
+    RoundRobin,
+}
+
+/// Peak output level of the last block below which a voice is free.
+const FREE_LEVEL: Sample = 1e-4;
+
+/// Names of the first input that `UGPoly` accepts as the voices' trigger.
+const TRIGGER_INPUTS: [&str; 3] = ["trigger", "gate", "clock"];
+
+/// Plays overlapping notes or hits on several copies (voices) of a UGen and sums their
+/// outputs.
+///
+/// The voices' ports are reported as this UGen's ports. Unconnected inputs are passed
+/// through unconnected, so voices apply their own defaults.
+///
+/// Inputs:
+///   0 trigger – The voices' `trigger`, `gate` or `clock`. On each rising edge
+///               (≤ 0.5 → > 0.5) a voice is chosen with the `ModeSteal` policy and
+///               receives a one-sample trigger.
+///   1..       – The voices' other inputs, latched per voice at its trigger, so each
+///               voice keeps the pitch and parameters of its own note while later
+///               notes play on other voices.
+///
+/// Outputs:
+///   The voices' outputs, summed over all voices.
+pub struct UGPoly {
+    voices: Vec<Box<dyn UGen>>,
+    steal: ModeSteal,
+    // Last value of the trigger input, to detect rising edges
+    prev_trigger: Sample,
+    // Per voice: the time of its last trigger, `None` if never triggered
+    started: Vec<Option<usize>>,
+    // Per voice: the peak absolute output in the last block
+    levels: Vec<Sample>,
+    // The voice `ModeSteal::RoundRobin` uses next
+    next: usize,
+    // Per voice, per input: the value latched at its last trigger
+    held: Vec<Vec<Sample>>,
+    // Triggers in the current block, as (sample, voice)
+    events: Vec<(usize, usize)>,
+    // Per input: the block passed to the voice being processed, if it is triggered in the
+    // block
+    voice_inputs: Vec<Vec<Sample>>,
+    // Per output: the block rendered by the voice being processed
+    voice_outputs: Vec<Vec<Sample>>,
+    // Empty scratch tables for the voice inputs and outputs, kept to reuse their
+    // allocations
+    input_scratch: Vec<Input<'static>>,
+    output_scratch: Vec<&'static mut [Sample]>,
+}
+
+impl UGPoly {
+    /// Create from `voices`, identically configured instances of one UGen whose first
+    /// input is a `trigger`, `gate` or `clock`. Fails if there are no voices, the first
+    /// input is not a trigger, or the voices' ports differ.
+    pub fn new(voices: Vec<Box<dyn UGen>>, steal: ModeSteal) -> Result<Self, String> {
+        let Some(first) = voices.first() else {
+            return Err("Poly requires at least one voice".into());
+        };
+        let trigger = first.input_names().first();
+        if !trigger.is_some_and(|name| TRIGGER_INPUTS.contains(&name.as_str())) {
+            let found =
+                trigger.map_or("no inputs".to_string(), |name| format!("'{name}'"));
+            return Err(format!(
+                "Poly voices require a trigger, gate or clock as the first input, but {} has {found}",
+                first.type_name()
+            ));
+        }
+        if voices.iter().any(|v| {
+            v.input_names() != first.input_names()
+                || v.output_names() != first.output_names()
+        }) {
+            return Err("Poly voices must have the same inputs and outputs".into());
+        }
+        let n = voices.len();
+        let inputs = first.input_names().len();
+        let outputs = first.output_names().len();
+        Ok(Self {
+            steal,
+            prev_trigger: 0.0,
+            started: vec![None; n],
+            levels: vec![0.0; n],
+            next: 0,
+            held: vec![vec![0.0; inputs]; n],
+            events: Vec::new(),
+            voice_inputs: vec![Vec::new(); inputs],
+            voice_outputs: vec![Vec::new(); outputs],
+            input_scratch: Vec::with_capacity(inputs),
+            output_scratch: Vec::with_capacity(outputs),
+            voices,
+        })
+    }
+
+    /// Choose the voice for a trigger with the `steal` policy.
+    fn choose_voice(&mut self) -> usize {
+        let n = self.voices.len();
+        match self.steal {
+            ModeSteal::RoundRobin => {
+                let v = self.next;
+                self.next = (v + 1) % n;
+                v
+            }
+            ModeSteal::Oldest => (0..n)
+                .find(|&v| self.levels[v] < FREE_LEVEL)
+                .or_else(|| (0..n).min_by_key(|&v| self.started[v]))
+                .unwrap_or(0),
+            ModeSteal::Quietest => (0..n)
+                .min_by(|&a, &b| {
+                    self.levels[a]
+                        .total_cmp(&self.levels[b])
+                        .then(self.started[a].cmp(&self.started[b]))
+                })
+                .unwrap_or(0),
+        }
+    }
+}
+
+impl UGen for UGPoly {
+    fn type_name(&self) -> &'static str {
+        "UGPoly"
+    }
+
+    fn to_facade(&self) -> Option<UGFacade> {
+        Some(UGFacade::Poly {
+            voices: self.voices.len(),
+            steal: self.steal,
+            ugen: Box::new(self.voices[0].to_facade()?),
+        })
+    }
+
+    fn describe_config(&self) -> Option<String> {
+        let voice = &self.voices[0];
+        let inner = match voice.describe_config() {
+            Some(config) => format!("{} ({config})", voice.type_name()),
+            None => voice.type_name().to_string(),
+        };
+        Some(format!(
+            "voices = {}, steal = {}, {inner}",
+            self.voices.len(),
+            self.steal
+        ))
+    }
+
+    fn input_names(&self) -> &[String] {
+        self.voices[0].input_names()
+    }
+
+    fn output_names(&self) -> &[String] {
+        self.voices[0].output_names()
+    }
+
+    fn default_input(&self, input_name: &str) -> Option<Sample> {
+        self.voices[0].default_input(input_name)
+    }
+
+    // each voice draws its own sequence
+    fn set_graph_seed(&mut self, seed: u64) {
+        for (k, voice) in self.voices.iter_mut().enumerate() {
+            voice.set_graph_seed(derive_seed(seed, &k.to_string()));
+        }
+    }
+
+    fn save_state(&self) -> Option<Value> {
+        let voices: Vec<Option<Value>> =
+            self.voices.iter().map(|v| v.save_state()).collect();
+        Some(json!({
+            "voices": voices,
+            "prev_trigger": self.prev_trigger,
+            "started": self.started,
+            "levels": self.levels,
+            "next": self.next,
+            "held": self.held,
+        }))
+    }
+
+    fn load_state(&mut self, state: &Value) -> Result<(), String> {
+        fn field<T: serde::de::DeserializeOwned>(
+            state: &Value,
+            key: &str,
+        ) -> Result<T, String> {
+            serde_json::from_value(state[key].clone()).map_err(|e| e.to_string())
+        }
+        let voices: Vec<Value> = field(state, "voices")?;
+        let started: Vec<Option<usize>> = field(state, "started")?;
+        let levels: Vec<Sample> = field(state, "levels")?;
+        let held: Vec<Vec<Sample>> = field(state, "held")?;
+        let n = self.voices.len();
+        if voices.len() != n || started.len() != n || levels.len() != n || held.len() != n
+        {
+            return Err("Poly state does not match the number of voices".into());
+        }
+        for (voice, state) in self.voices.iter_mut().zip(&voices) {
+            match state {
+                Value::Null => {}
+                state => voice.load_state(state)?,
+            }
+        }
+        self.prev_trigger = field(state, "prev_trigger")?;
+        self.next = field(state, "next")?;
+        self.started = started;
+        self.levels = levels;
+        self.held = held;
+        Ok(())
+    }
+
+    fn process(
+        &mut self,
+        inputs: &[Input],
+        outputs: &mut [&mut [Sample]],
+        sample_rate: f32,
+        time_sample: usize,
+    ) {
+        let len = outputs.iter().map(|o| o.len()).max().unwrap_or(0);
+        let trigger = inputs.first().copied().unwrap_or_default();
+
+        self.events.clear();
+        for i in 0..len {
+            let trig = trigger.get(i).unwrap_or(0.0);
+            if trig > 0.5 && self.prev_trigger <= 0.5 {
+                let v = self.choose_voice();
+                self.started[v] = Some(time_sample + i);
+                // not free for the rest of the block
+                self.levels[v] = Sample::MAX;
+                self.events.push((i, v));
+            }
+            self.prev_trigger = trig;
+        }
+
+        for out in outputs.iter_mut() {
+            out.fill(0.0);
+        }
+        for v in 0..self.voices.len() {
+            let triggered = self.events.iter().any(|(_, w)| *w == v);
+            // render the inputs of a triggered voice, with the trigger and each latched
+            // value changing at its trigger samples
+            if triggered {
+                for (i, buf) in self.voice_inputs.iter_mut().enumerate() {
+                    let input = inputs.get(i).copied().unwrap_or_default();
+                    buf.resize(len, 0.0);
+                    let mut start = 0;
+                    for &(k, _) in self.events.iter().filter(|(_, w)| *w == v) {
+                        if i == 0 {
+                            buf[start..k].fill(0.0);
+                            buf[k] = trigger.get(k).unwrap_or(1.0);
+                            start = k + 1;
+                        } else {
+                            buf[start..k].fill(self.held[v][i]);
+                            self.held[v][i] = input.get(k).unwrap_or(0.0);
+                            start = k;
+                        }
+                    }
+                    let rest = if i == 0 { 0.0 } else { self.held[v][i] };
+                    buf[start..].fill(rest);
+                }
+            }
+            let mut voice_inputs: Vec<Input> =
+                recycle(std::mem::take(&mut self.input_scratch));
+            for (i, buf) in self.voice_inputs.iter().enumerate() {
+                let input = inputs.get(i).copied().unwrap_or_default();
+                voice_inputs.push(match input {
+                    Input::Unconnected => Input::Unconnected,
+                    _ if triggered => Input::Audio(buf),
+                    _ if i == 0 => Input::Control(0.0),
+                    // before its first trigger, a voice reads the inputs directly
+                    _ if self.started[v].is_none() => input,
+                    _ => Input::Control(self.held[v][i]),
+                });
+            }
+            let mut voice_outputs: Vec<&mut [Sample]> =
+                recycle(std::mem::take(&mut self.output_scratch));
+            for buf in self.voice_outputs.iter_mut() {
+                buf.resize(len, 0.0);
+                voice_outputs.push(buf.as_mut_slice());
+            }
+            self.voices[v].process(
+                &voice_inputs,
+                &mut voice_outputs,
+                sample_rate,
+                time_sample,
+            );
+            self.input_scratch = recycle(voice_inputs);
+            self.output_scratch = recycle(voice_outputs);
+
+            let mut level: Sample = 0.0;
+            for (out, buf) in outputs.iter_mut().zip(&self.voice_outputs) {
+                for (o, s) in out.iter_mut().zip(buf) {
+                    *o += s;
+                    level = level.max(s.abs());
+                }
+            }
+            self.levels[v] = level;
+        }
+    }
+}
+
+//------------------------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        GenGraph, UGClock, UGSelect, UGSnareDrum, UGString, UnitRate,
+        graph_from_chain_expression,
+    };
+
+    fn strings(n: usize, steal: ModeSteal) -> UGPoly {
+        let voices: Vec<Box<dyn UGen>> = (0..n)
+            .map(|_| Box::new(UGString::new(440.0, 0.99, Some(7))) as Box<dyn UGen>)
+            .collect();
+        UGPoly::new(voices, steal).unwrap()
+    }
+
+    #[test]
+    fn test_poly_reports_voice_ports() {
+        let poly = strings(2, ModeSteal::Oldest);
+        let voice = UGString::new(440.0, 0.99, None);
+        assert_eq!(poly.input_names(), voice.input_names());
+        assert_eq!(poly.output_names(), voice.output_names());
+        assert_eq!(poly.default_input("freq"), voice.default_input("freq"));
+    }
+
+    #[test]
+    fn test_poly_overlapping_notes_a() {
+        // two notes in one block ring on separate voices, each with its own frequency
+        let trigger = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0];
+        let freq = [
+            1000.0, 1000.0, 1000.0, 1000.0, 2000.0, 2000.0, 2000.0, 2000.0,
+        ];
+        let mut poly = strings(2, ModeSteal::Oldest);
+        let mut out = vec![0.0; 8];
+        let mut rendered = Vec::new();
+        for block in 0..4 {
+            let t = if block == 0 {
+                &trigger[..]
+            } else {
+                &[0.0; 8][..]
+            };
+            poly.process(
+                &[Input::Audio(t), Input::Audio(&freq)],
+                &mut [&mut out],
+                8000.0,
+                block * 8,
+            );
+            rendered.extend_from_slice(&out);
+        }
+        assert_eq!(poly.started, vec![Some(0), Some(4)]);
+
+        // the same notes on two separate strings
+        let mut expected = vec![0.0; 32];
+        for (start, hz) in [(0, 1000.0), (4, 2000.0)] {
+            let mut voice = UGString::new(440.0, 0.99, Some(7));
+            let mut t = [0.0; 32];
+            t[start] = 1.0;
+            for block in 0..4 {
+                let mut buf = vec![0.0; 8];
+                voice.process(
+                    &[
+                        Input::Audio(&t[block * 8..block * 8 + 8]),
+                        Input::Control(hz),
+                    ],
+                    &mut [&mut buf],
+                    8000.0,
+                    block * 8,
+                );
+                for (e, s) in expected[block * 8..].iter_mut().zip(&buf) {
+                    *e += s;
+                }
+            }
+        }
+        assert_eq!(rendered, expected);
+    }
+
+    #[test]
+    fn test_poly_choose_voice_a() {
+        let mut poly = strings(3, ModeSteal::Oldest);
+        poly.started = vec![Some(5), Some(2), Some(9)];
+        poly.levels = vec![0.5, 0.1, 0.3];
+        // no voice is free: the oldest is stolen
+        assert_eq!(poly.choose_voice(), 1);
+        poly.levels[2] = 0.0;
+        assert_eq!(poly.choose_voice(), 2);
+
+        poly.steal = ModeSteal::Quietest;
+        poly.levels = vec![0.5, 0.1, 0.3];
+        assert_eq!(poly.choose_voice(), 1);
+        poly.levels = vec![0.0, 0.5, 0.0];
+        assert_eq!(poly.choose_voice(), 0);
+
+        poly.steal = ModeSteal::RoundRobin;
+        let chosen: Vec<usize> = (0..4).map(|_| poly.choose_voice()).collect();
+        assert_eq!(chosen, vec![0, 1, 2, 0]);
+    }
+
+    #[test]
+    fn test_poly_snare_hits_ring_out_a() {
+        // a fast clock retriggers a mono snare, cutting each hit; with voices, hits
+        // overlap
+        fn render(voices: usize) -> Vec<Sample> {
+            let mut g = GenGraph::new(8000.0, 64);
+            g.add_node("clock", Box::new(UGClock::new(40.0, UnitRate::Hz)));
+            let snares: Vec<Box<dyn UGen>> = (0..voices)
+                .map(|_| Box::new(UGSnareDrum::new_seeded(Some(3))) as Box<dyn UGen>)
+                .collect();
+            g.add_node(
+                "sd",
+                Box::new(UGPoly::new(snares, ModeSteal::Oldest).unwrap()),
+            );
+            g.connect("clock.out", "sd.gate");
+            let mut out = Vec::new();
+            for _ in 0..16 {
+                g.process();
+                out.extend_from_slice(g.get_output_by_label("sd.out"));
+            }
+            out
+        }
+        let mono = render(1);
+        let poly = render(4);
+        // identical until the second hit
+        assert_eq!(mono[..200], poly[..200]);
+        assert_ne!(mono, poly);
+    }
+
+    #[test]
+    fn test_poly_save_load_state_a() {
+        fn graph() -> GenGraph {
+            let mut g = GenGraph::new(8000.0, 32);
+            g.add_node("clock", Box::new(UGClock::new(50.0, UnitRate::Hz)));
+            g.add_node(
+                "sel",
+                Box::new(UGSelect::new(
+                    vec![220.0, 330.0, 440.0],
+                    crate::ModeSelect::Cycle,
+                    None,
+                )),
+            );
+            g.add_node("p", Box::new(strings(3, ModeSteal::Quietest)));
+            g.connect("clock.out", "sel.trigger");
+            g.connect("clock.out", "p.trigger");
+            g.connect("sel.out", "p.freq");
+            g
+        }
+        let mut g1 = graph();
+        for _ in 0..9 {
+            g1.process();
+        }
+        let snapshot = g1.snapshot();
+        let mut g2 = graph();
+        g2.restore(&snapshot).unwrap();
+        for _ in 0..9 {
+            g1.process();
+            g2.process();
+            assert_eq!(
+                g1.get_output_by_label("p.out"),
+                g2.get_output_by_label("p.out")
+            );
+        }
+    }
+
+    #[test]
+    fn test_poly_invalid_voices() {
+        let err = UGPoly::new(Vec::new(), ModeSteal::Oldest).err().unwrap();
+        assert_eq!(err, "Poly requires at least one voice");
+        for (chain, expected) in [
+            ("Poly(voices=0) { String() }", "at least one voice"),
+            (
+                "Poly(voices=2) { Const(value=1) }",
+                "but UGConst has no inputs",
+            ),
+            ("Poly(voices=4) { Sine() }", "but UGSine has 'freq'"),
+        ] {
+            let err = graph_from_chain_expression(chain, 8.0, 8).err().unwrap();
+            assert!(err.contains(expected), "{err}");
+        }
+    }
+}
@@ -1,5 +1,9 @@
+use crate::Input;
 use crate::Sample;
 use crate::UGen;
+use crate::graph_facade::UGFacade;
+use crate::ugen_core::impl_serde_state;
+use serde::{Deserialize, Serialize};
 
 const DEFAULT_DECAY: f32 = 0.6;
 const DEFAULT_PRE_DELAY_MS: f32 = 20.0;
@@ -22,7 +26,7 @@ const ALLPASS_BASE_DELAYS_R: [usize; 2] = [579, 464];
 // Mix a small amount of each channel into the opposite tank to decorrelate tails.
 const CROSSFEED_GAIN: f32 = 0.2;
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 struct DelayLine {
     buffer: Vec<f32>,
     write_idx: usize,
@@ -58,7 +62,7 @@ impl DelayLine {
 ///
 /// This stage applies feedback around a delay line to create decaying echoes, with
 /// a one-pole damping filter in the feedback path to roll off high frequencies over time.
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 struct Comb {
     delay: DelayLine,
     damp_state: f32,
@@ -94,7 +98,7 @@ impl Comb {
 ///
 /// Unlike comb filters, allpass filters primarily smear phase/transients (diffusion)
 /// without building strong resonant peaks, which helps smooth the reverb tail texture.
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 struct AllPass {
     delay: DelayLine,
 }
@@ -126,6 +130,7 @@ fn damping_coeff(high_cut_hz: f32, sample_rate: f32) -> f32 {
 
 /// Stereo reverb UGen with controls for decay time, pre-delay, dry/wet mix,
 /// room size, diffusion, and damping (high-cut).
+#[derive(Serialize, Deserialize)]
 pub struct UGReverb {
     pre_l: DelayLine,
     pre_r: DelayLine,
@@ -202,6 +207,12 @@ impl UGen for UGReverb {
         "UGReverb"
     }
 
+    fn to_facade(&self) -> Option<UGFacade> {
+        Some(UGFacade::Reverb {})
+    }
+
+    impl_serde_state!();
+
     fn input_names(&self) -> &[String] {
         static NAMES: std::sync::OnceLock<Vec<String>> = std::sync::OnceLock::new();
         NAMES.get_or_init(|| {
@@ -238,19 +249,19 @@ impl UGen for UGReverb {
 
     fn process(
         &mut self,
-        inputs: &[&[Sample]],
+        inputs: &[Input],
         outputs: &mut [&mut [Sample]],
         sample_rate: f32,
         _time_sample: usize,
     ) {
-        let in_l = inputs.first().copied().unwrap_or(&[]);
-        let in_r = inputs.get(1).copied().unwrap_or(&[]);
-        let decay = inputs.get(2).copied().unwrap_or(&[]);
-        let pre_delay = inputs.get(3).copied().unwrap_or(&[]);
-        let mix = inputs.get(4).copied().unwrap_or(&[]);
-        let size = inputs.get(5).copied().unwrap_or(&[]);
-        let diffusion = inputs.get(6).copied().unwrap_or(&[]);
-        let damping = inputs.get(7).copied().unwrap_or(&[]);
+        let in_l = inputs.first().copied().unwrap_or_default();
+        let in_r = inputs.get(1).copied().unwrap_or_default();
+        let decay = inputs.get(2).copied().unwrap_or_default();
+        let pre_delay = inputs.get(3).copied().unwrap_or_default();
+        let mix = inputs.get(4).copied().unwrap_or_default();
+        let size = inputs.get(5).copied().unwrap_or_default();
+        let diffusion = inputs.get(6).copied().unwrap_or_default();
+        let damping = inputs.get(7).copied().unwrap_or_default();
 
         let (left, right) = outputs.split_at_mut(1);
         let out_l = &mut left[0];
@@ -259,27 +270,21 @@ impl UGen for UGReverb {
         let sr_ratio = sample_rate / REFERENCE_SAMPLE_RATE;
 
         for i in 0..out_l.len() {
-            let dry_l = in_l.get(i).copied().unwrap_or(0.0);
-            let dry_r = in_r.get(i).copied().unwrap_or(0.0);
+            let dry_l = in_l.get(i).unwrap_or(0.0);
+            let dry_r = in_r.get(i).unwrap_or(0.0);
 
-            let decay_v = decay
-                .get(i)
-                .copied()
-                .unwrap_or(DEFAULT_DECAY)
-                .clamp(0.0, 0.98);
+            let decay_v = decay.get(i).unwrap_or(DEFAULT_DECAY).clamp(0.0, 0.98);
             let pre_ms = pre_delay
                 .get(i)
-                .copied()
                 .unwrap_or(DEFAULT_PRE_DELAY_MS)
                 .clamp(0.0, MAX_PRE_DELAY_MS);
-            let mix_v = mix.get(i).copied().unwrap_or(DEFAULT_MIX).clamp(0.0, 1.0);
-            let size_v = size.get(i).copied().unwrap_or(DEFAULT_SIZE).clamp(0.5, 1.5);
+            let mix_v = mix.get(i).unwrap_or(DEFAULT_MIX).clamp(0.0, 1.0);
+            let size_v = size.get(i).unwrap_or(DEFAULT_SIZE).clamp(0.5, 1.5);
             let diffusion_v = diffusion
                 .get(i)
-                .copied()
                 .unwrap_or(DEFAULT_DIFFUSION)
                 .clamp(0.0, 0.95);
-            let damping_v = damping.get(i).copied().unwrap_or(DEFAULT_DAMPING_HZ);
+            let damping_v = damping.get(i).unwrap_or(DEFAULT_DAMPING_HZ);
 
             let pre_samp = ((pre_ms * sample_rate) / 1000.0).round() as usize;
             let (pdl, pdr) = if pre_samp == 0 {
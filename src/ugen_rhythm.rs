@@ -1,8 +1,13 @@
+use crate::Input;
 use crate::ModeSelect;
 use crate::UGSelect;
 use crate::UGen;
+use crate::graph_facade::UGFacade;
+use crate::ugen_core::impl_serde_state;
 use crate::util::Sample;
+use serde::{Deserialize, Serialize};
 
+#[derive(Serialize, Deserialize)]
 pub struct UGPulseSelect {
     pulse_count: usize,
     pulse_target: usize,
@@ -28,6 +33,20 @@ impl UGen for UGPulseSelect {
         "UGPulseSelect"
     }
 
+    fn to_facade(&self) -> Option<UGFacade> {
+        Some(UGFacade::PulseSelect {
+            duration_values: self.duration_select.values().to_vec(),
+            duration_mode: self.duration_select.mode(),
+            seed: self.duration_select.seed(),
+        })
+    }
+
+    impl_serde_state!();
+
+    fn set_graph_seed(&mut self, seed: u64) {
+        self.duration_select.set_graph_seed(seed);
+    }
+
     fn input_names(&self) -> &[String] {
         static NAMES: std::sync::OnceLock<Vec<String>> = std::sync::OnceLock::new();
         NAMES.get_or_init(|| vec!["clock".to_string(), "step".to_string()])
@@ -52,23 +71,23 @@ impl UGen for UGPulseSelect {
 
     fn process(
         &mut self,
-        inputs: &[&[Sample]],
+        inputs: &[Input],
         outputs: &mut [&mut [Sample]],
         sample_rate: f32,
         time_sample: usize,
     ) {
-        let clock = inputs.first().copied().unwrap_or(&[]);
-        let step = inputs.get(1).copied().unwrap_or(&[]);
+        let clock = inputs.first().copied().unwrap_or_default();
+        let step = inputs.get(1).copied().unwrap_or_default();
         let out = &mut outputs[0];
 
         for i in 0..out.len() {
-            let triggered = clock.get(i).copied().unwrap_or(0.0) > 0.5;
+            let triggered = clock.get(i).unwrap_or(0.0) > 0.5;
 
             if triggered {
                 if self.pulse_count == 0 {
                     out[i] = 1.0;
 
-                    let step_size = step.get(i).copied().unwrap_or(1.0).max(1.0).round();
+                    let step_size = step.get(i).unwrap_or(1.0).max(1.0).round();
                     self.pulse_target = self
                         .duration_select
                         .select_next(step_size, sample_rate, time_sample)
@@ -0,0 +1,441 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::graph_facade::UGFacade;
+use crate::ugen_core::{Input, UGen};
+use crate::util::Sample;
+
+//------------------------------------------------------------------------------
+// UGSample
+
+/// How `UGSample` reads between the frames of its file.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    strum::EnumIter,
+    strum::Display,
+)]
+pub enum ModeInterp {
+    /// Straight line between the two nearest frames.
+    Linear,
+    /// Catmull-Rom spline through the four nearest frames.
+    Cubic,
+}
+
+/// Plays a WAV file loaded at construction (a sampler).
+///
+/// Playback runs at `rate` times the file's own speed, corrected for the file's sample
+/// rate, and stops at the other end of the range from where it started. Output is silent
+/// before the first trigger and after playback stops.
+///
+/// Inputs:
+///   0 trigger – Rising edge (≤ 0.5 → > 0.5) restarts playback at `start`, or at `end`
+///               if `rate` is negative.  Default: 0.0
+///   1 rate    – Playback speed relative to the file; 2 is an octave up, negative
+///               plays backwards.  Default: 1.0
+///   2 start   – Start of the played range, as a fraction of the file length.
+///               Default: 0.0
+///   3 end     – End of the played range, as a fraction of the file length.
+///               Default: 1.0
+///   4 loop    – Above 0.5, playback wraps around at the end of the range instead of
+///               stopping.  Default: 0.0
+///
+/// Outputs:
+///   0 out – Audio output of a mono file; a file with more channels has one output
+///           per channel, `out1`, `out2`, and so on.
+pub struct UGSample {
+    // The file path, for `to_facade`; `None` for audio given as channels
+    path: Option<String>,
+    interp: ModeInterp,
+    // Frames per channel, and the sample rate they were recorded at
+    channels: Vec<Vec<Sample>>,
+    file_rate: f32,
+    input_names: Vec<String>,
+    output_names: Vec<String>,
+    // Read position in frames; `None` while stopped
+    pos: Option<f64>,
+    prev_trigger: Sample,
+}
+
+impl UGSample {
+    /// Load the WAV file at `path`: 16- or 24-bit integer or 32-bit float (as written by
+    /// `Recorder`), with any number of channels. Fails if the file cannot be read.
+    pub fn new(path: &str, interp: ModeInterp) -> Result<Self, String> {
+        let fail = |e: hound::Error| format!("Cannot read WAV file '{path}': {e}");
+        let mut reader = hound::WavReader::open(path).map_err(fail)?;
+        let spec = reader.spec();
+        let interleaved: Vec<Sample> = match spec.sample_format {
+            hound::SampleFormat::Float => reader
+                .samples::<f32>()
+                .collect::<Result<_, _>>()
+                .map_err(fail)?,
+            hound::SampleFormat::Int => {
+                let scale = 1.0 / (1_i64 << (spec.bits_per_sample - 1)) as Sample;
+                reader
+                    .samples::<i32>()
+                    .map(|s| s.map(|s| s as Sample * scale))
+                    .collect::<Result<_, _>>()
+                    .map_err(fail)?
+            }
+        };
+        let count = spec.channels as usize;
+        let channels = (0..count)
+            .map(|c| interleaved.iter().skip(c).step_by(count).copied().collect())
+            .collect();
+        let mut ugen = Self::try_from_channels(channels, spec.sample_rate as f32, interp)
+            .map_err(|e| format!("Cannot read WAV file '{path}': {e}"))?;
+        ugen.path = Some(path.to_string());
+        Ok(ugen)
+    }
+
+    /// Play audio given as frames per channel, recorded at `sample_rate`.
+    /// Panics if there are no channels or the channels differ in length; see
+    /// `try_from_channels`.
+    pub fn from_channels(
+        channels: Vec<Vec<Sample>>,
+        sample_rate: f32,
+        interp: ModeInterp,
+    ) -> Self {
+        Self::try_from_channels(channels, sample_rate, interp)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Like `from_channels`, but returns an error instead of panicking.
+    pub fn try_from_channels(
+        channels: Vec<Vec<Sample>>,
+        sample_rate: f32,
+        interp: ModeInterp,
+    ) -> Result<Self, String> {
+        if channels.is_empty() {
+            return Err("Sample requires at least one channel".to_string());
+        }
+        if channels.iter().any(|c| c.len() != channels[0].len()) {
+            return Err("Sample channels must have the same length".to_string());
+        }
+        let output_names = if channels.len() == 1 {
+            vec!["out".to_string()]
+        } else {
+            (1..channels.len() + 1).map(|i| format!("out{i}")).collect()
+        };
+        Ok(Self {
+            path: None,
+            interp,
+            channels,
+            file_rate: sample_rate,
+            input_names: ["trigger", "rate", "start", "end", "loop"]
+                .map(String::from)
+                .to_vec(),
+            output_names,
+            pos: None,
+            prev_trigger: 0.0,
+        })
+    }
+
+    /// The sample of `channel` at frame `pos`, interpolated; zero outside the file.
+    fn read(&self, channel: usize, pos: f64) -> Sample {
+        let frames = &self.channels[channel];
+        let frame = |i: i64| -> Sample {
+            usize::try_from(i)
+                .ok()
+                .and_then(|i| frames.get(i))
+                .copied()
+                .unwrap_or(0.0)
+        };
+        let i = pos.floor() as i64;
+        let t = (pos - pos.floor()) as Sample;
+        match self.interp {
+            ModeInterp::Linear => {
+                let (x0, x1) = (frame(i), frame(i + 1));
+                x0 + t * (x1 - x0)
+            }
+            ModeInterp::Cubic => {
+                let (xm, x0, x1, x2) =
+                    (frame(i - 1), frame(i), frame(i + 1), frame(i + 2));
+                let a = -0.5 * xm + 1.5 * x0 - 1.5 * x1 + 0.5 * x2;
+                let b = xm - 2.5 * x0 + 2.0 * x1 - 0.5 * x2;
+                let c = 0.5 * (x1 - xm);
+                ((a * t + b) * t + c) * t + x0
+            }
+        }
+    }
+}
+
+impl UGen for UGSample {
+    fn type_name(&self) -> &'static str {
+        "UGSample"
+    }
+
+    fn to_facade(&self) -> Option<UGFacade> {
+        Some(UGFacade::Sample {
+            path: self.path.clone()?,
+            interp: self.interp,
+        })
+    }
+
+    fn describe_config(&self) -> Option<String> {
+        let path = self.path.as_deref().unwrap_or("none");
+        Some(format!(
+            "path = {path}, channels = {}, frames = {}, interp = {}",
+            self.channels.len(),
+            self.channels[0].len(),
+            self.interp
+        ))
+    }
+
+    fn input_names(&self) -> &[String] {
+        &self.input_names
+    }
+
+    fn output_names(&self) -> &[String] {
+        &self.output_names
+    }
+
+    fn default_input(&self, input_name: &str) -> Option<Sample> {
+        match input_name {
+            "trigger" | "start" | "loop" => Some(0.0),
+            "rate" | "end" => Some(1.0),
+            _ => None,
+        }
+    }
+
+    fn save_state(&self) -> Option<Value> {
+        Some(json!({
+            "pos": self.pos,
+            "prev_trigger": self.prev_trigger,
+        }))
+    }
+
+    fn load_state(&mut self, state: &Value) -> Result<(), String> {
+        self.pos =
+            serde_json::from_value(state["pos"].clone()).map_err(|e| e.to_string())?;
+        self.prev_trigger = serde_json::from_value(state["prev_trigger"].clone())
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    fn process(
+        &mut self,
+        inputs: &[Input],
+        outputs: &mut [&mut [Sample]],
+        sample_rate: f32,
+        _time_sample: usize,
+    ) {
+        let trigger = inputs.first().copied().unwrap_or_default();
+        let rate_in = inputs.get(1).copied().unwrap_or_default();
+        let start_in = inputs.get(2).copied().unwrap_or_default();
+        let end_in = inputs.get(3).copied().unwrap_or_default();
+        let loop_in = inputs.get(4).copied().unwrap_or_default();
+        let len = self.channels[0].len() as f64;
+        let step = (self.file_rate / sample_rate) as f64;
+
+        for i in 0..outputs[0].len() {
+            let trig = trigger.value_or(i, 0.0);
+            let rate = rate_in.value_or(i, 1.0) as f64;
+            let start = start_in.value_or(i, 0.0).clamp(0.0, 1.0) as f64 * len;
+            let end = end_in.value_or(i, 1.0).clamp(0.0, 1.0) as f64 * len;
+            let (lo, hi) = (start.min(end), start.max(end));
+
+            let triggered = trig > 0.5 && self.prev_trigger <= 0.5;
+            self.prev_trigger = trig;
+            if triggered {
+                self.pos = Some(if rate < 0.0 { (hi - 1.0).max(lo) } else { lo });
+            }
+
+            let pos = match self.pos {
+                Some(pos) if loop_in.value_or(i, 0.0) > 0.5 && hi > lo => {
+                    Some(lo + (pos - lo).rem_euclid(hi - lo))
+                }
+                Some(pos) if pos >= lo && pos < hi => Some(pos),
+                _ => None,
+            };
+            self.pos = pos;
+            match pos {
+                Some(pos) => {
+                    for (c, out) in outputs.iter_mut().enumerate() {
+                        out[i] = self.read(c, pos);
+                    }
+                    self.pos = Some(pos + rate * step);
+                }
+                None => {
+                    for out in outputs.iter_mut() {
+                        out[i] = 0.0;
+                    }
+                }
+            }
+        }
+    }
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GenGraph, Recorder, WavFormat, graph_from_chain_expression};
+    use tempfile::NamedTempFile;
+
+    fn run(ug: &mut UGSample, inputs: &[Input], n: usize) -> Vec<Vec<Sample>> {
+        let mut out = vec![vec![0.0; n]; ug.output_names().len()];
+        let mut slices: Vec<&mut [Sample]> = out.iter_mut().map(|o| &mut o[..]).collect();
+        ug.process(inputs, &mut slices, 8.0, 0);
+        out
+    }
+
+    fn ramp() -> UGSample {
+        let frames = (1..=4).map(|i| i as Sample).collect();
+        UGSample::from_channels(vec![frames], 8.0, ModeInterp::Linear)
+    }
+
+    #[test]
+    fn test_sample_ports() {
+        let ug = ramp();
+        assert_eq!(
+            ug.input_names(),
+            ["trigger", "rate", "start", "end", "loop"]
+        );
+        assert_eq!(ug.output_names(), ["out"]);
+        let ug = UGSample::from_channels(vec![vec![0.0]; 3], 8.0, ModeInterp::Cubic);
+        assert_eq!(ug.output_names(), ["out1", "out2", "out3"]);
+        assert_eq!(ug.to_facade().map(|_| ()), None);
+    }
+
+    #[test]
+    fn test_sample_trigger_a() {
+        let mut ug = ramp();
+        let trigger = [0.0, 1.0, 1.0, 1.0, 1.0, 1.0, 0.0, 1.0];
+        let out = run(&mut ug, &[Input::Audio(&trigger)], 8);
+        assert_eq!(out[0], vec![0.0, 1.0, 2.0, 3.0, 4.0, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_sample_rate_a() {
+        // half speed interpolates; a 16 Hz file plays at double speed at 8 Hz
+        let mut ug = ramp();
+        let inputs = [Input::Control(1.0), Input::Control(0.5)];
+        let out = run(&mut ug, &inputs, 8);
+        assert_eq!(out[0], vec![1.0, 1.5, 2.0, 2.5, 3.0, 3.5, 4.0, 2.0]);
+
+        let frames = (1..=4).map(|i| i as Sample).collect();
+        let mut ug = UGSample::from_channels(vec![frames], 16.0, ModeInterp::Linear);
+        let out = run(&mut ug, &[Input::Control(1.0)], 4);
+        assert_eq!(out[0], vec![1.0, 3.0, 0.0, 0.0]);
+
+        // reverse from the end
+        let mut ug = ramp();
+        let inputs = [Input::Control(1.0), Input::Control(-1.0)];
+        let out = run(&mut ug, &inputs, 6);
+        assert_eq!(out[0], vec![4.0, 3.0, 2.0, 1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_sample_start_end_loop_a() {
+        let mut ug = ramp();
+        let inputs = [
+            Input::Control(1.0),
+            Input::Unconnected,
+            Input::Control(0.25),
+            Input::Control(0.75),
+            Input::Control(1.0),
+        ];
+        let out = run(&mut ug, &inputs, 6);
+        assert_eq!(out[0], vec![2.0, 3.0, 2.0, 3.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn test_sample_cubic_a() {
+        // the spline passes through the frames, and is exact for a straight line
+        let frames = (0..8).map(|i| i as Sample).collect();
+        let mut ug = UGSample::from_channels(vec![frames], 8.0, ModeInterp::Cubic);
+        let inputs = [Input::Control(1.0), Input::Control(0.5)];
+        let out = run(&mut ug, &inputs, 8);
+        assert_eq!(out[0][2..6], [1.0, 1.5, 2.0, 2.5]);
+    }
+
+    #[test]
+    fn test_sample_wav_a() {
+        // every format `Recorder` writes, with two channels
+        let mut g = GenGraph::new(8.0, 8);
+        g.add_node("a", Box::new(crate::UGConst::new(0.5)));
+        g.add_node("b", Box::new(crate::UGConst::new(-0.25)));
+        let labels = Some(vec!["a.out".to_string(), "b.out".to_string()]);
+        let r = Recorder::from_samples(g, labels, 8);
+        for format in [WavFormat::Int16, WavFormat::Int24, WavFormat::Float32] {
+            let tmp = NamedTempFile::new().unwrap();
+            r.to_wav(tmp.path(), format).unwrap();
+            let path = tmp.path().to_str().unwrap();
+            let mut ug = UGSample::new(path, ModeInterp::Linear).unwrap();
+            assert_eq!(ug.output_names(), ["out1", "out2"]);
+            let out = run(&mut ug, &[Input::Control(1.0)], 8);
+            assert_eq!(out[0], vec![0.5; 8]);
+            assert_eq!(out[1], vec![-0.25; 8]);
+        }
+        let err = UGSample::new("missing.wav", ModeInterp::Linear)
+            .err()
+            .unwrap();
+        assert!(err.starts_with("Cannot read WAV file 'missing.wav'"));
+    }
+
+    #[test]
+    fn test_sample_chain_a() {
+        let mut g = GenGraph::new(8.0, 8);
+        g.add_node("a", Box::new(crate::UGConst::new(0.5)));
+        let r = Recorder::from_samples(g, Some(vec!["a.out".to_string()]), 4);
+        let tmp = NamedTempFile::new().unwrap();
+        r.to_wav(tmp.path(), WavFormat::Float32).unwrap();
+        let path = tmp.path().to_str().unwrap();
+
+        let chain = format!(r#"Sample(path="{path}", @trigger=1) => s"#);
+        let mut g = graph_from_chain_expression(&chain, 8.0, 8).unwrap();
+        g.process();
+        assert_eq!(
+            g.get_output_by_label("s.out"),
+            [0.5, 0.5, 0.5, 0.5, 0.0, 0.0, 0.0, 0.0]
+        );
+        let expected =
+            format!(r#"Sample(interp=Linear, path="{path}", @trigger=1) => s"#);
+        assert_eq!(g.to_chain().unwrap(), expected);
+
+        let err = graph_from_chain_expression("Sample(path=missing.wav)", 8.0, 8);
+        assert!(
+            err.err()
+                .unwrap()
+                .contains("Cannot read WAV file 'missing.wav'")
+        );
+    }
+
+    #[test]
+    fn test_sample_invalid_channels() {
+        let err = UGSample::try_from_channels(Vec::new(), 8.0, ModeInterp::Linear);
+        assert_eq!(err.err().unwrap(), "Sample requires at least one channel");
+        let channels = vec![vec![0.0; 4], vec![0.0; 3]];
+        let err = UGSample::try_from_channels(channels, 8.0, ModeInterp::Linear);
+        assert_eq!(
+            err.err().unwrap(),
+            "Sample channels must have the same length"
+        );
+
+        // a file declaring zero channels is an error, not a panic
+        let tmp = NamedTempFile::new().unwrap();
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 8,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(tmp.path(), spec).unwrap();
+        writer.write_sample(0_i16).unwrap();
+        writer.finalize().unwrap();
+        let mut bytes = std::fs::read(tmp.path()).unwrap();
+        bytes[22..24].copy_from_slice(&0_u16.to_le_bytes());
+        std::fs::write(tmp.path(), bytes).unwrap();
+        let path = tmp.path().to_str().unwrap();
+        let chain = format!(r#"Sample(path="{path}")"#);
+        let err = graph_from_chain_expression(&chain, 8.0, 8).err().unwrap();
+        assert!(err.contains("Cannot read WAV file"), "{err}");
+    }
+}
@@ -1,8 +1,12 @@
+use crate::Input;
 use crate::UGen;
+use crate::graph_facade::UGFacade;
+use crate::ugen_core::impl_serde_state;
 use crate::util::Sample;
 
 use rand::seq::SliceRandom;
-use rand::{Rng, SeedableRng, rngs::StdRng};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
 use serde::{Deserialize, Serialize};
 
 //------------------------------------------------------------------------------
@@ -15,20 +19,21 @@ pub enum ModeSelect {
     Walk,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct UGSelect {
     values: Vec<Sample>,
     mode: ModeSelect,
     index: usize,
     shuffle_remaining: Vec<usize>,
-    rng: StdRng,
+    rng: ChaCha12Rng,
+    seed: Option<u64>,
 }
 
 impl UGSelect {
     pub fn new(values: Vec<Sample>, mode: ModeSelect, seed: Option<u64>) -> Self {
         let rng = match seed {
-            Some(s) => StdRng::seed_from_u64(s),
-            None => StdRng::from_rng(&mut rand::rng()),
+            Some(s) => ChaCha12Rng::seed_from_u64(s),
+            None => ChaCha12Rng::from_rng(&mut rand::rng()),
         };
         let len = values.len().max(1);
         UGSelect {
@@ -37,9 +42,22 @@ impl UGSelect {
             index: len - 1, // not optimal
             shuffle_remaining: Vec::new(),
             rng,
+            seed,
         }
     }
 
+    pub(crate) fn values(&self) -> &[Sample] {
+        &self.values
+    }
+
+    pub(crate) fn mode(&self) -> ModeSelect {
+        self.mode
+    }
+
+    pub(crate) fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
     /// Alternate interface to select a single value.
     pub fn select_next(
         &mut self,
@@ -47,10 +65,8 @@ impl UGSelect {
         sample_rate: f32,
         time_sample: usize,
     ) -> Sample {
-        let trigger = [1.0];
-        let step = [step];
         let mut out = [0.0];
-        let inputs = [&trigger[..], &step[..]];
+        let inputs = [Input::Control(1.0), Input::Control(step)];
         let mut outputs = [&mut out[..]];
 
         self.process(&inputs, &mut outputs, sample_rate, time_sample);
@@ -63,6 +79,22 @@ impl UGen for UGSelect {
         "UGSelect"
     }
 
+    fn to_facade(&self) -> Option<UGFacade> {
+        Some(UGFacade::Select {
+            values: self.values.clone(),
+            mode: self.mode,
+            seed: self.seed,
+        })
+    }
+
+    impl_serde_state!();
+
+    fn set_graph_seed(&mut self, seed: u64) {
+        if self.seed.is_none() {
+            self.rng = ChaCha12Rng::seed_from_u64(seed);
+        }
+    }
+
     fn input_names(&self) -> &[String] {
         static NAMES: std::sync::OnceLock<Vec<String>> = std::sync::OnceLock::new();
         NAMES.get_or_init(|| vec!["trigger".to_string(), "step".to_string()])
@@ -87,13 +119,13 @@ impl UGen for UGSelect {
 
     fn process(
         &mut self,
-        inputs: &[&[Sample]],
+        inputs: &[Input],
         outputs: &mut [&mut [Sample]],
         _sample_rate: f32,
         _time_sample: usize,
     ) {
-        let trigger = inputs.first().copied().unwrap_or(&[]);
-        let step = inputs.get(1).copied().unwrap_or(&[]);
+        let trigger = inputs.first().copied().unwrap_or_default();
+        let step = inputs.get(1).copied().unwrap_or_default();
         let out = &mut outputs[0];
 
         let n = self.values.len();
@@ -105,9 +137,8 @@ impl UGen for UGSelect {
         }
 
         for i in 0..out.len() {
-            if trigger.get(i).copied().unwrap_or(0.0) > 0.5 {
-                let step_size =
-                    step.get(i).copied().unwrap_or(1.0).round().max(1.0) as usize;
+            if trigger.get(i).unwrap_or(0.0) > 0.5 {
+                let step_size = step.get(i).unwrap_or(1.0).round().max(1.0) as usize;
 
                 match self.mode {
                     ModeSelect::Cycle => {
@@ -119,7 +150,7 @@ impl UGen for UGSelect {
                     ModeSelect::Shuffle => {
                         for _ in 0..step_size {
                             if self.shuffle_remaining.is_empty() {
-                                self.shuffle_remaining = (0..n).collect();
+                                self.shuffle_remaining.extend(0..n);
                                 self.shuffle_remaining.shuffle(&mut self.rng);
                             }
                             self.index = self.shuffle_remaining.pop().unwrap();
@@ -488,4 +519,27 @@ step ←= 1.000
             ]
         );
     }
+
+    #[test]
+    fn test_select_save_load_state_a() {
+        let trigger = [1.0; 8];
+        let mut out = [0.0; 8];
+        let mut a =
+            UGSelect::new(vec![1.0, 2.0, 3.0, 4.0, 5.0], ModeSelect::Shuffle, Some(9));
+        a.process(&[Input::Audio(&trigger)], &mut [&mut out], 8.0, 0);
+        let state = a.save_state().unwrap();
+
+        // a different seed, replaced by the saved RNG and shuffle state
+        let mut b =
+            UGSelect::new(vec![1.0, 2.0, 3.0, 4.0, 5.0], ModeSelect::Shuffle, Some(1));
+        b.load_state(&state).unwrap();
+        let mut out_a = [0.0; 8];
+        let mut out_b = [0.0; 8];
+        for t in 1..4 {
+            a.process(&[Input::Audio(&trigger)], &mut [&mut out_a], 8.0, t * 8);
+            b.process(&[Input::Audio(&trigger)], &mut [&mut out_b], 8.0, t * 8);
+            assert_eq!(out_a, out_b);
+        }
+        assert!(b.load_state(&serde_json::json!({"index": 0})).is_err());
+    }
 }
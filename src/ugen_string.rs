@@ -1,7 +1,11 @@
-use rand::rngs::StdRng;
 use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
 
+use crate::Input;
 use crate::UGen;
+use crate::graph_facade::UGFacade;
+use crate::ugen_core::impl_serde_state;
 use crate::util::Sample;
 
 /// Karplus-Strong string synthesis UGen.
@@ -25,15 +29,17 @@ use crate::util::Sample;
 ///
 /// Outputs:
 ///   0 out – Audio output signal.
+#[derive(Serialize, Deserialize)]
 pub struct UGString {
-    /// Delay-line ring buffer. Grown lazily as needed.
+    /// Delay-line ring buffer. Grown as needed, within capacity reserved on the first
+    /// block.
     buffer: Vec<Sample>,
     /// Active delay-line length in samples (= round(sample_rate / freq)).
     delay_len: usize,
     /// Current read/write head position within the ring buffer.
     read_pos: usize,
     /// Random-number generator used to seed the delay line on each trigger.
-    rng: StdRng,
+    rng: ChaCha12Rng,
     /// Optional seed stored for `describe_config`.
     seed: Option<u64>,
     /// Default frequency (Hz); also reported by `default_input`.
@@ -58,7 +64,7 @@ impl UGString {
             buffer: Vec::new(),
             delay_len: 0,
             read_pos: 0,
-            rng: StdRng::seed_from_u64(actual_seed),
+            rng: ChaCha12Rng::seed_from_u64(actual_seed),
             seed,
             default_freq: freq,
             default_damping: damping,
@@ -72,6 +78,22 @@ impl UGen for UGString {
         "UGString"
     }
 
+    fn to_facade(&self) -> Option<UGFacade> {
+        Some(UGFacade::String {
+            freq: self.default_freq,
+            damping: self.default_damping,
+            seed: self.seed,
+        })
+    }
+
+    impl_serde_state!();
+
+    fn set_graph_seed(&mut self, seed: u64) {
+        if self.seed.is_none() {
+            self.rng = ChaCha12Rng::seed_from_u64(seed);
+        }
+    }
+
     fn input_names(&self) -> &[String] {
         static NAMES: std::sync::OnceLock<Vec<String>> = std::sync::OnceLock::new();
         NAMES.get_or_init(|| {
@@ -107,26 +129,28 @@ impl UGen for UGString {
 
     fn process(
         &mut self,
-        inputs: &[&[Sample]],
+        inputs: &[Input],
         outputs: &mut [&mut [Sample]],
         sample_rate: f32,
         _time_sample: usize,
     ) {
-        let trigger = inputs.first().copied().unwrap_or(&[]);
-        let freq_in = inputs.get(1).copied().unwrap_or(&[]);
-        let damping_in = inputs.get(2).copied().unwrap_or(&[]);
+        let trigger = inputs.first().copied().unwrap_or_default();
+        let freq_in = inputs.get(1).copied().unwrap_or_default();
+        let damping_in = inputs.get(2).copied().unwrap_or_default();
         let out = &mut outputs[0];
 
+        // reserve the longest delay line (at the 1 Hz minimum frequency), so that
+        // triggers do not allocate
+        let max_len = sample_rate.round() as usize;
+        if self.buffer.capacity() < max_len {
+            self.buffer.reserve_exact(max_len - self.buffer.len());
+        }
+
         for i in 0..out.len() {
-            let trig = trigger.get(i).copied().unwrap_or(0.0);
-            let freq = freq_in
-                .get(i)
-                .copied()
-                .unwrap_or(self.default_freq)
-                .max(1.0);
+            let trig = trigger.get(i).unwrap_or(0.0);
+            let freq = freq_in.get(i).unwrap_or(self.default_freq).max(1.0);
             let damping = damping_in
                 .get(i)
-                .copied()
                 .unwrap_or(self.default_damping)
                 .clamp(0.0, 1.0);
 
@@ -177,7 +201,7 @@ mod tests {
     fn test_silence_before_trigger() {
         let mut ug = UGString::new(440.0, 0.996, Some(0));
         let trigger = vec![0.0_f32; 8];
-        let inputs: Vec<&[f32]> = vec![&trigger];
+        let inputs = vec![Input::Audio(&trigger)];
         let mut buf = vec![0.0_f32; 8];
         let mut outputs: Vec<&mut [f32]> = vec![&mut buf];
         ug.process(&inputs, &mut outputs, 44100.0, 0);
@@ -193,7 +217,7 @@ mod tests {
         let mut ug = UGString::new(440.0, 0.996, Some(42));
         // First sample is the trigger, the rest are silent.
         let trigger = vec![1.0_f32, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0];
-        let inputs: Vec<&[f32]> = vec![&trigger];
+        let inputs = vec![Input::Audio(&trigger)];
         let mut buf = vec![0.0_f32; 8];
         let mut outputs: Vec<&mut [f32]> = vec![&mut buf];
         ug.process(&inputs, &mut outputs, 44100.0, 0);
@@ -211,7 +235,7 @@ mod tests {
 
         let run = |seed: u64| {
             let mut ug = UGString::new(440.0, 0.996, Some(seed));
-            let inputs: Vec<&[f32]> = vec![&trigger];
+            let inputs = vec![Input::Audio(&trigger)];
             let mut buf = vec![0.0_f32; 8];
             let mut outputs: Vec<&mut [f32]> = vec![&mut buf];
             ug.process(&inputs, &mut outputs, 44100.0, 0);
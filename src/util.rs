@@ -4,9 +4,37 @@ use std::str::FromStr;
 //------------------------------------------------------------------------------
 pub type Sample = f32;
 
-pub(crate) fn split_name(s: &str) -> (&str, &str) {
+/// Split a `name.port` label at the last '.'; returns `None` if there is no '.'.
+pub(crate) fn split_name(s: &str) -> Option<(&str, &str)> {
     s.rsplit_once('.')
-        .unwrap_or_else(|| panic!("Expected 'name.port', got: '{s}'"))
+}
+
+/// Clear `v` and reuse its allocation for an element type of the same size and alignment.
+///
+/// Typically the element is the same type with a different lifetime, so per-block scratch
+/// `Vec`s of borrowed slices can be kept between blocks without allocating.
+pub(crate) fn recycle<T, U>(mut v: Vec<T>) -> Vec<U> {
+    const {
+        assert!(size_of::<T>() == size_of::<U>() && align_of::<T>() == align_of::<U>());
+    }
+    v.clear();
+    let mut v = std::mem::ManuallyDrop::new(v);
+    // SAFETY: the Vec is empty and its allocation has the layout of `capacity` elements
+    // of `U`
+    unsafe { Vec::from_raw_parts(v.as_mut_ptr().cast::<U>(), 0, v.capacity()) }
+}
+
+/// Derive a seed from `seed` and `key` (such as a node name) that is stable across
+/// platforms and releases: an FNV-1a hash of `key` mixed into `seed` with the SplitMix64
+/// finalizer.
+pub(crate) fn derive_seed(seed: u64, key: &str) -> u64 {
+    let hash = key.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |h, b| {
+        (h ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+    });
+    let mut z = (seed ^ hash).wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
 }
 
 //------------------------------------------------------------------------------
@@ -0,0 +1,131 @@
+//! `GenGraph::process` does not allocate on one thread once the graph has been processed.
+//! This runs as its own test binary because it replaces the global allocator.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use ampullator::{
+    GenGraph, GraphEvent, ModeSelect, OscWave, Sample, UGAsHz, UGBassDrum, UGClock,
+    UGEnvAR, UGLowPass, UGMult, UGOsc, UGOversample, UGPulseSelect, UGReverb, UGSelect,
+    UGSine, UGString, UGSum, UGWhite, UnitRate, register_many,
+};
+
+/// Counts allocations on every thread while `COUNTING` is set.
+struct CountingAlloc;
+
+static COUNTING: AtomicBool = AtomicBool::new(false);
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+fn count_allocation() {
+    if COUNTING.load(Ordering::Relaxed) {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+// SAFETY: every method forwards to `System` unchanged, so this allocator upholds the
+// `GlobalAlloc` contract exactly when `System` does
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count_allocation();
+        // SAFETY: the caller's guarantees for `layout` are those `System.alloc` needs
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        count_allocation();
+        // SAFETY: as for `alloc`
+        unsafe { System.alloc_zeroed(layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count_allocation();
+        // SAFETY: `ptr` was allocated by `System` with `layout`, as every allocation
+        // here is
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // SAFETY: as for `realloc`
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAlloc = CountingAlloc;
+
+/// The number of allocations made while `f` runs, on any thread.
+///
+/// This binary has a single test, so no other test allocates meanwhile.
+fn allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    COUNTING.store(true, Ordering::Relaxed);
+    f();
+    COUNTING.store(false, Ordering::Relaxed);
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+/// A graph covering the UGens with scratch buffers, feedback and scheduled events.
+fn graph() -> GenGraph {
+    let mut g = GenGraph::new(8000.0, 64);
+    register_many![g,
+        "clock" => UGClock::new(30.0, UnitRate::Hz),
+        "note" => 57,
+        "hz" => UGAsHz::new(UnitRate::Midi),
+        "sine" => UGSine::new(),
+        "sel" => UGSelect::new(vec![55.0, 110.0, 82.5], ModeSelect::Shuffle, Some(1)),
+        "str" => UGString::new(110.0, 0.99, Some(2)),
+        "noise" => UGWhite::new(Some(3)),
+        "env" => UGEnvAR::new(),
+        "pulse" => UGPulseSelect::new(vec![1.0, 2.0], ModeSelect::Random, Some(4)),
+        "bd" => UGBassDrum::new(),
+        "mix" => UGSum::new(5),
+        "amp" => UGMult::new(2),
+        "lpf" => UGLowPass::new(12.0),
+        "os" => UGOversample::new(Box::new(UGOsc::new(OscWave::Saw)), 2).unwrap(),
+        "verb" => UGReverb::new(),
+    ];
+    g.connect("note.out", "hz.in");
+    g.connect("hz.out", "sine.freq");
+    g.connect("clock.out", "sel.trigger");
+    g.connect("clock.out", "str.trigger");
+    g.connect("sel.out", "str.freq");
+    g.connect("clock.out", "env.trigger");
+    g.connect("clock.out", "pulse.clock");
+    g.connect("pulse.out", "bd.gate");
+    g.connect("sel.out", "os.freq");
+    g.connect("sine.wave", "mix.in1");
+    g.connect("str.out", "mix.in2");
+    g.connect("noise.out", "amp.in1");
+    g.connect("env.out", "amp.in2");
+    g.connect("amp.out", "mix.in3");
+    g.connect("os.wave", "mix.in4");
+    g.connect_feedback("verb.out_l", "mix.in5");
+    g.connect("mix.out", "lpf.in");
+    g.connect("bd.out", "verb.in_r");
+    g.connect("lpf.out", "verb.in_l");
+    g.set_input_default("lpf.cutoff", 2000.0);
+    g.schedule("lpf.cutoff", 64 * 20, GraphEvent::Override(500.0));
+    g.schedule("note.out", 64 * 30, GraphEvent::Override(60.0));
+
+    g
+}
+
+#[test]
+fn test_process_does_not_allocate_a() {
+    let mut g = graph();
+    // warm-up: the first blocks resolve the execution order and size scratch buffers
+    for _ in 0..8 {
+        g.process();
+    }
+    let n = allocations(|| {
+        for _ in 0..64 {
+            g.process();
+        }
+    });
+    assert_eq!(n, 0);
+    assert!(
+        g.get_output_by_label("verb.out_l")
+            .iter()
+            .any(|v| *v != 0.0)
+    );
+}